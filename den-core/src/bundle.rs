@@ -0,0 +1,74 @@
+use std::{collections::HashMap, path::Path};
+
+use den_transpiler_swc::{BundleModule, EasySwcTranspilerError, SourceMap};
+pub use den_transpiler_swc::{BundleOptions, MinifyOptions};
+use derive_more::{Display, Error, From};
+
+use crate::{
+    config::Config,
+    engine::{transpiler, EngineError},
+    graph::ModuleGraph,
+};
+
+#[derive(Display, From, Error, Debug)]
+pub enum BundleError {
+    #[from]
+    Engine(EngineError),
+    #[from]
+    Transpiler(EasySwcTranspilerError),
+    #[display("cannot bundle {_0}: {_1}")]
+    Module(String, #[error(not(source))] String),
+}
+
+/// The output of `bundle`, along with how many modules went into it
+pub struct Bundle {
+    pub code:       String,
+    pub source_map: Option<SourceMap>,
+    pub modules:    usize,
+}
+
+/// Bundle `entry` and the local and remote modules it imports into a single
+/// ES module, `den:` modules stay imports
+pub async fn bundle(
+    config: &Config,
+    entry: &Path,
+    options: &BundleOptions,
+) -> Result<Bundle, BundleError> {
+    let graph = ModuleGraph::build(config, entry).await?;
+
+    let mut modules = vec![];
+    for (name, module) in graph.modules {
+        if let Some(error) = module.error {
+            return Err(BundleError::Module(name, error));
+        }
+        let Some((source, syntax)) = module.source else {
+            continue;
+        };
+
+        let mut dependencies = HashMap::new();
+        for dependency in module.dependencies {
+            match (dependency.resolved, dependency.error) {
+                (Some(resolved), _) => {
+                    dependencies.insert(dependency.specifier, resolved);
+                }
+                (None, error) => {
+                    return Err(BundleError::Module(name, error.unwrap_or_default()));
+                }
+            }
+        }
+        modules.push(BundleModule {
+            name,
+            source,
+            syntax,
+            dependencies,
+        });
+    }
+
+    let (code, source_map) =
+        tokio::task::block_in_place(|| transpiler(config).bundle(&graph.root, &modules, options))?;
+    Ok(Bundle {
+        code,
+        source_map,
+        modules: modules.len(),
+    })
+}
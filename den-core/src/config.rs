@@ -0,0 +1,547 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use derive_more::{Display, Error, From};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+/// The name of the project config file looked up from the working directory
+pub const CONFIG_FILE_NAME: &str = "den.json";
+/// The name of the TypeScript config compiler options are also read from
+pub const TSCONFIG_FILE_NAME: &str = "tsconfig.json";
+
+/// A `den.json` project config
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    pub compiler_options: CompilerOptions,
+    /// Path to an import map, relative to the config file
+    pub import_map:       Option<PathBuf>,
+    pub permissions:      Permissions,
+    pub tasks:            BTreeMap<String, Task>,
+    pub lint:             LintConfig,
+    pub test:             FileSet,
+    /// The directory the config was loaded from, relative paths in the config
+    /// are resolved against it
+    #[serde(skip)]
+    pub dir:              PathBuf,
+}
+
+/// Options of the transpiler, named like the ones of TypeScript. Those
+/// missing are taken from the nearest `tsconfig.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompilerOptions {
+    pub jsx:                         Option<Jsx>,
+    /// The function JSX elements are turned into, e.g. `h`
+    pub jsx_factory:                 Option<String>,
+    /// The component JSX fragments are turned into, e.g. `Fragment`
+    pub jsx_fragment_factory:        Option<String>,
+    /// The module the automatic JSX runtime is imported from
+    pub jsx_import_source:           Option<String>,
+    /// Legacy TypeScript decorators instead of the TC39 ones
+    pub experimental_decorators:     Option<bool>,
+    /// Emit the types of decorated declarations along with legacy decorators
+    pub emit_decorator_metadata:     Option<bool>,
+    /// Define class fields rather than assigning them, the default
+    pub use_define_for_class_fields: Option<bool>,
+    /// Keep imports that are only used as types unless marked with `type`
+    pub verbatim_module_syntax:      Option<bool>,
+    /// The directory `paths` are relative to
+    pub base_url:                    Option<PathBuf>,
+    /// Aliases of module specifiers, a `*` in a pattern stands for anything
+    /// and is substituted in the targets, which are tried in order
+    pub paths:                       Option<BTreeMap<String, Vec<String>>>,
+    /// Where the config defining `paths` lives, they are relative to it
+    /// without a `baseUrl`
+    #[serde(skip)]
+    pub paths_dir:                   PathBuf,
+}
+
+/// How JSX is compiled, named like the `jsx` option of TypeScript
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub enum Jsx {
+    /// Calls to `jsxFactory`, the default
+    #[serde(rename = "react")]
+    React,
+    /// Calls to the automatic runtime of `jsxImportSource`
+    #[serde(rename = "react-jsx")]
+    ReactJsx,
+    /// The automatic runtime with the source location of each element
+    #[serde(rename = "react-jsxdev")]
+    ReactJsxDev,
+    /// `preserve` and `react-native` leave JSX to another tool, den compiles
+    /// it like `react`
+    #[serde(other)]
+    Other,
+}
+
+impl CompilerOptions {
+    /// Resolve the paths of options loaded from a config in `dir`
+    fn relative_to(mut self, dir: &Path) -> Self {
+        self.base_url = self.base_url.map(|x| dir.join(x));
+        self.paths_dir = dir.to_path_buf();
+        self
+    }
+
+    /// Take the options missing from these ones from `base`
+    fn or(self, base: Self) -> Self {
+        let (paths, paths_dir) = match self.paths {
+            Some(paths) => (Some(paths), self.paths_dir),
+            None => (base.paths, base.paths_dir),
+        };
+        Self {
+            jsx: self.jsx.or(base.jsx),
+            jsx_factory: self.jsx_factory.or(base.jsx_factory),
+            jsx_fragment_factory: self.jsx_fragment_factory.or(base.jsx_fragment_factory),
+            jsx_import_source: self.jsx_import_source.or(base.jsx_import_source),
+            experimental_decorators: self
+                .experimental_decorators
+                .or(base.experimental_decorators),
+            emit_decorator_metadata: self
+                .emit_decorator_metadata
+                .or(base.emit_decorator_metadata),
+            use_define_for_class_fields: self
+                .use_define_for_class_fields
+                .or(base.use_define_for_class_fields),
+            verbatim_module_syntax: self.verbatim_module_syntax.or(base.verbatim_module_syntax),
+            base_url: self.base_url.or(base.base_url),
+            paths,
+            paths_dir,
+        }
+    }
+
+    /// The directory the targets of `paths` are relative to
+    pub fn paths_base(&self) -> &Path {
+        self.base_url.as_deref().unwrap_or(&self.paths_dir)
+    }
+}
+
+/// A `tsconfig.json`, only the parts den understands
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct TsConfig {
+    /// Configs this one overrides, later ones override earlier ones
+    extends:          Option<Extends>,
+    compiler_options: CompilerOptions,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Extends {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl TsConfig {
+    /// The compiler options of the config at `path` and of the ones it
+    /// extends. `seen` holds the configs being loaded, to break cycles
+    fn compiler_options(
+        path: &Path,
+        seen: &mut Vec<PathBuf>,
+    ) -> Result<CompilerOptions, ConfigError> {
+        if seen.iter().any(|x| x == path) {
+            return Err(ConfigError::Extends(path.to_path_buf()));
+        }
+        seen.push(path.to_path_buf());
+
+        let source =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        let config: TsConfig = serde_json::from_str(&strip_jsonc(&source))
+            .map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        let extends = match config.extends {
+            Some(Extends::One(x)) => vec![x],
+            Some(Extends::Many(x)) => x,
+            None => vec![],
+        };
+        let mut options = config.compiler_options.relative_to(dir);
+        for base in extends.iter().rev() {
+            let base = find_extended(dir, base).ok_or_else(|| {
+                ConfigError::Io(
+                    dir.join(base),
+                    io::Error::new(io::ErrorKind::NotFound, "extended config not found"),
+                )
+            })?;
+            options = options.or(Self::compiler_options(&base, seen)?);
+        }
+
+        seen.pop();
+        Ok(options)
+    }
+}
+
+/// The config `extends` refers to from a config in `dir`, either a path or
+/// a config of a package in `node_modules`
+fn find_extended(dir: &Path, extends: &str) -> Option<PathBuf> {
+    let candidates = |path: PathBuf| {
+        let mut json = path.clone().into_os_string();
+        json.push(".json");
+        [path.clone(), json.into(), path.join(TSCONFIG_FILE_NAME)]
+    };
+    let is_path =
+        extends.starts_with("./") || extends.starts_with("../") || Path::new(extends).is_absolute();
+    if is_path {
+        return candidates(dir.join(extends))
+            .into_iter()
+            .find(|x| x.is_file());
+    }
+
+    dir.ancestors()
+        .flat_map(|x| candidates(x.join("node_modules").join(extends)))
+        .find(|x| x.is_file())
+}
+
+/// Blank out the comments and drop the trailing commas TypeScript allows in
+/// its configs
+fn strip_jsonc(source: &str) -> String {
+    let mut stripped = String::new();
+    json_comments::StripComments::new(source.as_bytes())
+        .read_to_string(&mut stripped)
+        .expect("stripping comments from a string cannot fail");
+
+    let mut output = String::with_capacity(stripped.len());
+    let mut chars = stripped.chars().peekable();
+    let (mut in_string, mut escaped) = (false, false);
+    while let Some(c) = chars.next() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let rest = chars.clone().find(|x| !x.is_whitespace());
+            if matches!(rest, Some('}' | ']')) {
+                continue;
+            }
+        }
+        output.push(c);
+    }
+    output
+}
+
+/// What scripts are allowed to reach
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Permissions {
+    /// URL prefixes remote modules may be imported from, anything if absent
+    pub net:      Option<Vec<String>>,
+    /// URL prefixes remote modules may never be imported from
+    pub deny_net: Vec<String>,
+}
+
+/// A task is either a plain command or a definition with more details
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Task {
+    Command(String),
+    Definition(TaskDefinition),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TaskDefinition {
+    /// A command line for the built-in shell
+    pub command:      Option<String>,
+    /// A script run with den, as an alternative to `command`
+    pub script:       Option<PathBuf>,
+    pub description:  Option<String>,
+    /// Tasks to run before this one, in order
+    pub dependencies: Vec<TaskDependency>,
+    pub env:          BTreeMap<String, String>,
+    /// The directory to run in, relative to the config file, which is also
+    /// the default
+    pub cwd:          Option<PathBuf>,
+}
+
+/// A single task, or a group of tasks that run in parallel
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TaskDependency {
+    Task(String),
+    Parallel(Vec<String>),
+}
+
+impl TaskDependency {
+    pub fn names(&self) -> &[String] {
+        match self {
+            TaskDependency::Task(name) => std::slice::from_ref(name),
+            TaskDependency::Parallel(names) => names,
+        }
+    }
+}
+
+impl Task {
+    pub fn definition(&self) -> TaskDefinition {
+        match self {
+            Task::Command(command) => {
+                TaskDefinition {
+                    command: Some(command.clone()),
+                    ..Default::default()
+                }
+            }
+            Task::Definition(definition) => definition.clone(),
+        }
+    }
+}
+
+/// What `den lint` checks and with which rules
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    #[serde(flatten)]
+    pub files: FileSet,
+    pub rules: LintRules,
+}
+
+/// The lint rules to run, every built-in one unless `include` lists some,
+/// without the `exclude`d ones
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LintRules {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+/// Globs of files a tool works on, relative to the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FileSet {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl FileSet {
+    /// Every file under `root` matching the included globs, or
+    /// `default_include` if there are none, and none of the excluded ones.
+    /// Hidden directories and `node_modules` are skipped
+    pub fn files(
+        &self,
+        root: &Path,
+        default_include: &[&str],
+    ) -> Result<Vec<PathBuf>, ConfigError> {
+        let include = if self.include.is_empty() {
+            build_glob_set(default_include)?
+        } else {
+            build_glob_set(&self.include)?
+        };
+        let exclude = build_glob_set(&self.exclude)?;
+
+        let root = if root.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            root
+        };
+        let mut files: Vec<PathBuf> = WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|x| {
+                x.depth() == 0 || {
+                    let name = x.file_name().to_string_lossy();
+                    !name.starts_with('.') && name != "node_modules"
+                }
+            })
+            .filter_map(Result::ok)
+            .filter(|x| x.file_type().is_file())
+            .filter_map(|x| {
+                let path = x.path().strip_prefix(root).ok()?;
+                (include.is_match(path) && !exclude.is_match(path)).then(|| x.into_path())
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
+
+fn build_glob_set<S: AsRef<str>>(globs: &[S]) -> Result<GlobSet, ConfigError> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob.as_ref())?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Where den keeps its caches, `DEN_DIR` if set
+pub fn cache_dir() -> PathBuf {
+    std::env::var_os("DEN_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::cache_dir().map(|x| x.join("den")))
+        .unwrap_or_else(|| std::env::temp_dir().join("den"))
+}
+
+/// Where den keeps what should outlive the cache, like the REPL history,
+/// `DEN_DIR` if set
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("DEN_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::data_dir().map(|x| x.join("den")))
+        .unwrap_or_else(|| std::env::temp_dir().join("den"))
+}
+
+#[derive(Display, From, Error, Debug)]
+pub enum ConfigError {
+    #[display("cannot read {}: {_1}", _0.display())]
+    Io(PathBuf, io::Error),
+    #[display("invalid config {}: {_1}", _0.display())]
+    Parse(PathBuf, serde_json::Error),
+    #[display("{} extends itself", _0.display())]
+    Extends(#[error(not(source))] PathBuf),
+    #[from]
+    Glob(globset::Error),
+}
+
+impl Config {
+    /// Load a config file
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let source = std::fs::read(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        let mut config: Config = serde_json::from_slice(&source)
+            .map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        config.dir = path
+            .parent()
+            .filter(|x| !x.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        config.compiler_options = config.compiler_options.relative_to(&config.dir);
+        let dir = config.dir.clone();
+        config.merge_tsconfig(&dir)?;
+        Ok(config)
+    }
+
+    /// Fill in the compiler options missing from this config with the ones
+    /// of the nearest `tsconfig.json` from `dir`, if any
+    pub fn merge_tsconfig(&mut self, dir: &Path) -> Result<(), ConfigError> {
+        let Some(path) = dir
+            .ancestors()
+            .map(|x| x.join(TSCONFIG_FILE_NAME))
+            .find(|x| x.is_file())
+        else {
+            return Ok(());
+        };
+        let options = TsConfig::compiler_options(&path, &mut vec![])?;
+        self.compiler_options = std::mem::take(&mut self.compiler_options).or(options);
+        Ok(())
+    }
+
+    /// Look for a config file in `dir` and then in each of its ancestors,
+    /// returns the path of the first one found
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|x| x.join(CONFIG_FILE_NAME))
+            .find(|x| x.is_file())
+    }
+
+    /// Load the config that applies to `dir`, if any
+    pub fn discover(dir: &Path) -> Result<Option<(PathBuf, Self)>, ConfigError> {
+        Self::find(dir)
+            .map(|path| Self::load(&path).map(|config| (path, config)))
+            .transpose()
+    }
+
+    /// Resolve a path from the config against the directory it lives in
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        self.dir.join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Project;
+
+    #[test]
+    fn strips_comments_and_trailing_commas() {
+        let source = concat!(
+            "{\n",
+            "  // a comment\n",
+            "  \"a\": \"// not a comment, \\\" ,}\", /* another */\n",
+            "  \"b\": [1, 2,],\n",
+            "}\n",
+        );
+        let value: serde_json::Value = serde_json::from_str(&strip_jsonc(source)).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "a": "// not a comment, \" ,}", "b": [1, 2] })
+        );
+    }
+
+    #[test]
+    fn merges_extended_tsconfigs() {
+        let dir = Project::new(
+            "extends",
+            &[
+                (
+                    "den.json",
+                    r#"{ "compilerOptions": { "jsxFactory": "h" } }"#,
+                ),
+                (
+                    "tsconfig.json",
+                    concat!(
+                        "{\n",
+                        "  // Later configs win\n",
+                        "  \"extends\": [\"@scope/base\", \"./config/strict\"],\n",
+                        "  \"compilerOptions\": { \"jsxFactory\": \"ignored\", },\n",
+                        "}\n",
+                    ),
+                ),
+                (
+                    "node_modules/@scope/base/tsconfig.json",
+                    r#"{ "compilerOptions": { "jsxImportSource": "preact", "experimentalDecorators": true } }"#,
+                ),
+                (
+                    "config/strict.json",
+                    r#"{ "compilerOptions": { "experimentalDecorators": false, "baseUrl": "../src", "paths": { "@/*": ["*"] } } }"#,
+                ),
+            ],
+        );
+        let options = Config::load(&dir.join("den.json"))
+            .unwrap()
+            .compiler_options;
+
+        assert_eq!(options.jsx_factory.as_deref(), Some("h"));
+        assert_eq!(options.jsx_import_source.as_deref(), Some("preact"));
+        assert_eq!(options.experimental_decorators, Some(false));
+        assert_eq!(options.paths_base(), dir.join("config/../src"));
+        assert_eq!(
+            options.paths,
+            Some(BTreeMap::from([("@/*".to_string(), vec!["*".to_string()])]))
+        );
+    }
+
+    #[test]
+    fn paths_are_relative_to_their_config_without_a_base_url() {
+        let dir = Project::new(
+            "paths-dir",
+            &[
+                ("tsconfig.json", r#"{ "extends": "./base/tsconfig.json" }"#),
+                (
+                    "base/tsconfig.json",
+                    r#"{ "compilerOptions": { "paths": { "lib": ["./lib.ts"] } } }"#,
+                ),
+            ],
+        );
+        let mut config = Config::default();
+        config.merge_tsconfig(&dir).unwrap();
+        assert_eq!(config.compiler_options.paths_base(), dir.join("base"));
+    }
+
+    #[test]
+    fn rejects_extends_cycles() {
+        let dir = Project::new(
+            "cycle",
+            &[
+                ("tsconfig.json", r#"{ "extends": "./a" }"#),
+                ("a.json", r#"{ "extends": "./b.json" }"#),
+                ("b.json", r#"{ "extends": "./a.json" }"#),
+            ],
+        );
+        let merged = Config::default().merge_tsconfig(&dir);
+        assert!(
+            matches!(&merged, Err(ConfigError::Extends(path)) if path.ends_with("a.json")),
+            "{merged:?}"
+        );
+    }
+}
@@ -1,122 +1,118 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeSet,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
+#[cfg(feature = "transpile")]
+use den_transpiler_swc::{
+    get_best_transpiling, infer_transpile_syntax_by_extension, EasySwcTranspiler,
+    EasySwcTranspilerError, IsModule, JsxRuntime, MinifyOptions, SourceMap, Syntax,
+    TranspilerOptions,
+};
 use derive_more::{Debug, Display, Error, From};
 use rquickjs::{
     async_with,
     context::EvalOptions,
-    loader::{BuiltinLoader, BuiltinResolver, FileResolver, ModuleLoader},
-    AsyncContext, AsyncRuntime, FromJs, Module, Object, Promise,
+    loader::{BuiltinLoader, ModuleLoader, Resolver},
+    AsyncContext, AsyncRuntime, Ctx, FromJs, Module, Object, Promise, Value,
 };
 use tokio_util::sync::CancellationToken;
-#[cfg(feature = "transpile")]
-use {
-    den_transpiler_swc::{
-        get_best_transpiling, infer_transpile_syntax_by_extension, EasySwcTranspiler,
-        EasySwcTranspilerError, IsModule, SourceMap, Syntax,
+
+use crate::{
+    config::{Config, ConfigError},
+    loader::{
+        cache::ModuleCache, http::HttpLoader, mmap_script::MmapScriptLoader, payload::PayloadLoader,
+    },
+    resolver::{
+        builtin::BuiltinResolver, file::FileResolver, http::HttpResolver,
+        import_map::ImportMapResolver, paths::PathsResolver, payload::PayloadResolver,
+        ModuleResolver,
     },
-    std::sync::Arc,
+    standalone::Payload,
 };
-
+#[cfg(feature = "transpile")]
 use crate::{
-    loader::{http::HttpLoader, mmap_script::MmapScriptLoader},
-    resolver::http::HttpResolver,
+    graph::ModuleGraph,
+    transpile_cache::{CachedTranspiler, FileStamp},
 };
 
 #[derive(Clone)]
 pub struct Engine {
     #[cfg(feature = "transpile")]
-    pub transpiler: Arc<EasySwcTranspiler>,
-    pub runtime:    AsyncRuntime,
-    pub context:    AsyncContext,
-    pub stop_token: CancellationToken,
+    pub transpiler:  CachedTranspiler,
+    pub runtime:     AsyncRuntime,
+    pub context:     AsyncContext,
+    pub stop_token:  CancellationToken,
+    // Checked by the interrupt handler next to `stop_token`, so that a single
+    // evaluation can be aborted without shutting down the whole engine
+    interrupt_token: Arc<Mutex<CancellationToken>>,
+    loaded_modules:  Arc<Mutex<BTreeSet<PathBuf>>>,
+    // Filled by `prefetch` and drained by the loaders
+    module_cache:    ModuleCache,
+    // `Den.args` of every context
+    args:            Arc<Mutex<Vec<String>>>,
+    #[cfg(feature = "transpile")]
+    config:          Arc<Config>,
+    // Compiled programs already have every module they import at hand
+    #[cfg(feature = "transpile")]
+    standalone:      bool,
 }
 
 #[allow(dead_code)]
 impl Engine {
     pub async fn new() -> Engine {
+        Self::with_config(&Config::default())
+            .await
+            .expect("the default config is always valid")
+    }
+
+    /// Create an engine with the compiler options, import map and permissions
+    /// of a project config
+    pub async fn with_config(config: &Config) -> Result<Engine, EngineError> {
+        Self::build(config, None).await
+    }
+
+    /// Create an engine that runs the modules of a compiled program, with the
+    /// permissions it was compiled with
+    pub async fn standalone(payload: Payload) -> Result<Engine, EngineError> {
+        let config = Config {
+            permissions: payload.permissions.clone(),
+            ..Default::default()
+        };
+        Self::build(&config, Some(Arc::new(payload))).await
+    }
+
+    async fn build(config: &Config, payload: Option<Arc<Payload>>) -> Result<Engine, EngineError> {
         #[cfg(feature = "transpile")]
-        let transpiler = Arc::new(EasySwcTranspiler::default());
+        let transpiler = cached_transpiler(config);
+
+        let loaded_modules = Arc::new(Mutex::new(BTreeSet::new()));
+        let module_cache = ModuleCache::default();
 
         let runtime = AsyncRuntime::new().unwrap();
         runtime.set_max_stack_size(0).await;
 
         {
-            let resolver = (
-                {
-                    #[allow(unused_mut)]
-                    let mut resolver = BuiltinResolver::default();
-
-                    #[cfg(feature = "stdlib-core")]
-                    {
-                        resolver = resolver.with_module("den:core");
-                    }
-                    #[cfg(feature = "stdlib-console")]
-                    {
-                        resolver = resolver.with_module("den:console");
-                    }
-                    #[cfg(feature = "stdlib-networking")]
-                    {
-                        resolver = resolver.with_module("den:networking");
-                    }
-                    #[cfg(feature = "stdlib-text")]
-                    {
-                        resolver = resolver.with_module("den:text");
-                    }
-                    #[cfg(feature = "stdlib-timer")]
-                    {
-                        resolver = resolver.with_module("den:timer");
-                    }
-                    #[cfg(feature = "stdlib-fs")]
-                    {
-                        resolver = resolver.with_module("den:fs");
-                    }
-                    #[cfg(feature = "stdlib-sqlite")]
-                    {
-                        resolver = resolver.with_module("den:sqlite");
-                    }
-                    #[cfg(feature = "stdlib-whatwg-fetch")]
-                    {
-                        resolver = resolver.with_module("den:whatcg-fetch");
-                    }
-                    #[cfg(feature = "stdlib-crypto")]
-                    {
-                        resolver = resolver.with_module("den:crypto");
-                    }
-                    #[cfg(feature = "wasm")]
-                    {
-                        resolver = resolver.with_module("den:wasm");
-                    }
-                    resolver
-                },
-                HttpResolver::default(),
+            let resolver = (PayloadResolver::new(payload.clone()), resolver(config)?);
+            let loader = (
+                PayloadLoader::new(payload.clone()),
                 {
                     #[allow(unused_mut)]
-                    let mut resolver = FileResolver::default()
-                        .with_path("./")
-                        .with_pattern("{}.js")
-                        .with_pattern("{}.mjs");
+                    let mut loader = BuiltinLoader::default();
 
-                    #[cfg(feature = "react")]
+                    #[cfg(feature = "stdlib-jsx")]
                     {
-                        resolver = resolver.with_pattern("{}.jsx");
-                        resolver = resolver.with_pattern("{}.mjsx");
-                    }
-
-                    #[cfg(feature = "typescript")]
-                    {
-                        resolver = resolver.with_pattern("{}.ts");
-
-                        #[cfg(feature = "react")]
-                        {
-                            resolver = resolver.with_pattern("{}.tsx");
-                        }
+                        loader = loader
+                            .with_module("den:jsx", den_stdlib_jsx::JSX)
+                            .with_module("den:jsx/jsx-runtime", den_stdlib_jsx::JSX_RUNTIME)
+                            .with_module(
+                                "den:jsx/jsx-dev-runtime",
+                                den_stdlib_jsx::JSX_DEV_RUNTIME,
+                            );
                     }
-
-                    resolver
+                    loader
                 },
-            );
-            let loader = (
-                BuiltinLoader::default(),
                 {
                     #[allow(unused_mut)]
                     let mut loader = ModuleLoader::default();
@@ -165,6 +161,11 @@ impl Engine {
                     {
                         loader = loader.with_module("den:crypto", den_stdlib_crypto::js_crypto);
                     }
+                    #[cfg(feature = "stdlib-transpiler")]
+                    {
+                        loader = loader
+                            .with_module("den:transpiler", den_stdlib_transpiler::js_transpiler);
+                    }
                     #[cfg(feature = "wasm")]
                     {
                         loader = loader.with_module("den:wasm", den_stdlib_wasm::js_wasm)
@@ -172,10 +173,10 @@ impl Engine {
                     loader
                 },
                 {
-                    let builder = HttpLoader::builder();
+                    let builder = HttpLoader::builder().cache(module_cache.clone());
                     #[cfg(feature = "transpile")]
                     {
-                        builder.transpiler(transpiler.clone())
+                        builder.transpiler(transpiler.transpiler().clone())
                     }
                     #[cfg(not(feature = "transpile"))]
                     {
@@ -186,7 +187,9 @@ impl Engine {
                 {
                     #[allow(unused_mut)]
                     let mut loader = {
-                        let mut builder = MmapScriptLoader::builder();
+                        let builder = MmapScriptLoader::builder()
+                            .loaded(loaded_modules.clone())
+                            .cache(module_cache.clone());
                         #[cfg(feature = "transpile")]
                         {
                             builder.transpiler(transpiler.clone())
@@ -224,18 +227,54 @@ impl Engine {
         }
 
         let stop_token = CancellationToken::new();
+        let interrupt_token = Arc::new(Mutex::new(CancellationToken::new()));
 
         runtime
             .set_interrupt_handler({
                 let world_end = stop_token.child_token();
-                Some(Box::new(move || world_end.is_cancelled()))
+                let interrupt_token = interrupt_token.clone();
+                Some(Box::new(move || {
+                    world_end.is_cancelled() || interrupt_token.lock().unwrap().is_cancelled()
+                }))
             })
             .await;
 
-        let context = AsyncContext::full(&runtime).await.unwrap();
+        let args = Arc::new(Mutex::new(vec![]));
+        let context = Self::new_context(&runtime, &[]).await;
+        #[cfg(feature = "transpile")]
+        define_transpile(&context, transpiler.transpiler().clone()).await;
+
+        Ok(Self {
+            #[cfg(feature = "transpile")]
+            transpiler,
+            runtime,
+            context,
+            stop_token,
+            interrupt_token,
+            loaded_modules,
+            module_cache,
+            args,
+            #[cfg(feature = "transpile")]
+            config: Arc::new(config.clone()),
+            #[cfg(feature = "transpile")]
+            standalone: payload.is_some(),
+        })
+    }
+
+    /// Create a fresh context on the runtime with the standard library globals
+    /// evaluated
+    async fn new_context(runtime: &AsyncRuntime, args: &[String]) -> AsyncContext {
+        let context = AsyncContext::full(runtime).await.unwrap();
 
         context
             .with(|ctx| {
+                let den = Object::new(ctx.clone())?;
+                den.set("args", args)?;
+                ctx.globals().set("Den", den)?;
+                // For `using` declarations, QuickJS does not have them yet
+                den_utils::symbol::dispose(&ctx)?;
+                den_utils::symbol::async_dispose(&ctx)?;
+
                 #[cfg(feature = "stdlib-console")]
                 {
                     let _ = Module::evaluate_def::<den_stdlib_console::js_console, _>(
@@ -297,20 +336,39 @@ impl Engine {
             .await
             .unwrap();
 
-        Self {
-            #[cfg(feature = "transpile")]
-            transpiler,
-            runtime,
-            context,
-            stop_token,
-        }
+        context
+    }
+
+    /// Throw away every binding in the current context and start over with a
+    /// new one, keeping the runtime, its loaders and the pending jobs
+    pub async fn reset_context(&mut self) {
+        let args = self.args.lock().unwrap().clone();
+        self.context = Self::new_context(&self.runtime, &args).await;
+        #[cfg(feature = "transpile")]
+        define_transpile(&self.context, self.transpiler.transpiler().clone()).await;
+    }
+
+    /// Set `Den.args`, the arguments given to the script
+    pub async fn set_args(&self, args: Vec<String>) -> Result<(), EngineError> {
+        self.context
+            .with(|ctx| {
+                ctx.globals()
+                    .get::<_, Object>("Den")?
+                    .set("args", args.clone())
+            })
+            .await?;
+        *self.args.lock().unwrap() = args;
+        Ok(())
     }
 
     pub async fn run_file<U: for<'a> FromJs<'a> + Sync + Send + 'static>(
         &self,
         filename: PathBuf,
     ) -> Result<U, EngineError> {
-        Ok(async_with!(self.context => |ctx| {
+        #[cfg(feature = "transpile")]
+        self.prefetch(&filename).await;
+
+        let value = async_with!(self.context => |ctx| {
             // Evil hack by using top-level await, so that the eval will transfer the import to our file resolver
             // then we can use it to transpile Typescript and other stuff
             // However, this is the problem because rather than returning the underlying value,
@@ -319,7 +377,7 @@ impl Engine {
             // Technically we can do an optimization to just run the future and discard the returned value,
             // since we run under an assumption of running this function on a file
             // However, with REPL continuation, things could change
-            let src = format!(r#"await import(`{}`)"#, filename.to_str().unwrap());
+            let src = format!(r#"await import(`{}`)"#, relative_to_cwd(&filename).to_str().unwrap());
             ctx.eval_with_options::<Promise, _>(src, {
                 let mut options = EvalOptions::default();
                 options.global = true;
@@ -328,31 +386,148 @@ impl Engine {
                 options
             })?.into_future::<Object>().await?.get("value")
         })
+        .await;
+        // Once the entry is evaluated every module it statically imports was
+        // loaded, anything left over would never be taken
+        #[cfg(feature = "transpile")]
+        self.module_cache.clear();
+        Ok(value?)
+    }
+
+    /// Load and transpile the modules `entry` statically imports, many at a
+    /// time, so that the loaders do not go through them one by one
+    #[cfg(feature = "transpile")]
+    pub async fn prefetch(&self, entry: &Path) {
+        if self.standalone {
+            return;
+        }
+        // Whatever fails here fails again in the loaders, with a proper error
+        let _ = ModuleGraph::walk(
+            &self.config,
+            entry,
+            self.transpiler.clone(),
+            Some(&self.module_cache),
+        )
+        .await;
+    }
+
+    /// Evaluate `src` as the main module, registered under `name` which is
+    /// also what its relative imports are resolved against
+    pub async fn run_module_source(&self, name: &str, src: &str) -> Result<(), EngineError> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "transpile")] {
+                let syntax = infer_transpile_syntax_by_extension(get_best_transpiling()).unwrap_or_default();
+                let (src, _) = self.transpile(name, src, syntax, IsModule::Bool(true)).await?;
+            } else {
+                let src = src.to_string();
+            }
+        }
+        let name = name.to_string();
+
+        Ok(async_with!(self.context => |ctx| {
+            let (_, promise) = Module::declare(ctx.clone(), name, src)?.eval()?;
+            promise.into_future::<()>().await
+        })
         .await?)
     }
 
+    /// Transpile `src` on the blocking pool, sources are parsed and
+    /// transformed in parallel when several are transpiled at once
     #[cfg(feature = "transpile")]
-    pub fn transpile(
+    pub async fn transpile(
         &self,
+        name: &str,
         src: &str,
         syntax: Syntax,
         module: IsModule,
     ) -> Result<(String, Option<SourceMap>), EasySwcTranspilerError> {
-        self.transpiler.transpile(src, syntax, module, false)
+        let transpiler = self.transpiler.transpiler().clone();
+        let (name, src) = (name.to_string(), src.to_string());
+        tokio::task::spawn_blocking(move || {
+            transpiler.transpile(&name, &src, syntax, module, false)
+        })
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
+    /// Point the `file:line:column` locations of an error stack back into the
+    /// sources of the modules, as they are shifted by transpiling, e.g. by
+    /// the helpers it inlines
+    #[cfg(feature = "transpile")]
+    pub fn map_error_locations(&self, error: &str) -> String {
+        if self.standalone {
+            return error.to_string();
+        }
+
+        let mut maps = std::collections::HashMap::new();
+        let mut lookup = |file: &str, line: u32, column: u32| {
+            let map = maps.entry(file.to_string()).or_insert_with(|| {
+                let extension = Path::new(file).extension()?.to_str()?;
+                let syntax = infer_transpile_syntax_by_extension(extension)?;
+                let stamp = FileStamp::of(Path::new(file)).ok()?;
+                let source = std::fs::read_to_string(file).ok()?;
+                // Same as the loader, so that the map comes out of the cache
+                let (_, map) = self
+                    .transpiler
+                    .transpile(file, Some((Path::new(file), stamp)), &source, syntax, true)
+                    .ok()?;
+                map
+            });
+            let token = map
+                .as_ref()?
+                .lookup_token(line.checked_sub(1)?, column.saturating_sub(1))?;
+            Some((token.get_src_line() + 1, token.get_src_col() + 1))
+        };
+
+        let mut mapped = vec![];
+        for frame in error.lines() {
+            // Frames read `    at name (file:line:column)` or `    at file:line:column`
+            let location = frame.trim_start().strip_prefix("at ").map(|x| {
+                x.strip_suffix(')')
+                    .and_then(|x| x.rsplit_once('(').map(|(_, x)| x))
+                    .unwrap_or(x)
+            });
+            let mut parts = location.into_iter().flat_map(|x| x.rsplitn(3, ':'));
+            let original = match (parts.next(), parts.next(), parts.next()) {
+                (Some(column), Some(line), Some(file)) => {
+                    column
+                        .parse()
+                        .ok()
+                        .zip(line.parse().ok())
+                        .and_then(|(column, line)| lookup(file, line, column))
+                        .map(|(line, column)| (file, line, column))
+                }
+                _ => None,
+            };
+            mapped.push(match (original, location) {
+                (Some((file, line, column)), Some(location)) => {
+                    frame.replace(location, &format!("{file}:{line}:{column}"))
+                }
+                _ => frame.to_string(),
+            });
+        }
+        mapped.join("\n")
     }
 
     pub async fn eval<U: for<'js> FromJs<'js> + Send + Sync + 'static>(
         &self,
         src: &str,
     ) -> Result<U, EngineError> {
+        self.eval_with(src, |ctx, value| U::from_js(ctx, value))
+            .await
+    }
+
+    /// Evaluate `src` and hand its completion value to `f`, which runs in the
+    /// context and may keep the value around, e.g. as a global
+    pub async fn eval_with<U, F>(&self, src: &str, f: F) -> Result<U, EngineError>
+    where
+        U: Send + Sync + 'static,
+        F: for<'js> FnOnce(&Ctx<'js>, Value<'js>) -> rquickjs::Result<U> + Send,
+    {
         cfg_if::cfg_if! {
             if #[cfg(feature = "transpile")] {
                 let syntax = infer_transpile_syntax_by_extension(get_best_transpiling()).unwrap_or_default();
-                let (src, _) = self.transpile(
-                    src,
-                    syntax,
-                    IsModule::Unknown,
-                )?;
+                let (src, _) = self.transpile("<eval>", src, syntax, IsModule::Unknown).await?;
             }
         }
 
@@ -363,7 +538,7 @@ impl Engine {
                 options.promise = true;
                 options.strict = true;
                 options
-            })?.into_future::<Object>().await?.get("value")
+            })?.into_future::<Object>().await?.get("value").and_then(|value| f(&ctx, value))
         })
         .await?)
     }
@@ -375,6 +550,22 @@ impl Engine {
     pub fn stop_token(&self) -> CancellationToken {
         self.stop_token.clone()
     }
+
+    /// Interrupt any script running on the runtime once `token` is cancelled,
+    /// until another token takes its place
+    pub fn set_interrupt_token(&self, token: CancellationToken) {
+        *self.interrupt_token.lock().unwrap() = token;
+    }
+
+    /// Canonical paths of every module loaded from disk so far
+    pub fn loaded_modules(&self) -> Vec<PathBuf> {
+        self.loaded_modules
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect()
+    }
 }
 
 #[derive(Display, From, Error, Debug)]
@@ -384,16 +575,296 @@ pub enum EngineError {
     EasySwcTranspiler(EasySwcTranspilerError),
     #[from]
     Rquickjs(rquickjs::Error),
+    #[from]
+    Config(ConfigError),
     #[cfg(feature = "transpile")]
     #[from]
     InferTranspileSyntaxError(den_transpiler_swc::InferTranspileSyntaxError),
 }
 
+/// A transpiler with the compiler options of a project config
+#[cfg(feature = "transpile")]
+pub(crate) fn transpiler(config: &Config) -> EasySwcTranspiler {
+    use crate::config::Jsx;
+
+    let options = &config.compiler_options;
+    let jsx = options.jsx.unwrap_or(Jsx::React);
+    EasySwcTranspiler::new(TranspilerOptions {
+        jsx_runtime:             match jsx {
+            Jsx::React | Jsx::Other => JsxRuntime::Classic,
+            Jsx::ReactJsx | Jsx::ReactJsxDev => JsxRuntime::Automatic,
+        },
+        jsx_development:         jsx == Jsx::ReactJsxDev,
+        jsx_factory:             options.jsx_factory.clone(),
+        jsx_fragment_factory:    options.jsx_fragment_factory.clone(),
+        jsx_import_source:       options.jsx_import_source.clone(),
+        legacy_decorators:       options.experimental_decorators.unwrap_or_default(),
+        emit_decorator_metadata: options.emit_decorator_metadata.unwrap_or_default(),
+        assign_class_fields:     !options.use_define_for_class_fields.unwrap_or(true),
+        verbatim_module_syntax:  options.verbatim_module_syntax.unwrap_or_default(),
+        minify:                  None,
+        target:                  None,
+    })
+}
+
+/// Expose `Den.transpile(code, options)` to scripts, which transpiles with
+/// the compiler options of the project and returns the `code` along with its
+/// source `map` as JSON when asked for. Options pick the `syntax` by
+/// extension, the `filename` the map refers to, whether the code is a
+/// `module` and how to `minify` it
+#[cfg(feature = "transpile")]
+async fn define_transpile(context: &AsyncContext, transpiler: Arc<EasySwcTranspiler>) {
+    use std::collections::HashMap;
+
+    use rquickjs::{prelude::Opt, Exception, Function, IntoJs};
+
+    // One transpiler for each way of minifying that was asked for
+    type Transpilers = Mutex<HashMap<Option<MinifyOptions>, Arc<EasySwcTranspiler>>>;
+
+    fn transpile<'js>(
+        transpilers: &Transpilers,
+        ctx: Ctx<'js>,
+        code: String,
+        options: Opt<Object<'js>>,
+    ) -> rquickjs::Result<Object<'js>> {
+        let options = match options.0 {
+            Some(options) => options,
+            None => Object::new(ctx.clone())?,
+        };
+        let syntax = options
+            .get::<_, Option<String>>("syntax")?
+            .unwrap_or_else(|| get_best_transpiling().to_string());
+        let syntax = infer_transpile_syntax_by_extension(&syntax)
+            .ok_or_else(|| Exception::throw_type(&ctx, &format!("unsupported syntax {syntax}")))?;
+        let module = options.get::<_, Option<bool>>("module")?.unwrap_or(true);
+        let source_map = options
+            .get::<_, Option<bool>>("sourceMap")?
+            .unwrap_or_default();
+        let name = options
+            .get::<_, Option<String>>("filename")?
+            .unwrap_or_else(|| "<transpile>".to_string());
+
+        let minify = minify_options(options.get("minify")?)?;
+        let transpiler = {
+            let mut transpilers = transpilers.lock().unwrap();
+            let base = transpilers[&None].clone();
+            transpilers
+                .entry(minify)
+                .or_insert_with(|| {
+                    Arc::new(EasySwcTranspiler::new(TranspilerOptions {
+                        minify,
+                        ..base.options().clone()
+                    }))
+                })
+                .clone()
+        };
+        let (code, map) = transpiler
+            .transpile(&name, &code, syntax, IsModule::Bool(module), source_map)
+            .map_err(|e| Exception::throw_syntax(&ctx, &e.to_string()))?;
+        let map = match map {
+            Some(map) => {
+                let mut buf = vec![];
+                map.to_writer(&mut buf)
+                    .map_err(|e| Exception::throw_internal(&ctx, &e.to_string()))?;
+                String::from_utf8_lossy(&buf).into_js(&ctx)?
+            }
+            None => Value::new_null(ctx.clone()),
+        };
+
+        let output = Object::new(ctx)?;
+        output.set("code", code)?;
+        output.set("map", map)?;
+        Ok(output)
+    }
+
+    // `true` or what to keep of the names
+    fn minify_options(value: Value) -> rquickjs::Result<Option<MinifyOptions>> {
+        let Some(options) = value.as_object() else {
+            return Ok(value.as_bool().unwrap_or_default().then(Default::default));
+        };
+        Ok(Some(MinifyOptions {
+            keep_fn_names:    options
+                .get::<_, Option<bool>>("keepFnames")?
+                .unwrap_or_default(),
+            keep_class_names: options
+                .get::<_, Option<bool>>("keepClassnames")?
+                .unwrap_or_default(),
+        }))
+    }
+
+    let transpilers: Transpilers = Mutex::new(HashMap::from([(None, transpiler)]));
+    context
+        .with(|ctx| {
+            let transpile = Function::new(ctx.clone(), move |ctx, code, options| {
+                transpile(&transpilers, ctx, code, options)
+            })?;
+            ctx.globals()
+                .get::<_, Object>("Den")?
+                .set("transpile", transpile)
+        })
+        .await
+        .unwrap();
+}
+
+/// The transpiler of a project config, keeping what it outputs under the
+/// cache directory
+#[cfg(feature = "transpile")]
+pub(crate) fn cached_transpiler(config: &Config) -> CachedTranspiler {
+    CachedTranspiler::new(
+        Arc::new(transpiler(config)),
+        Some(crate::config::cache_dir().join("transpiled")),
+    )
+}
+
+/// The chain every module specifier goes through: the import map, then the
+/// `paths` of the compiler options, then the `den:` builtins, then remote
+/// modules and finally files
+pub(crate) fn resolver(config: &Config) -> Result<impl Resolver + ModuleResolver, EngineError> {
+    let import_map = match &config.import_map {
+        Some(path) => ImportMapResolver::load(&config.resolve_path(path))?,
+        None => ImportMapResolver::default(),
+    };
+
+    Ok((
+        import_map,
+        PathsResolver::new(&config.compiler_options),
+        {
+            #[allow(unused_mut)]
+            let mut resolver = BuiltinResolver::default();
+
+            #[cfg(feature = "stdlib-core")]
+            {
+                resolver = resolver.with_module("den:core");
+            }
+            #[cfg(feature = "stdlib-console")]
+            {
+                resolver = resolver.with_module("den:console");
+            }
+            #[cfg(feature = "stdlib-networking")]
+            {
+                resolver = resolver.with_module("den:networking");
+            }
+            #[cfg(feature = "stdlib-text")]
+            {
+                resolver = resolver.with_module("den:text");
+            }
+            #[cfg(feature = "stdlib-timer")]
+            {
+                resolver = resolver.with_module("den:timer");
+            }
+            #[cfg(feature = "stdlib-fs")]
+            {
+                resolver = resolver.with_module("den:fs");
+            }
+            #[cfg(feature = "stdlib-jsx")]
+            {
+                resolver = resolver
+                    .with_module("den:jsx")
+                    .with_module("den:jsx/jsx-runtime")
+                    .with_module("den:jsx/jsx-dev-runtime");
+            }
+            #[cfg(feature = "stdlib-sqlite")]
+            {
+                resolver = resolver.with_module("den:sqlite");
+            }
+            #[cfg(feature = "stdlib-whatwg-fetch")]
+            {
+                resolver = resolver.with_module("den:whatcg-fetch");
+            }
+            #[cfg(feature = "stdlib-crypto")]
+            {
+                resolver = resolver.with_module("den:crypto");
+            }
+            #[cfg(feature = "stdlib-transpiler")]
+            {
+                resolver = resolver.with_module("den:transpiler");
+            }
+            #[cfg(feature = "wasm")]
+            {
+                resolver = resolver.with_module("den:wasm");
+            }
+            resolver
+        },
+        {
+            let mut resolver = HttpResolver::default();
+            for prefix in config.permissions.net.iter().flatten() {
+                resolver = resolver.with_allowed(prefix);
+            }
+            for prefix in &config.permissions.deny_net {
+                resolver = resolver.with_denied(prefix);
+            }
+            resolver
+        },
+        {
+            #[allow(unused_mut)]
+            let mut resolver = FileResolver::default()
+                .with_path("./")
+                .with_pattern("{}.js")
+                .with_pattern("{}.mjs");
+
+            #[cfg(feature = "react")]
+            {
+                resolver = resolver.with_pattern("{}.jsx");
+                resolver = resolver.with_pattern("{}.mjsx");
+            }
+
+            #[cfg(feature = "typescript")]
+            {
+                resolver = resolver.with_pattern("{}.ts");
+
+                #[cfg(feature = "react")]
+                {
+                    resolver = resolver.with_pattern("{}.tsx");
+                }
+            }
+
+            resolver
+        },
+    ))
+}
+
+/// The file resolver only knows paths relative to the working directory, so
+/// express absolute paths that way, e.g. `../x.ts` for `/a/x.ts` in `/a/b`
+pub(crate) fn relative_to_cwd(path: &Path) -> PathBuf {
+    let Some(cwd) = std::env::current_dir().ok().filter(|_| path.is_absolute()) else {
+        return path.to_path_buf();
+    };
+
+    let mut path_components = path.components().peekable();
+    let mut cwd_components = cwd.components().peekable();
+    while let (Some(x), Some(y)) = (path_components.peek(), cwd_components.peek()) {
+        if x != y {
+            break;
+        }
+        path_components.next();
+        cwd_components.next();
+    }
+    // Different roots, e.g. another drive on Windows
+    if cwd_components
+        .peek()
+        .is_some_and(|x| matches!(x, Component::Prefix(_) | Component::RootDir))
+    {
+        return path.to_path_buf();
+    }
+
+    let mut relative = PathBuf::from(".");
+    relative.extend(cwd_components.map(|_| Component::ParentDir));
+    relative.extend(path_components);
+    relative
+}
+
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "transpile")]
+    use std::collections::BTreeSet;
+
     use color_eyre::eyre;
+    use rquickjs::async_with;
+    use tokio_util::sync::CancellationToken;
 
     use crate::engine::Engine;
+    #[cfg(feature = "transpile")]
+    use crate::fixture::Project;
 
     #[tokio::test(flavor = "multi_thread")]
     async fn my_test() -> eyre::Result<()> {
@@ -422,4 +893,110 @@ mod tests {
             .await?;
         Ok(())
     }
+
+    /// A directory with `files`, each module recording its name in `loaded`
+    /// when it runs
+    #[cfg(feature = "transpile")]
+    fn project(name: &str, files: &[(&str, &str)]) -> Project {
+        let project = Project::new(name, &[]);
+        for (file, source) in files {
+            project.write(
+                file,
+                &format!("(globalThis.loaded ??= []).push({file:?});\n{source}"),
+            );
+        }
+        project
+    }
+
+    #[cfg(feature = "transpile")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn prefetches_the_modules_loading_runs() -> eyre::Result<()> {
+        let dir = project(
+            "prefetch",
+            &[
+                (
+                    "main.ts",
+                    "import './a.ts'; import './b.ts'; () => import('./lazy.ts')",
+                ),
+                ("a.ts", "import './c.ts'; import 'den:fs'"),
+                ("b.ts", "import './c.ts'"),
+                ("c.ts", "import './a.ts'"),
+                ("lazy.ts", ""),
+            ],
+        );
+        let engine = Engine::new().await;
+        engine.prefetch(&dir.join("main.ts")).await;
+        let prefetched = engine
+            .module_cache
+            .names()
+            .iter()
+            .map(|x| x.rsplit('/').next().unwrap().to_string())
+            .collect::<BTreeSet<_>>();
+
+        engine.run_file::<()>(dir.join("main.ts")).await?;
+        let loaded = engine.eval::<Vec<String>>("globalThis.loaded").await?;
+
+        assert_eq!(prefetched, loaded.into_iter().collect());
+        assert!(engine.module_cache.names().is_empty());
+        Ok(())
+    }
+
+    #[cfg(feature = "transpile")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn drops_prefetched_modules_of_failed_graphs() {
+        let dir = project(
+            "prefetch-failed",
+            &[
+                ("main.ts", "import './missing.ts'; import './a.ts'"),
+                ("a.ts", ""),
+            ],
+        );
+        let engine = Engine::new().await;
+        let result = engine.run_file::<()>(dir.join("main.ts")).await;
+
+        assert!(result.is_err());
+        assert!(engine.module_cache.names().is_empty());
+    }
+
+    #[cfg(feature = "transpile")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn transpiles_with_source_maps() -> eyre::Result<()> {
+        let engine = Engine::new().await;
+        let output = engine
+            .eval::<Vec<String>>(concat!(
+                "const plain = Den.transpile('const a: number = 1', { syntax: 'ts' });\n",
+                "const min = Den.transpile('export const a: number = 1 + 1', ",
+                "{ syntax: 'ts', minify: true, sourceMap: true, filename: 'a.ts' });\n",
+                "[plain.code.trim(), String(plain.map), min.code, JSON.parse(min.map).sources[0]]",
+            ))
+            .await?;
+        assert_eq!(
+            output,
+            ["const a = 1;", "null", "export const a=2;", "a.ts"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn interrupts_endless_loops() -> eyre::Result<()> {
+        let engine = Engine::new().await;
+        engine.eval::<()>("globalThis.kept = 1").await?;
+
+        let token = CancellationToken::new();
+        engine.set_interrupt_token(token.clone());
+        // The loop holds a worker of the runtime, so the interrupt comes from
+        // another thread
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            token.cancel();
+        });
+        let looped = engine.eval::<()>("while (true) {}").await;
+        assert!(looped.is_err());
+
+        // Another token lets scripts run again, in the same context
+        engine.set_interrupt_token(CancellationToken::new());
+        async_with!(engine.context => |ctx| { ctx.catch(); }).await;
+        assert_eq!(engine.eval::<i32>("kept + 1").await?, 2);
+        Ok(())
+    }
 }
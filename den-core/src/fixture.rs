@@ -0,0 +1,43 @@
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// A directory of files for a test, under the temporary directory and named
+/// after it, removed once dropped
+pub(crate) struct Project(PathBuf);
+
+impl Project {
+    /// A fresh directory with `files`, given by their path relative to it
+    pub(crate) fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("den-core-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let project = Self(dir);
+        for (file, contents) in files {
+            project.write(file, contents);
+        }
+        project
+    }
+
+    pub(crate) fn write(&self, file: &str, contents: &str) {
+        let path = self.0.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+impl Deref for Project {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
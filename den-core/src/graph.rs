@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    path::Path,
+};
+
+use den_transpiler_swc::{infer_transpile_syntax_by_extension, parse_imports, Import, Syntax};
+use relative_path::RelativePath;
+use serde::Serialize;
+use tokio::task::JoinSet;
+
+use crate::{
+    config::Config,
+    engine::{cached_transpiler, relative_to_cwd, resolver, EngineError},
+    loader::{cache::ModuleCache, http},
+    resolver::ModuleResolver,
+    transpile_cache::{CachedTranspiler, FileStamp},
+};
+
+/// How many modules are loaded at the same time while walking a graph
+const MAX_CONCURRENT_LOADS: usize = 16;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ModuleKind {
+    /// A `den:` module built into the runtime
+    Builtin,
+    File,
+    Remote,
+}
+
+impl ModuleKind {
+    fn of(name: &str) -> Self {
+        if name.starts_with("den:") {
+            Self::Builtin
+        } else if name.starts_with("http://") || name.starts_with("https://") {
+            Self::Remote
+        } else {
+            Self::File
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Dependency {
+    /// The specifier as written in the source
+    pub specifier: String,
+    pub dynamic:   bool,
+    /// The module name it resolves to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved:  Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error:     Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleInfo {
+    pub kind:          ModuleKind,
+    /// Size of the source in bytes, builtins have none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size:          Option<usize>,
+    /// Why the module could not be loaded or parsed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error:         Option<String>,
+    pub dependencies:  Vec<Dependency>,
+    // Kept for bundling and compiling, prefetching hands the transpiled code
+    // to the loaders instead
+    #[serde(skip)]
+    pub(crate) source: Option<(String, Syntax)>,
+    #[serde(skip)]
+    pub(crate) code:   Option<String>,
+}
+
+impl ModuleInfo {
+    /// A module of `kind` yet to be loaded
+    fn new(kind: ModuleKind) -> Self {
+        Self {
+            kind,
+            size: None,
+            error: None,
+            dependencies: vec![],
+            source: None,
+            code: None,
+        }
+    }
+}
+
+/// The modules an entry point imports, found by parsing the sources and
+/// resolving their imports the same way the engine does, without running
+/// anything
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleGraph {
+    pub root:    String,
+    pub modules: BTreeMap<String, ModuleInfo>,
+}
+
+impl ModuleGraph {
+    pub async fn build(config: &Config, entry: &Path) -> Result<Self, EngineError> {
+        Self::walk(config, entry, cached_transpiler(config), None).await
+    }
+
+    /// Load the modules reachable from `entry`, up to `MAX_CONCURRENT_LOADS`
+    /// at a time, putting the transpiled ones into `prefetch` if given.
+    /// Prefetching only follows static imports since dynamic ones may never
+    /// run
+    pub(crate) async fn walk(
+        config: &Config,
+        entry: &Path,
+        transpiler: CachedTranspiler,
+        prefetch: Option<&ModuleCache>,
+    ) -> Result<Self, EngineError> {
+        let mut resolver = resolver(config)?;
+        let mut resolve = |base: &str, name: &str| resolver.resolve_module(base, name);
+
+        let root = resolve(".", &relative_to_cwd(entry).to_string_lossy())?;
+        let mut modules = BTreeMap::new();
+        let mut seen = BTreeSet::from([root.clone()]);
+        let mut pending = VecDeque::from([root.clone()]);
+        let mut loads = JoinSet::new();
+
+        loop {
+            while loads.len() < MAX_CONCURRENT_LOADS {
+                let Some(name) = pending.pop_front() else {
+                    break;
+                };
+                let kind = ModuleKind::of(&name);
+                if kind == ModuleKind::Builtin {
+                    modules.insert(name, ModuleInfo::new(kind));
+                    continue;
+                }
+                let transpiler = transpiler.clone();
+                let with_source = prefetch.is_none();
+                loads.spawn(async move {
+                    let loaded = load(&name, kind, transpiler, with_source).await;
+                    (name, kind, loaded)
+                });
+            }
+
+            let Some(joined) = loads.join_next().await else {
+                break;
+            };
+            let (name, kind, loaded) = joined.expect("loading a module does not panic");
+            let mut info = ModuleInfo::new(kind);
+            match loaded {
+                Ok(loaded) => {
+                    info.size = Some(loaded.size);
+                    match prefetch {
+                        Some(cache) => cache.insert(name.clone(), loaded.code),
+                        None => {
+                            info.source = loaded.source.map(|x| (x, loaded.syntax));
+                            info.code = Some(loaded.code);
+                        }
+                    }
+                    for import in loaded.imports {
+                        let resolved = resolve(&name, &import.specifier);
+                        if let Ok(resolved) = &resolved {
+                            let follow = prefetch.is_none() || !import.dynamic;
+                            if follow && seen.insert(resolved.clone()) {
+                                pending.push_back(resolved.clone());
+                            }
+                        }
+                        info.dependencies.push(Dependency {
+                            specifier: import.specifier,
+                            dynamic:   import.dynamic,
+                            error:     resolved.as_ref().err().map(|e| e.to_string()),
+                            resolved:  resolved.ok(),
+                        });
+                    }
+                }
+                Err(e) => info.error = Some(e),
+            }
+            modules.insert(name, info);
+        }
+
+        Ok(Self { root, modules })
+    }
+
+    /// Total size of every module source in the graph
+    pub fn size(&self) -> usize {
+        self.modules.values().filter_map(|x| x.size).sum()
+    }
+
+    /// Import cycles, each one starting and ending with the same module
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        fn visit<'a>(
+            graph: &'a ModuleGraph,
+            name: &'a str,
+            stack: &mut Vec<&'a str>,
+            done: &mut Vec<&'a str>,
+            cycles: &mut Vec<Vec<String>>,
+        ) {
+            if let Some(start) = stack.iter().position(|x| *x == name) {
+                let mut cycle: Vec<String> = stack[start..].iter().map(|x| x.to_string()).collect();
+                cycle.push(name.to_string());
+                cycles.push(cycle);
+                return;
+            }
+            if done.contains(&name) {
+                return;
+            }
+
+            stack.push(name);
+            for dependency in graph.modules.get(name).iter().flat_map(|x| &x.dependencies) {
+                if let Some(resolved) = &dependency.resolved {
+                    visit(graph, resolved, stack, done, cycles);
+                }
+            }
+            stack.pop();
+            done.push(name);
+        }
+
+        let mut cycles = vec![];
+        visit(self, &self.root, &mut vec![], &mut vec![], &mut cycles);
+        cycles
+    }
+}
+
+struct Loaded {
+    source:  Option<String>,
+    syntax:  Syntax,
+    size:    usize,
+    imports: Vec<Import>,
+    code:    String,
+}
+
+/// Read or download a module and transpile it as the loaders would. Files
+/// already in the transpile cache are not read unless `with_source`
+async fn load(
+    name: &str,
+    kind: ModuleKind,
+    transpiler: CachedTranspiler,
+    with_source: bool,
+) -> Result<Loaded, String> {
+    if kind == ModuleKind::File && !with_source {
+        let extension = RelativePath::new(name).extension().unwrap_or("js");
+        let syntax = infer_transpile_syntax_by_extension(extension).unwrap_or_default();
+        let cached = {
+            let name = name.to_string();
+            let transpiler = transpiler.clone();
+            tokio::task::spawn_blocking(move || {
+                let code = transpiler.cached(&name, Path::new(&name), syntax)?;
+                let size = std::fs::metadata(&name).ok()?.len() as usize;
+                let imports = parse_imports(&code, Default::default()).ok()?;
+                Some(Loaded {
+                    source: None,
+                    syntax,
+                    size,
+                    imports,
+                    code,
+                })
+            })
+            .await
+            .map_err(|e| e.to_string())?
+        };
+        if let Some(loaded) = cached {
+            return Ok(loaded);
+        }
+    }
+
+    let mut stamp = None;
+    let (source, extension) = match kind {
+        ModuleKind::Remote => http::fetch(name, false).await.map_err(|e| e.to_string())?,
+        _ => {
+            stamp = FileStamp::of(Path::new(name)).ok();
+            let source = tokio::fs::read_to_string(name)
+                .await
+                .map_err(|e| format!("cannot read {name}: {e}"))?;
+            (source, RelativePath::new(name).extension().unwrap_or("js"))
+        }
+    };
+    let syntax = infer_transpile_syntax_by_extension(extension).unwrap_or_default();
+
+    // Parsing and transpiling would hold up the other loads otherwise
+    let name = name.to_string();
+    tokio::task::spawn_blocking(move || {
+        let file = stamp.map(|x| (Path::new(&name), x));
+        let (code, _) = transpiler
+            .transpile(&name, file, &source, syntax, false)
+            .map_err(|e| e.to_string())?;
+        // Imports of types are gone by now, while the automatic JSX runtime
+        // brings its own
+        let imports = parse_imports(&code, Default::default()).map_err(|e| e.to_string())?;
+        Ok(Loaded {
+            size: source.len(),
+            source: Some(source),
+            syntax,
+            imports,
+            code,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -1,3 +1,11 @@
+#[cfg(feature = "transpile")] pub mod bundle;
+pub mod config;
 pub mod engine;
+#[cfg(test)] mod fixture;
+#[cfg(feature = "transpile")] pub mod graph;
+#[cfg(feature = "transpile")] pub mod lint;
 pub mod loader;
 pub mod resolver;
+pub mod standalone;
+#[cfg(feature = "transpile")]
+pub mod transpile_cache;
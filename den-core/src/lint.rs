@@ -0,0 +1,111 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use den_transpiler_swc::{
+    apply_lint_fixes, infer_transpile_syntax_by_extension, EasySwcTranspiler,
+    EasySwcTranspilerError,
+};
+pub use den_transpiler_swc::{LintFix, LintProblem, LINT_RULES};
+use derive_more::{Display, Error, From};
+
+use crate::{
+    config::{Config, ConfigError},
+    engine::transpiler,
+};
+
+/// What `den lint` looks for when the config does not say otherwise
+const DEFAULT_LINT_INCLUDE: &[&str] = &["**/*.{js,mjs,cjs,jsx,ts,mts,cts,tsx}"];
+
+#[derive(Display, From, Error, Debug)]
+pub enum LintError {
+    #[from]
+    Config(ConfigError),
+    #[display("unknown lint rule {_0}")]
+    UnknownRule(#[error(not(source))] String),
+    #[display("cannot lint {}: unsupported extension", _0.display())]
+    Extension(#[error(not(source))] PathBuf),
+    #[display("cannot read {}: {_1}", _0.display())]
+    Io(#[error(not(source))] PathBuf, io::Error),
+    #[from]
+    Transpiler(EasySwcTranspilerError),
+}
+
+/// Lints files with the rules of a project config
+pub struct Linter {
+    transpiler: EasySwcTranspiler,
+    rules:      Vec<&'static str>,
+}
+
+/// What is left to fix in a file, after fixing what could be
+pub struct LintedFile {
+    pub problems: Vec<LintProblem>,
+    /// How many problems were fixed
+    pub fixed:    usize,
+}
+
+impl Linter {
+    pub fn new(config: &Config) -> Result<Self, LintError> {
+        let rules = &config.lint.rules;
+        for rule in rules.include.iter().chain(&rules.exclude) {
+            if !LINT_RULES.contains(&rule.as_str()) {
+                return Err(LintError::UnknownRule(rule.clone()));
+            }
+        }
+        let rules = LINT_RULES
+            .iter()
+            .copied()
+            .filter(|x| rules.include.is_empty() || rules.include.iter().any(|y| x == y))
+            .filter(|x| !rules.exclude.iter().any(|y| x == y))
+            .collect();
+        Ok(Self {
+            transpiler: transpiler(config),
+            rules,
+        })
+    }
+
+    /// The files to lint, directories are searched like the project root
+    pub fn files(config: &Config, paths: &[PathBuf]) -> Result<Vec<PathBuf>, LintError> {
+        if paths.is_empty() {
+            return Ok(config.lint.files.files(&config.dir, DEFAULT_LINT_INCLUDE)?);
+        }
+
+        let mut files = vec![];
+        for path in paths {
+            if path.is_dir() {
+                files.extend(config.lint.files.files(path, DEFAULT_LINT_INCLUDE)?);
+            } else {
+                files.push(path.clone());
+            }
+        }
+        Ok(files)
+    }
+
+    /// Lint a file, writing it back with the problems that can be fixed
+    /// fixed when `fix` is set
+    pub fn lint(&self, path: &Path, fix: bool) -> Result<LintedFile, LintError> {
+        let extension = match path.extension().and_then(|x| x.to_str()).unwrap_or("js") {
+            "mts" | "cts" => "ts",
+            "cjs" => "js",
+            extension => extension,
+        };
+        let syntax = infer_transpile_syntax_by_extension(extension)
+            .ok_or_else(|| LintError::Extension(path.to_path_buf()))?;
+        let name = path.to_string_lossy();
+        let source =
+            std::fs::read_to_string(path).map_err(|e| LintError::Io(path.to_path_buf(), e))?;
+        let problems = self.transpiler.lint(&name, &source, syntax, &self.rules)?;
+        if !fix || problems.iter().all(|x| x.fix.is_none()) {
+            return Ok(LintedFile { problems, fixed: 0 });
+        }
+
+        let fixed = apply_lint_fixes(&source, &problems);
+        std::fs::write(path, &fixed).map_err(|e| LintError::Io(path.to_path_buf(), e))?;
+        let remaining = self.transpiler.lint(&name, &fixed, syntax, &self.rules)?;
+        Ok(LintedFile {
+            fixed:    problems.len().saturating_sub(remaining.len()),
+            problems: remaining,
+        })
+    }
+}
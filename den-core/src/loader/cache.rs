@@ -0,0 +1,31 @@
+#[cfg(test)] use std::collections::BTreeSet;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Module sources loaded and transpiled ahead of evaluation, keyed by their
+/// resolved name. Each one is handed out once, later loads go to the source
+#[derive(Debug, Clone, Default)]
+pub struct ModuleCache(Arc<Mutex<HashMap<String, String>>>);
+
+impl ModuleCache {
+    pub fn insert(&self, name: String, source: String) {
+        self.0.lock().unwrap().insert(name, source);
+    }
+
+    pub fn take(&self, name: &str) -> Option<String> {
+        self.0.lock().unwrap().remove(name)
+    }
+
+    /// Drop whatever was never taken, e.g. the modules of a graph that failed
+    /// to load
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn names(&self) -> BTreeSet<String> {
+        self.0.lock().unwrap().keys().cloned().collect()
+    }
+}
@@ -10,97 +10,110 @@ use {
     std::sync::Arc,
 };
 
+use crate::loader::cache::ModuleCache;
+
 #[derive(Derivative, TypedBuilder)]
 #[derivative(Default(new = "true"))]
 pub struct HttpLoader {
     #[derivative(Default(value = "true"))]
     #[builder(default)]
     check_mime: bool,
+    #[builder(default)]
+    cache:      ModuleCache,
     #[derivative(Debug = "ignore")]
     #[cfg(feature = "transpile")]
     transpiler: Arc<EasySwcTranspiler>,
 }
 
+/// Download a remote module, along with the extension its MIME type maps to
+/// when `check_mime` is set, `js` otherwise
+pub(crate) async fn fetch(name: &str, check_mime: bool) -> Result<(String, &'static str)> {
+    let body = reqwest::get(name)
+        .await
+        .map_err(|e| Error::new_loading_message(name, e.to_string()))?;
+    let extension = if check_mime {
+        let mime_type = body
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.parse::<Mime>().ok());
+        // We need to check whether the MIME type is "text/javascript",
+        // "text/typescript", "application/javascript", "application/typescript", ...
+        'check_mime: loop {
+            match mime_type {
+                Some(ref mime) if matches!(mime.type_(), mime::TEXT | mime::APPLICATION) => {
+                    let subtype = mime.subtype();
+
+                    if subtype == mime::JAVASCRIPT {
+                        break 'check_mime Some("js");
+                    }
+
+                    #[cfg(feature = "typescript")]
+                    if subtype == "typescript" {
+                        break 'check_mime Some("ts");
+                    }
+                    return Err(Error::new_loading_message(
+                        name,
+                        format!("{name} is not a valid script"),
+                    ));
+                }
+                Some(_) => {
+                    return Err(Error::new_loading_message(
+                        name,
+                        format!("{name} is not a valid script"),
+                    ))
+                }
+                None => {
+                    let msg = format!(
+                        "cannot determine whether the content of {name} is valid javascript"
+                    );
+                    return Err(Error::new_loading_message(name, msg));
+                }
+            };
+        }
+    } else {
+        None
+    }
+    .unwrap_or("js");
+
+    let body = body.text().await.map_err(|_| {
+        Error::new_loading_message(name, format!("cannot load {name} as program text"))
+    })?;
+    Ok((body, extension))
+}
+
 impl Loader for HttpLoader {
     fn load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> Result<Module<'js, Declared>> {
+        // Files are left to their own loader, which keeps track of them
+        if !(name.starts_with("http://") || name.starts_with("https://")) {
+            return Err(Error::new_loading(name));
+        }
+        if let Some(src) = self.cache.take(name) {
+            return Module::declare(ctx.clone(), name, src);
+        }
+
         let task = async move {
-            let body = reqwest::get(name)
-                .await
-                .map_err(|e| Error::new_loading_message(name, e.to_string()))?;
-            let extension = if self.check_mime {
-                let mime_type = body
-                    .headers()
-                    .get(CONTENT_TYPE)
-                    .and_then(|x| x.to_str().ok())
-                    .and_then(|x| x.parse::<Mime>().ok());
-                // We need to check whether the MIME type is "text/javascript",
-                // "text/typescript", "application/javascript", "application/typescript", ...
-                'check_mime: loop {
-                    match mime_type {
-                        Some(ref mime)
-                            if matches!(mime.type_(), mime::TEXT | mime::APPLICATION) =>
-                        {
-                            let subtype = mime.subtype();
+            #[allow(unused_variables)]
+            let (body, extension) = fetch(name, self.check_mime).await?;
 
-                            if subtype == mime::JAVASCRIPT {
-                                break 'check_mime Some("js");
-                            }
+            #[cfg(feature = "transpile")]
+            {
+                let (src, _) = self
+                    .transpiler
+                    .transpile(
+                        name,
+                        &body,
+                        infer_transpile_syntax_by_extension(extension).unwrap_or_default(),
+                        IsModule::Bool(true),
+                        false,
+                    )
+                    .map_err(|e| Error::new_loading_message(name, e.to_string()))?;
 
-                            #[cfg(feature = "typescript")]
-                            if subtype == "typescript" {
-                                break 'check_mime Some("ts");
-                            }
-                            return Err(Error::new_loading_message(
-                                name,
-                                format!("{name} is not a valid script"),
-                            ));
-                        }
-                        Some(_) => {
-                            return Err(Error::new_loading_message(
-                                name,
-                                format!("{name} is not a valid script"),
-                            ))
-                        }
-                        None => {
-                            let msg = format!(
-                                "cannot determine whether the content of {name} is valid \
-                                 javascript"
-                            );
-                            return Err(Error::new_loading_message(name, msg));
-                        }
-                    };
-                }
-            } else {
-                None
+                Module::declare(ctx.clone(), name, src)
             }
-            .unwrap_or("js");
-
-            if let Ok(body) = body.text().await {
-                #[cfg(feature = "transpile")]
-                {
-                    let (src, _) = self
-                        .transpiler
-                        .transpile(
-                            &body,
-                            infer_transpile_syntax_by_extension(extension).unwrap_or_default(),
-                            IsModule::Bool(true),
-                            false,
-                        )
-                        .map_err(|e| {
-                            Error::new_loading_message("cannot transpile", e.to_string())
-                        })?;
-
-                    Module::declare(ctx.clone(), name, src)
-                }
-                #[cfg(not(feature = "transpile"))]
-                {
-                    Module::declare(ctx.clone(), name, body)
-                }
-            } else {
-                Err(Error::new_loading_message(
-                    name,
-                    format!("cannot load {name} as program text"),
-                ))
+            #[cfg(not(feature = "transpile"))]
+            {
+                Module::declare(ctx.clone(), name, body)
             }
         };
 
@@ -1,14 +1,21 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "transpile")]
+use den_transpiler_swc::infer_transpile_syntax_by_extension;
 use derivative::Derivative;
 use fmmap::tokio::{AsyncMmapFile, AsyncMmapFileExt};
 use relative_path::RelativePath;
 use rquickjs::{loader::Loader, module::Declared, Ctx, Error, Module, Result};
 use tokio::runtime::Handle;
 use typed_builder::TypedBuilder;
+
+use crate::loader::cache::ModuleCache;
 #[cfg(feature = "transpile")]
-use {
-    den_transpiler_swc::{infer_transpile_syntax_by_extension, EasySwcTranspiler, IsModule},
-    std::sync::Arc,
-};
+use crate::transpile_cache::{CachedTranspiler, FileStamp};
 
 #[derive(Derivative, TypedBuilder)]
 #[derivative(Debug)]
@@ -16,9 +23,14 @@ use {
 pub struct MmapScriptLoader {
     #[builder(default)]
     extensions: Vec<String>,
+    // Every file successfully opened so far, i.e. the module graph on disk
+    #[builder(default)]
+    loaded:     Arc<Mutex<BTreeSet<PathBuf>>>,
+    #[builder(default)]
+    cache:      ModuleCache,
     #[derivative(Debug = "ignore")]
     #[cfg(feature = "transpile")]
-    transpiler: Arc<EasySwcTranspiler>,
+    transpiler: CachedTranspiler,
 }
 
 impl MmapScriptLoader {
@@ -38,6 +50,13 @@ impl MmapScriptLoader {
 
 impl Loader for MmapScriptLoader {
     fn load<'js>(&mut self, ctx: &Ctx<'js>, path: &str) -> Result<Module<'js, Declared>> {
+        if let Some(src) = self.cache.take(path) {
+            if let Ok(path) = std::fs::canonicalize(path) {
+                self.loaded.lock().unwrap().insert(path);
+            }
+            return Module::declare(ctx.clone(), path, src);
+        }
+
         let task = async move {
             let extension = RelativePath::new(path)
                 .extension()
@@ -50,21 +69,37 @@ impl Loader for MmapScriptLoader {
                 .find(|&e| extension == e)
                 .ok_or(Error::new_loading(path))?;
 
+            #[cfg(feature = "transpile")]
+            let syntax = infer_transpile_syntax_by_extension(extension).unwrap_or_default();
+            #[cfg(feature = "transpile")]
+            if let Some(src) = self.transpiler.cached(path, Path::new(path), syntax) {
+                if let Ok(path) = std::fs::canonicalize(path) {
+                    self.loaded.lock().unwrap().insert(path);
+                }
+                return Module::declare(ctx.clone(), path, src);
+            }
+
+            #[cfg(feature = "transpile")]
+            let stamp = FileStamp::of(Path::new(path)).ok();
             let src = AsyncMmapFile::open(path)
                 .await
                 .map_err(|_| Error::new_loading(path))?;
+            if let Ok(path) = std::fs::canonicalize(path) {
+                self.loaded.lock().unwrap().insert(path);
+            }
 
             #[cfg(feature = "transpile")]
             {
                 let (src, _) = self
                     .transpiler
                     .transpile(
+                        path,
+                        stamp.map(|x| (Path::new(path), x)),
                         std::str::from_utf8(src.as_slice())?,
-                        infer_transpile_syntax_by_extension(extension).unwrap_or_default(),
-                        IsModule::Bool(true),
+                        syntax,
                         false,
                     )
-                    .map_err(|e| Error::new_loading_message("cannot transpile", e.to_string()))?;
+                    .map_err(|e| Error::new_loading_message(path, e.to_string()))?;
 
                 let module = Module::declare(ctx.clone(), path, src)?;
                 Ok(module)
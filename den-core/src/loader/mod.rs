@@ -1,2 +1,4 @@
+pub mod cache;
 pub mod http;
 pub mod mmap_script;
+pub mod payload;
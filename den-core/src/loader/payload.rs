@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use rquickjs::{loader::Loader, module::Declared, Ctx, Error, Module, Result};
+
+use crate::standalone::Payload;
+
+/// Loads the already transpiled modules of a compiled program
+#[derive(Debug, Clone, Default)]
+pub struct PayloadLoader {
+    payload: Option<Arc<Payload>>,
+}
+
+impl PayloadLoader {
+    pub fn new(payload: Option<Arc<Payload>>) -> Self {
+        Self { payload }
+    }
+}
+
+impl Loader for PayloadLoader {
+    fn load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> Result<Module<'js, Declared>> {
+        let src = self
+            .payload
+            .as_ref()
+            .and_then(|x| x.modules.get(name))
+            .ok_or_else(|| Error::new_loading(name))?;
+        Module::declare(ctx.clone(), name, src.as_str())
+    }
+}
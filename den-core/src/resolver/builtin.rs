@@ -0,0 +1,36 @@
+use std::collections::HashSet;
+
+use relative_path::RelativePath;
+use rquickjs::{Error, Result};
+
+use crate::resolver::{impl_resolver, ModuleResolver};
+
+/// Resolves the names of modules built into the runtime, e.g. `den:fs`
+#[derive(Debug, Default)]
+pub struct BuiltinResolver {
+    modules: HashSet<String>,
+}
+
+impl BuiltinResolver {
+    #[must_use]
+    pub fn with_module<P: Into<String>>(mut self, name: P) -> Self {
+        self.modules.insert(name.into());
+        self
+    }
+}
+
+impl ModuleResolver for BuiltinResolver {
+    fn resolve_module(&mut self, base: &str, name: &str) -> Result<String> {
+        let full = match RelativePath::new(base).parent() {
+            Some(dir) if name.starts_with('.') => dir.join_normalized(name).to_string(),
+            _ => name.to_string(),
+        };
+        if self.modules.contains(&full) {
+            Ok(full)
+        } else {
+            Err(Error::new_resolving(base, name))
+        }
+    }
+}
+
+impl_resolver!(BuiltinResolver);
@@ -0,0 +1,66 @@
+use relative_path::{RelativePath, RelativePathBuf};
+use rquickjs::{Error, Result};
+
+use crate::resolver::{impl_resolver, ModuleResolver};
+
+/// Resolves relative imports, and bare ones under the search paths, to
+/// files. A name without an extension is tried with every pattern, e.g.
+/// `{}.ts`
+#[derive(Debug, Default)]
+pub struct FileResolver {
+    paths:    Vec<RelativePathBuf>,
+    patterns: Vec<String>,
+}
+
+impl FileResolver {
+    #[must_use]
+    pub fn with_path<P: Into<RelativePathBuf>>(mut self, path: P) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_pattern<P: Into<String>>(mut self, pattern: P) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    fn try_patterns(&self, path: &RelativePath) -> Option<RelativePathBuf> {
+        if let Some(extension) = path.extension() {
+            // Only files of a known extension
+            let known = self
+                .patterns
+                .iter()
+                .any(|x| RelativePath::new(x).extension() == Some(extension));
+            return (known && is_file(path)).then(|| path.to_relative_path_buf());
+        }
+        self.patterns.iter().find_map(|pattern| {
+            let file = path.with_file_name(pattern.replace("{}", path.file_name()?));
+            is_file(&file).then_some(file)
+        })
+    }
+}
+
+fn is_file(path: &RelativePath) -> bool {
+    path.to_path(".").is_file()
+}
+
+impl ModuleResolver for FileResolver {
+    fn resolve_module(&mut self, base: &str, name: &str) -> Result<String> {
+        let path = if name.starts_with('.') {
+            let path = match RelativePath::new(base).parent() {
+                Some(dir) => dir.join_normalized(name),
+                None => name.into(),
+            };
+            self.try_patterns(&path)
+        } else {
+            self.paths
+                .iter()
+                .find_map(|x| self.try_patterns(&x.join_normalized(name)))
+        };
+        path.map(|x| x.to_string())
+            .ok_or_else(|| Error::new_resolving(base, name))
+    }
+}
+
+impl_resolver!(FileResolver);
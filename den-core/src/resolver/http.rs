@@ -1,15 +1,46 @@
 use matchit::{MatchError, Router};
-use rquickjs::{loader::Resolver, Ctx, Error, Result};
+use rquickjs::{Error, Result};
 use url::{ParseError, Url};
 
+use crate::resolver::{impl_resolver, ModuleResolver};
+
 #[derive(Default)]
 pub struct HttpResolver {
     pub(crate) allowlist: Option<Router<String>>,
     pub(crate) denylist:  Option<Router<String>>,
 }
 
-impl Resolver for HttpResolver {
-    fn resolve(&mut self, _ctx: &Ctx<'_>, base_path: &str, path: &str) -> Result<String> {
+impl HttpResolver {
+    /// Only allow URLs under `prefix`, along with any other allowed prefix
+    #[must_use]
+    pub fn with_allowed(mut self, prefix: &str) -> Self {
+        insert_prefix(self.allowlist.get_or_insert_with(Router::new), prefix);
+        self
+    }
+
+    /// Deny URLs under `prefix`, even if they are allowed
+    #[must_use]
+    pub fn with_denied(mut self, prefix: &str) -> Self {
+        insert_prefix(self.denylist.get_or_insert_with(Router::new), prefix);
+        self
+    }
+}
+
+fn insert_prefix(router: &mut Router<String>, prefix: &str) {
+    let prefix = prefix.trim_end_matches('/');
+    // Overlapping prefixes conflict with each other, but any of them matching is
+    // all that matters
+    for route in [
+        prefix.to_string(),
+        format!("{prefix}/"),
+        format!("{prefix}/{{*rest}}"),
+    ] {
+        let _ = router.insert(route, prefix.to_string());
+    }
+}
+
+impl ModuleResolver for HttpResolver {
+    fn resolve_module(&mut self, base_path: &str, path: &str) -> Result<String> {
         let base_path_url = Url::parse(base_path);
         let path_url = Url::parse(path);
 
@@ -52,3 +83,5 @@ impl Resolver for HttpResolver {
         }
     }
 }
+
+impl_resolver!(HttpResolver);
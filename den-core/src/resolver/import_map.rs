@@ -0,0 +1,71 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use rquickjs::{Error, Result};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    config::ConfigError,
+    resolver::{impl_resolver, ModuleResolver},
+};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ImportMap {
+    #[serde(default)]
+    imports: BTreeMap<String, String>,
+}
+
+/// Resolves bare specifiers through the `imports` of an import map. Keys
+/// ending with `/` map every specifier under them, the longest one wins
+#[derive(Debug, Clone, Default)]
+pub struct ImportMapResolver {
+    imports: BTreeMap<String, String>,
+    // Relative targets are resolved against the directory of the import map
+    dir:     PathBuf,
+}
+
+impl ImportMapResolver {
+    pub fn load(path: &Path) -> std::result::Result<Self, ConfigError> {
+        let source = std::fs::read(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        let map: ImportMap = serde_json::from_slice(&source)
+            .map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        Ok(Self {
+            imports: map.imports,
+            dir:     path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        if let Some(target) = self.imports.get(name) {
+            return Some(target.clone());
+        }
+
+        self.imports
+            .iter()
+            .filter(|(key, _)| key.ends_with('/') && name.starts_with(key.as_str()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(key, target)| format!("{target}{}", &name[key.len()..]))
+    }
+}
+
+impl ModuleResolver for ImportMapResolver {
+    fn resolve_module(&mut self, base: &str, name: &str) -> Result<String> {
+        let target = self
+            .lookup(name)
+            .ok_or_else(|| Error::new_resolving(base, name))?;
+
+        if Url::parse(&target).is_ok() {
+            return Ok(target);
+        }
+
+        let path = self.dir.join(&target);
+        path.to_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::new_resolving_message(base, name, "path is not valid UTF-8"))
+    }
+}
+
+impl_resolver!(ImportMapResolver);
@@ -1 +1,64 @@
+use rquickjs::{Error, Result};
+
+pub mod builtin;
+pub mod file;
 pub mod http;
+pub mod import_map;
+pub mod paths;
+pub mod payload;
+
+/// A [`rquickjs::loader::Resolver`] that does not need a JS context, so that
+/// imports can be resolved outside of the engine too, e.g. to walk a module
+/// graph
+pub trait ModuleResolver {
+    fn resolve_module(&mut self, base: &str, name: &str) -> Result<String>;
+}
+
+/// Implement [`rquickjs::loader::Resolver`] for a [`ModuleResolver`]
+macro_rules! impl_resolver {
+    ($ty:ty) => {
+        impl rquickjs::loader::Resolver for $ty {
+            fn resolve(
+                &mut self,
+                _ctx: &rquickjs::Ctx<'_>,
+                base: &str,
+                name: &str,
+            ) -> rquickjs::Result<String> {
+                $crate::resolver::ModuleResolver::resolve_module(self, base, name)
+            }
+        }
+    };
+}
+pub(crate) use impl_resolver;
+
+// Resolvers in a tuple are tried in order, the same way as with
+// `rquickjs::loader::Resolver`
+macro_rules! impl_tuple {
+    ($($t:ident)+) => {
+        impl<$($t: ModuleResolver),+> ModuleResolver for ($($t,)+) {
+            #[allow(non_snake_case)]
+            fn resolve_module(&mut self, base: &str, name: &str) -> Result<String> {
+                let mut messages = vec![];
+                let ($($t,)+) = self;
+                $(
+                    match $t.resolve_module(base, name) {
+                        Err(Error::Resolving { message, .. }) => messages.extend(message),
+                        result => return result,
+                    }
+                )+
+                Err(if messages.is_empty() {
+                    Error::new_resolving(base, name)
+                } else {
+                    Error::new_resolving_message(base, name, messages.join("\n"))
+                })
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A B);
+impl_tuple!(A B C);
+impl_tuple!(A B C D);
+impl_tuple!(A B C D E);
+impl_tuple!(A B C D E F);
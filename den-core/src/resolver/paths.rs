@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+use rquickjs::{Error, Result};
+
+use crate::{
+    config::CompilerOptions,
+    engine::relative_to_cwd,
+    resolver::{impl_resolver, ModuleResolver},
+};
+
+// Tried after the target itself, then as the index of a directory
+const EXTENSIONS: &[&str] = &["ts", "tsx", "js", "mjs", "jsx", "mjsx"];
+
+/// Resolves bare specifiers through the `paths` of the compiler options,
+/// like TypeScript does. An exact pattern wins over the ones with a `*`,
+/// among which the longest prefix wins
+#[derive(Debug, Clone, Default)]
+pub struct PathsResolver {
+    paths: Vec<(String, Vec<String>)>,
+    // Targets are relative to it
+    dir:   PathBuf,
+}
+
+impl PathsResolver {
+    pub fn new(options: &CompilerOptions) -> Self {
+        Self {
+            paths: options
+                .paths
+                .iter()
+                .flatten()
+                .map(|(pattern, targets)| (pattern.clone(), targets.clone()))
+                .collect(),
+            dir:   options.paths_base().to_path_buf(),
+        }
+    }
+
+    /// The targets `name` maps to with what the `*` stands for
+    fn lookup<'a>(&self, name: &'a str) -> Option<(&[String], &'a str)> {
+        if let Some((_, targets)) = self.paths.iter().find(|(x, _)| x == name) {
+            return Some((targets, ""));
+        }
+
+        self.paths
+            .iter()
+            .filter_map(|(pattern, targets)| {
+                let (prefix, suffix) = pattern.split_once('*')?;
+                let matched = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                Some((prefix.len(), targets, matched))
+            })
+            .max_by_key(|(len, ..)| *len)
+            .map(|(_, targets, matched)| (targets.as_slice(), matched))
+    }
+}
+
+/// The file a target refers to, trying extensions and index files the way
+/// TypeScript does
+fn find_module(path: &Path) -> Option<PathBuf> {
+    let with_extension = |path: PathBuf| {
+        EXTENSIONS.iter().map(move |x| {
+            let mut file = path.clone().into_os_string();
+            file.push(format!(".{x}"));
+            PathBuf::from(file)
+        })
+    };
+    std::iter::once(path.to_path_buf())
+        .chain(with_extension(path.to_path_buf()))
+        .chain(with_extension(path.join("index")))
+        .find(|x| x.is_file())
+}
+
+impl ModuleResolver for PathsResolver {
+    fn resolve_module(&mut self, base: &str, name: &str) -> Result<String> {
+        let (targets, matched) = self
+            .lookup(name)
+            .ok_or_else(|| Error::new_resolving(base, name))?;
+
+        let path = targets
+            .iter()
+            .find_map(|x| find_module(&self.dir.join(x.replacen('*', matched, 1))))
+            .ok_or_else(|| {
+                Error::new_resolving_message(base, name, "no file matches its paths mapping")
+            })?;
+        // Relative imports of the module are resolved by the file resolver
+        relative_to_cwd(&path)
+            .to_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::new_resolving_message(base, name, "path is not valid UTF-8"))
+    }
+}
+
+impl_resolver!(PathsResolver);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::fixture::Project;
+
+    #[test]
+    fn resolves_through_paths() {
+        let dir = Project::new(
+            "paths",
+            &[
+                ("src/util.ts", ""),
+                ("src/lib/index.tsx", ""),
+                ("vendor/exact.js", ""),
+            ],
+        );
+        let paths = [
+            ("@/*", vec!["missing/*", "src/*"]),
+            ("@/lib/*", vec!["vendor/*"]),
+            ("@/exact", vec!["vendor/exact.js"]),
+            ("none", vec!["missing"]),
+        ];
+        let mut resolver = PathsResolver::new(&CompilerOptions {
+            base_url: Some(dir.to_path_buf()),
+            paths: Some(BTreeMap::from_iter(paths.map(|(pattern, targets)| {
+                (
+                    pattern.to_string(),
+                    targets.into_iter().map(String::from).collect(),
+                )
+            }))),
+            ..Default::default()
+        });
+        let mut resolve = |name| resolver.resolve_module("main.ts", name);
+        let resolved = [
+            resolve("@/util"),
+            resolve("@/lib"),
+            resolve("@/lib/exact"),
+            resolve("@/exact"),
+            resolve("none"),
+            resolve("other"),
+        ];
+
+        let file = |x: &str| relative_to_cwd(&dir.join(x)).to_string_lossy().into_owned();
+        let [util, lib, prefixed, exact, none, other] = resolved;
+        assert_eq!(util.unwrap(), file("src/util.ts"));
+        assert_eq!(lib.unwrap(), file("src/lib/index.tsx"));
+        assert_eq!(prefixed.unwrap(), file("vendor/exact.js"));
+        assert_eq!(exact.unwrap(), file("vendor/exact.js"));
+        assert!(none
+            .unwrap_err()
+            .to_string()
+            .contains("no file matches its paths mapping"));
+        assert!(matches!(other, Err(Error::Resolving { message: None, .. })));
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use relative_path::RelativePath;
+use rquickjs::{Error, Result};
+
+use crate::{
+    resolver::{impl_resolver, ModuleResolver},
+    standalone::Payload,
+};
+
+// Tried after the name itself for modules imported with computed specifiers
+const EXTENSIONS: &[&str] = &["js", "mjs", "jsx", "mjsx", "ts", "tsx"];
+
+/// Resolves imports to the modules of a compiled program the same way they
+/// were resolved when compiling it. Without a payload nothing is resolved
+#[derive(Debug, Clone, Default)]
+pub struct PayloadResolver {
+    payload: Option<Arc<Payload>>,
+}
+
+impl PayloadResolver {
+    pub fn new(payload: Option<Arc<Payload>>) -> Self {
+        Self { payload }
+    }
+}
+
+impl ModuleResolver for PayloadResolver {
+    fn resolve_module(&mut self, base: &str, name: &str) -> Result<String> {
+        let Some(payload) = &self.payload else {
+            return Err(Error::new_resolving(base, name));
+        };
+        if let Some(resolved) = payload.resolutions.get(base).and_then(|x| x.get(name)) {
+            return Ok(resolved.clone());
+        }
+
+        let path = if name.starts_with("./") || name.starts_with("../") {
+            RelativePath::new(base)
+                .parent()
+                .unwrap_or(RelativePath::new(""))
+                .join_normalized(name)
+        } else {
+            RelativePath::new(name).normalize()
+        };
+        std::iter::once(path.to_string())
+            .chain(EXTENSIONS.iter().map(|x| format!("{path}.{x}")))
+            .find(|x| payload.modules.contains_key(x))
+            .ok_or_else(|| Error::new_resolving(base, name))
+    }
+}
+
+impl_resolver!(PayloadResolver);
@@ -0,0 +1,255 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+#[cfg(feature = "transpile")]
+use den_transpiler_swc::{EasySwcTranspiler, IsModule, MinifyOptions, TranspilerOptions};
+use derive_more::{Display, Error, From};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Permissions;
+#[cfg(feature = "transpile")]
+use crate::{
+    engine::{transpiler, EngineError},
+    graph::ModuleGraph,
+};
+
+/// Marks the end of an executable that carries a payload, right after the
+/// length of the payload
+const PAYLOAD_MAGIC: &[u8; 8] = b"denpayld";
+const TRAILER_LEN: u64 = 16;
+
+/// The modules of a compiled program, appended to a copy of the den binary
+/// which runs them instead of parsing its command line
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+    /// The module evaluated at startup
+    pub entry:       String,
+    /// Transpiled sources keyed by module name, remote ones included
+    pub modules:     BTreeMap<String, String>,
+    /// What each specifier of a module resolved to when compiling
+    pub resolutions: HashMap<String, HashMap<String, String>>,
+    pub permissions: Permissions,
+    /// Arguments that come before the ones given on the command line
+    pub args:        Vec<String>,
+}
+
+#[derive(Display, From, Error, Debug)]
+pub enum PayloadError {
+    #[from]
+    Io(io::Error),
+    #[display("invalid payload: {_0}")]
+    #[from]
+    Parse(serde_json::Error),
+    #[cfg(feature = "transpile")]
+    #[from]
+    Engine(EngineError),
+    #[display("cannot compile {_0}: {_1}")]
+    Module(String, #[error(not(source))] String),
+}
+
+impl Payload {
+    /// The payload of the running executable, if it is a compiled program
+    pub fn current() -> Result<Option<Self>, PayloadError> {
+        Self::read(&std::env::current_exe()?)
+    }
+
+    /// The payload at the end of the executable at `path`, if there is one
+    pub fn read(path: &Path) -> Result<Option<Self>, PayloadError> {
+        let mut file = File::open(path)?;
+        let Some(len) = payload_len(&mut file)? else {
+            return Ok(None);
+        };
+        file.seek(SeekFrom::End(-((len + TRAILER_LEN) as i64)))?;
+        Ok(Some(serde_json::from_reader(file.take(len))?))
+    }
+
+    /// Write a copy of the executable at `binary` with the payload appended
+    /// to `output`. A payload already in `binary` is replaced
+    pub fn write(&self, binary: &Path, output: &Path) -> Result<(), PayloadError> {
+        let mut binary = File::open(binary)?;
+        let end = binary.seek(SeekFrom::End(0))?;
+        let len = payload_len(&mut binary)?.map_or(end, |x| end - x - TRAILER_LEN);
+        binary.rewind()?;
+
+        let payload = serde_json::to_vec(self)?;
+        let mut file = File::create(output)?;
+        io::copy(&mut binary.take(len), &mut file)?;
+        file.write_all(&payload)?;
+        file.write_all(&(payload.len() as u64).to_le_bytes())?;
+        file.write_all(PAYLOAD_MAGIC)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(())
+    }
+
+    /// Gather the transpiled `entry` and the modules it imports, along with
+    /// `include`d ones that are only imported with computed specifiers,
+    /// into a payload, minified with `minify`
+    #[cfg(feature = "transpile")]
+    pub async fn compile(
+        config: &crate::config::Config,
+        entry: &Path,
+        include: &[impl AsRef<Path>],
+        minify: Option<MinifyOptions>,
+        args: Vec<String>,
+    ) -> Result<Self, PayloadError> {
+        let minifier = minify.map(|minify| {
+            EasySwcTranspiler::new(TranspilerOptions {
+                minify: Some(minify),
+                ..transpiler(config).options().clone()
+            })
+        });
+        let mut payload = Payload {
+            permissions: config.permissions.clone(),
+            args,
+            ..Default::default()
+        };
+
+        for (i, path) in std::iter::once(entry)
+            .chain(include.iter().map(AsRef::as_ref))
+            .enumerate()
+        {
+            let graph = ModuleGraph::build(config, path).await?;
+            if i == 0 {
+                payload.entry = graph.root;
+            }
+
+            for (name, module) in graph.modules {
+                if let Some(error) = module.error {
+                    return Err(PayloadError::Module(name, error));
+                }
+                let Some(mut code) = module.code else {
+                    continue;
+                };
+                if payload.modules.contains_key(&name) {
+                    continue;
+                }
+                if let (Some(minifier), Some((source, syntax))) = (&minifier, module.source) {
+                    code = tokio::task::block_in_place(|| {
+                        minifier.transpile(&name, &source, syntax, IsModule::Bool(true), false)
+                    })
+                    .map_err(|e| PayloadError::Module(name.clone(), e.to_string()))?
+                    .0;
+                }
+
+                let mut resolutions = HashMap::new();
+                for dependency in module.dependencies {
+                    match (dependency.resolved, dependency.error) {
+                        (Some(resolved), _) => {
+                            resolutions.insert(dependency.specifier, resolved);
+                        }
+                        // A dynamic import may never run, it fails when it does
+                        (None, _) if dependency.dynamic => {}
+                        (None, error) => {
+                            return Err(PayloadError::Module(name, error.unwrap_or_default()));
+                        }
+                    }
+                }
+                payload.modules.insert(name.clone(), code);
+                payload.resolutions.insert(name, resolutions);
+            }
+        }
+        Ok(payload)
+    }
+}
+
+/// The length of the payload at the end of `file`, if there is one
+fn payload_len(file: &mut File) -> io::Result<Option<u64>> {
+    if file.seek(SeekFrom::End(0))? < TRAILER_LEN {
+        return Ok(None);
+    }
+    let mut trailer = [0; TRAILER_LEN as usize];
+    file.seek(SeekFrom::End(-(TRAILER_LEN as i64)))?;
+    file.read_exact(&mut trailer)?;
+
+    let (len, magic) = trailer.split_at(8);
+    Ok((magic == PAYLOAD_MAGIC).then(|| u64::from_le_bytes(len.try_into().unwrap())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    /// A directory of its own for a test, with a fake binary in it
+    fn binary(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("den-payload-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("den");
+        fs::write(&binary, b"\x7fELF not really a binary").unwrap();
+        (dir, binary)
+    }
+
+    fn payload(entry: &str) -> Payload {
+        Payload {
+            entry: entry.to_string(),
+            modules: BTreeMap::from([(entry.to_string(), "console.log(1)".to_string())]),
+            resolutions: HashMap::from([(
+                entry.to_string(),
+                HashMap::from([("./a.ts".to_string(), "a.ts".to_string())]),
+            )]),
+            args: vec!["--flag".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn json(payload: &Payload) -> serde_json::Value {
+        serde_json::to_value(payload).unwrap()
+    }
+
+    #[test]
+    fn reads_what_was_written() {
+        let (dir, binary) = binary("round-trip");
+        let output = dir.join("app");
+        payload("main.ts").write(&binary, &output).unwrap();
+
+        let read = Payload::read(&output).unwrap().unwrap();
+        let bytes = fs::read(&output).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(json(&read), json(&payload("main.ts")));
+        assert!(bytes.starts_with(b"\x7fELF not really a binary"));
+        assert!(bytes.ends_with(PAYLOAD_MAGIC));
+    }
+
+    #[test]
+    fn replaces_the_payload_of_a_compiled_binary() {
+        let (dir, binary) = binary("replace");
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        payload("first.ts").write(&binary, &first).unwrap();
+        payload("second.ts").write(&first, &second).unwrap();
+
+        let read = Payload::read(&second).unwrap().unwrap();
+        let fresh = dir.join("fresh");
+        payload("second.ts").write(&binary, &fresh).unwrap();
+        let (second, fresh) = (fs::read(&second).unwrap(), fs::read(&fresh).unwrap());
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(read.entry, "second.ts");
+        assert_eq!(second, fresh);
+    }
+
+    #[test]
+    fn plain_binaries_have_no_payload() {
+        let (dir, binary) = binary("plain");
+        let empty = dir.join("empty");
+        fs::write(&empty, b"").unwrap();
+
+        let plain = Payload::read(&binary).unwrap();
+        let empty = Payload::read(&empty).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(plain.is_none());
+        assert!(empty.is_none());
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use den_transpiler_swc::{
+    EasySwcTranspiler, EasySwcTranspilerError, IsModule, MinifyOptions, SourceMap, Syntax,
+    TranspilerOptions,
+};
+use sha2::{Digest, Sha256};
+
+/// A transpiler that keeps what it outputs on disk, so that unchanged sources
+/// are transpiled once across runs. Entries are keyed on the hash of the
+/// source, its name, syntax, the transpiler options and the den version.
+/// Files are also looked up by their size and modification time, which
+/// skips reading and hashing them
+#[derive(Debug, Clone, Default)]
+pub struct CachedTranspiler {
+    transpiler:  Arc<EasySwcTranspiler>,
+    /// Where entries go, nothing is cached without one
+    dir:         Option<PathBuf>,
+    // What every entry depends on besides its source
+    fingerprint: String,
+}
+
+impl CachedTranspiler {
+    pub fn new(transpiler: Arc<EasySwcTranspiler>, dir: Option<PathBuf>) -> Self {
+        let fingerprint = fingerprint(transpiler.options());
+        Self {
+            transpiler,
+            dir,
+            fingerprint,
+        }
+    }
+
+    pub fn transpiler(&self) -> &Arc<EasySwcTranspiler> {
+        &self.transpiler
+    }
+
+    /// The transpiled file at `path`, named `name`, if it has not changed
+    /// since it was cached
+    pub fn cached(&self, name: &str, path: &Path, syntax: Syntax) -> Option<String> {
+        let dir = self.dir.as_ref()?;
+        let stat = fs::read_to_string(dir.join(self.stat_key(name, path, syntax))).ok()?;
+        let (stamp, key) = stat.rsplit_once(' ')?;
+        if stamp != FileStamp::of(path).ok()?.0 {
+            return None;
+        }
+        fs::read_to_string(dir.join(format!("{key}.js"))).ok()
+    }
+
+    /// Transpile `source` named `name` as a module, unless it is cached
+    /// already. With the `file` the source was read from, along with its
+    /// stamp from before it was read, the next lookup of the file with
+    /// [`Self::cached`] does not need the source
+    pub fn transpile(
+        &self,
+        name: &str,
+        file: Option<(&Path, FileStamp)>,
+        source: &str,
+        syntax: Syntax,
+        emit_sourcemap: bool,
+    ) -> Result<(String, Option<SourceMap>), EasySwcTranspilerError> {
+        let Some(dir) = &self.dir else {
+            return self.transpiler.transpile(
+                name,
+                source,
+                syntax,
+                IsModule::Bool(true),
+                emit_sourcemap,
+            );
+        };
+
+        let key = hash(&[
+            self.fingerprint.as_bytes(),
+            format!("{syntax:?}").as_bytes(),
+            name.as_bytes(),
+            source.as_bytes(),
+        ]);
+        let entry = dir.join(format!("{key}.js"));
+        let map = dir.join(format!("{key}.js.map"));
+        let output = match fs::read_to_string(&entry) {
+            Ok(code) => {
+                let map = emit_sourcemap
+                    .then(|| SourceMap::from_slice(&fs::read(&map).ok()?).ok())
+                    .flatten();
+                (code, map)
+            }
+            Err(_) => {
+                // The map is cached whether asked for or not, for the next
+                // time it is
+                let (code, source_map) =
+                    self.transpiler
+                        .transpile(name, source, syntax, IsModule::Bool(true), true)?;
+                // A cache that cannot be written to only makes things slower
+                let _ = fs::create_dir_all(dir).and_then(|_| {
+                    if let Some(source_map) = &source_map {
+                        let mut buf = vec![];
+                        source_map.to_writer(&mut buf).map_err(io::Error::other)?;
+                        write_atomic(&map, &buf)?;
+                    }
+                    write_atomic(&entry, code.as_bytes())
+                });
+                (code, source_map.filter(|_| emit_sourcemap))
+            }
+        };
+
+        if let Some((path, FileStamp(stamp))) = file {
+            let _ = write_atomic(
+                &dir.join(self.stat_key(name, path, syntax)),
+                format!("{stamp} {key}").as_bytes(),
+            );
+        }
+        Ok(output)
+    }
+
+    fn stat_key(&self, name: &str, path: &Path, syntax: Syntax) -> String {
+        // The same name may refer to files of different projects
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let key = hash(&[
+            self.fingerprint.as_bytes(),
+            format!("{syntax:?}").as_bytes(),
+            name.as_bytes(),
+            path.as_os_str().as_encoded_bytes(),
+        ]);
+        format!("{key}.stat")
+    }
+}
+
+fn hash(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        // Lengths keep the boundaries between parts unambiguous
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// What every entry depends on besides its source. Options are listed one
+/// by one, so that a new one cannot be forgotten
+fn fingerprint(options: &TranspilerOptions) -> String {
+    let TranspilerOptions {
+        jsx_runtime,
+        jsx_development,
+        jsx_factory,
+        jsx_fragment_factory,
+        jsx_import_source,
+        legacy_decorators,
+        emit_decorator_metadata,
+        assign_class_fields,
+        verbatim_module_syntax,
+        minify,
+        target,
+    } = options;
+    let minify = minify.map(
+        |MinifyOptions {
+             keep_fn_names,
+             keep_class_names,
+         }| [keep_fn_names as u8, keep_class_names as u8],
+    );
+    // `None` and `Some("")` differ
+    let text = |x: &Option<String>| {
+        x.as_ref()
+            .map_or(vec![0], |x| [b"=", x.as_bytes()].concat())
+    };
+    hash(&[
+        env!("CARGO_PKG_VERSION").as_bytes(),
+        &[
+            *jsx_runtime as u8,
+            *jsx_development as u8,
+            *legacy_decorators as u8,
+            *emit_decorator_metadata as u8,
+            *assign_class_fields as u8,
+            *verbatim_module_syntax as u8,
+        ],
+        &text(jsx_factory),
+        &text(jsx_fragment_factory),
+        &text(jsx_import_source),
+        minify.as_ref().map_or(&[][..], |x| &x[..]),
+        &target.map_or(vec![], |x| vec![x as u8]),
+    ])
+}
+
+/// The size and modification time of a file, which change along with its
+/// content. Taken before the file is read, so that a change made while it
+/// is read does not go unnoticed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStamp(String);
+
+impl FileStamp {
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?;
+        Ok(Self(format!("{} {}", metadata.len(), modified.as_nanos())))
+    }
+}
+
+/// Write a file under a temporary name first, so that other processes never
+/// read it half written
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc, thread, time::Duration};
+
+    use den_transpiler_swc::{EasySwcTranspiler, Syntax, TranspilerOptions};
+
+    use super::{CachedTranspiler, FileStamp};
+    use crate::fixture::Project;
+
+    fn cached_transpiler(project: &Project, options: TranspilerOptions) -> CachedTranspiler {
+        CachedTranspiler::new(
+            Arc::new(EasySwcTranspiler::new(options)),
+            Some(project.join("cache")),
+        )
+    }
+
+    /// Read `main.ts` and transpile it, the way the loaders do
+    fn load(project: &Project, transpiler: &CachedTranspiler) -> String {
+        let path = project.join("main.ts");
+        let stamp = FileStamp::of(&path).unwrap();
+        let source = fs::read_to_string(&path).unwrap();
+        let (code, _) = transpiler
+            .transpile("main.ts", Some((&path, stamp)), &source, syntax(), false)
+            .unwrap();
+        code
+    }
+
+    fn syntax() -> Syntax {
+        Syntax::Typescript(Default::default())
+    }
+
+    #[test]
+    fn unchanged_files_are_cached() {
+        let project = Project::new("hit", &[("main.ts", "const x: number = 1")]);
+        let main = project.join("main.ts");
+        let transpiler = cached_transpiler(&project, Default::default());
+
+        assert_eq!(transpiler.cached("main.ts", &main, syntax()), None);
+        let code = load(&project, &transpiler);
+        assert_eq!(
+            transpiler.cached("main.ts", &main, syntax()),
+            Some(code.clone())
+        );
+        // Across runs too
+        let transpiler = cached_transpiler(&project, Default::default());
+        assert_eq!(transpiler.cached("main.ts", &main, syntax()), Some(code));
+    }
+
+    #[test]
+    fn changes_invalidate_entries() {
+        let project = Project::new("invalidation", &[("main.ts", "const x: number = 1")]);
+        let main = project.join("main.ts");
+        let transpiler = cached_transpiler(&project, Default::default());
+        load(&project, &transpiler);
+
+        // A change of the options
+        let minified = cached_transpiler(
+            &project,
+            TranspilerOptions {
+                minify: Some(Default::default()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(minified.cached("main.ts", &main, syntax()), None);
+
+        // A change of the file
+        project.write("main.ts", "const y: number = 2");
+        assert_eq!(transpiler.cached("main.ts", &main, syntax()), None);
+        assert!(load(&project, &transpiler).contains("const y = 2"));
+    }
+
+    #[test]
+    fn changes_while_reading_invalidate_entries() {
+        let project = Project::new("race", &[("main.ts", "const x: number = 1")]);
+        let main = project.join("main.ts");
+        let transpiler = cached_transpiler(&project, Default::default());
+
+        let stamp = FileStamp::of(&main).unwrap();
+        let source = fs::read_to_string(&main).unwrap();
+        // Modification times may be coarse
+        thread::sleep(Duration::from_millis(20));
+        project.write("main.ts", "const x: number = 2");
+        transpiler
+            .transpile("main.ts", Some((&main, stamp)), &source, syntax(), false)
+            .unwrap();
+
+        assert_eq!(transpiler.cached("main.ts", &main, syntax()), None);
+    }
+
+    #[test]
+    fn unwritable_caches_still_transpile() {
+        let project = Project::new("unwritable", &[("main.ts", "const x: number = 1")]);
+        let main = project.join("main.ts");
+        // A file where the directory should be
+        project.write("cache", "");
+        let transpiler = cached_transpiler(&project, Default::default());
+
+        assert!(load(&project, &transpiler).contains("const x = 1"));
+        assert_eq!(transpiler.cached("main.ts", &main, syntax()), None);
+    }
+}
@@ -0,0 +1,8 @@
+export {
+  Fragment,
+  jsx,
+  jsxs,
+  jsxDEV,
+  renderToStream,
+  renderToString,
+} from "den:jsx";
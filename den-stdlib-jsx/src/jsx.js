@@ -0,0 +1,228 @@
+const ELEMENT = Symbol.for("den.jsx.element");
+
+// Marks where a stream should hand out what it has, before waiting on an
+// async component
+const FLUSH = Symbol("flush");
+
+// How much a stream buffers before handing it out anyway
+const CHUNK_SIZE = 16 * 1024;
+
+export const Fragment = Symbol.for("den.jsx.fragment");
+
+const VOID_ELEMENTS = new Set([
+  "area",
+  "base",
+  "br",
+  "col",
+  "embed",
+  "hr",
+  "img",
+  "input",
+  "link",
+  "meta",
+  "param",
+  "source",
+  "track",
+  "wbr",
+]);
+
+const ATTRIBUTE_NAMES = {
+  className: "class",
+  htmlFor: "for",
+  httpEquiv: "http-equiv",
+  acceptCharset: "accept-charset",
+};
+
+// Props that never end up as attributes
+const RESERVED_PROPS = new Set([
+  "children",
+  "key",
+  "ref",
+  "dangerouslySetInnerHTML",
+]);
+
+const TAG_NAME = /^[a-zA-Z][a-zA-Z0-9:._-]*$/;
+const ATTRIBUTE_NAME = /^[^\s"'<>\/=\x00-\x1f\x7f]+$/;
+
+const ESCAPES = {
+  "&": "&amp;",
+  "<": "&lt;",
+  ">": "&gt;",
+  '"': "&quot;",
+  "'": "&#39;",
+};
+
+function escape(value) {
+  return String(value).replace(/[&<>"']/g, (c) => ESCAPES[c]);
+}
+
+/** Create an element, `children` are part of `props` */
+export function jsx(type, props, key) {
+  return {
+    $$typeof: ELEMENT,
+    type,
+    props: props ?? {},
+    key: key ?? null,
+  };
+}
+
+/** Create an element with static children */
+export const jsxs = jsx;
+
+/** Create an element along with where it comes from in the source */
+export function jsxDEV(type, props, key, _isStatic, source) {
+  const element = jsx(type, props, key);
+  element.source = source;
+  return element;
+}
+
+/** Create an element the classic way, with the children as arguments */
+export function createElement(type, config, ...children) {
+  const { key, ...props } = config ?? {};
+  if (children.length === 1) {
+    props.children = children[0];
+  } else if (children.length > 1) {
+    props.children = children;
+  }
+  return jsx(type, props, key);
+}
+
+export const h = createElement;
+
+export function isValidElement(value) {
+  return typeof value === "object" && value !== null &&
+    value.$$typeof === ELEMENT;
+}
+
+function isThenable(value) {
+  return typeof value?.then === "function";
+}
+
+function styleToString(style) {
+  return Object.entries(style)
+    .filter(([, value]) => value != null && value !== false)
+    .map(([name, value]) => {
+      const property = name.startsWith("--")
+        ? name
+        : name.replace(/[A-Z]/g, (c) => `-${c.toLowerCase()}`);
+      return `${property}:${value}`;
+    })
+    .join(";");
+}
+
+function renderAttributes(props) {
+  let html = "";
+  for (const [name, value] of Object.entries(props)) {
+    if (
+      RESERVED_PROPS.has(name) || value == null || value === false ||
+      typeof value === "function" || typeof value === "symbol"
+    ) {
+      continue;
+    }
+    if (!ATTRIBUTE_NAME.test(name)) {
+      throw new TypeError(`invalid attribute name: ${name}`);
+    }
+
+    const attribute = ATTRIBUTE_NAMES[name] ?? name;
+    if (value === true) {
+      html += ` ${attribute}`;
+    } else if (name === "style" && typeof value === "object") {
+      html += ` style="${escape(styleToString(value))}"`;
+    } else {
+      html += ` ${attribute}="${escape(value)}"`;
+    }
+  }
+  return html;
+}
+
+async function* render(node) {
+  if (node == null || typeof node === "boolean") {
+    return;
+  }
+  if (typeof node !== "object" && typeof node !== "function") {
+    yield escape(node);
+    return;
+  }
+  if (isThenable(node)) {
+    yield FLUSH;
+    yield* render(await node);
+    return;
+  }
+  if (!isValidElement(node)) {
+    if (typeof node[Symbol.iterator] === "function") {
+      for (const child of node) {
+        yield* render(child);
+      }
+      return;
+    }
+    throw new TypeError(`cannot render ${Object.prototype.toString.call(node)}`);
+  }
+
+  const { type, props } = node;
+  if (type === Fragment) {
+    yield* render(props.children);
+    return;
+  }
+  if (typeof type === "function") {
+    yield* render(type(props));
+    return;
+  }
+  if (typeof type !== "string" || !TAG_NAME.test(type)) {
+    throw new TypeError(`invalid element type: ${String(type)}`);
+  }
+
+  const inner = props.dangerouslySetInnerHTML;
+  if (inner != null && props.children != null) {
+    throw new TypeError(
+      `<${type}> cannot have both children and dangerouslySetInnerHTML`,
+    );
+  }
+
+  yield `<${type}${renderAttributes(props)}>`;
+  if (VOID_ELEMENTS.has(type.toLowerCase())) {
+    if (props.children != null || inner != null) {
+      throw new TypeError(`<${type}> is a void element and cannot have children`);
+    }
+    return;
+  }
+  if (inner != null) {
+    yield String(inner.__html ?? "");
+  } else {
+    yield* render(props.children);
+  }
+  yield `</${type}>`;
+}
+
+/** Render an element to HTML, waiting for every async component */
+export async function renderToString(node) {
+  let html = "";
+  for await (const chunk of render(node)) {
+    if (chunk !== FLUSH) {
+      html += chunk;
+    }
+  }
+  return html;
+}
+
+/**
+ * Render an element to HTML in chunks, handing out what is ready whenever an
+ * async component has to be waited on
+ */
+export async function* renderToStream(node) {
+  let buffer = "";
+  for await (const chunk of render(node)) {
+    if (chunk !== FLUSH) {
+      buffer += chunk;
+      if (buffer.length < CHUNK_SIZE) {
+        continue;
+      }
+    }
+    if (buffer) {
+      yield buffer;
+      buffer = "";
+    }
+  }
+  if (buffer) {
+    yield buffer;
+  }
+}
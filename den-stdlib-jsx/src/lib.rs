@@ -0,0 +1,12 @@
+// Elements are plain objects and rendering is string concatenation with a
+// lot of awaiting in between, which is far simpler to write in JavaScript
+
+/// `den:jsx`, the element factories and the HTML renderers
+pub const JSX: &str = include_str!("jsx.js");
+
+/// `den:jsx/jsx-runtime`, imported by the automatic JSX runtime
+pub const JSX_RUNTIME: &str = include_str!("jsx-runtime.js");
+
+/// `den:jsx/jsx-dev-runtime`, imported by the automatic JSX runtime in
+/// development mode
+pub const JSX_DEV_RUNTIME: &str = include_str!("jsx-dev-runtime.js");
@@ -8,5 +8,12 @@ pub mod socket_addr;
     rename_types = "PascalCase"
 )]
 pub mod networking {
+    use rquickjs::{module::Exports, Ctx, Result};
+
     pub use crate::socket::{TcpListenerWrapper as TcpListener, TcpStreamWrapper as TcpStream};
+
+    #[qjs(evaluate)]
+    pub fn evaluate<'js>(ctx: &Ctx<'js>, _: &Exports<'js>) -> Result<()> {
+        crate::socket::define_disposal(ctx)
+    }
 }
@@ -1,14 +1,20 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use den_stdlib_io::{AsyncReadWrapper, AsyncWriteWrapper};
 use derivative::Derivative;
 use derive_more::{Deref, DerefMut, From, Into};
 use either::Either;
-use rquickjs::{class::Trace, convert::List, Ctx, Error, JsLifetime, Result, TypedArray};
+use rquickjs::{
+    class::Trace,
+    convert::List,
+    prelude::{Async, This},
+    Class, Ctx, Error, Function, JsLifetime, Result, TypedArray,
+};
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::RwLock,
 };
+use tokio_util::sync::CancellationToken;
 
 use crate::socket_addr::SocketAddrWrapper;
 
@@ -66,12 +72,55 @@ impl TcpStreamWrapper {
     }
 }
 
-#[derive(Trace, JsLifetime, Derivative, From, Into, Deref, DerefMut)]
+/// Shut a stream down at the end of an `await using` declaration, and close
+/// a listener, failing the `accept` calls still waiting on it
+pub(crate) fn define_disposal(ctx: &Ctx<'_>) -> Result<()> {
+    if let Some(prototype) = Class::<TcpStreamWrapper>::prototype(ctx)? {
+        let dispose = Function::new(
+            ctx.clone(),
+            Async(|this: This<TcpStreamWrapper>| {
+                async move {
+                    // The peer may have closed the stream already
+                    let _ = this.0.shutdown().await;
+                    Ok::<_, Error>(())
+                }
+            }),
+        )?;
+        prototype.set(den_utils::symbol::async_dispose(ctx)?, dispose)?;
+    }
+    if let Some(prototype) = Class::<TcpListenerWrapper>::prototype(ctx)? {
+        let dispose = Function::new(
+            ctx.clone(),
+            Async(|this: This<TcpListenerWrapper>| {
+                async move {
+                    this.0.closed.cancel();
+                    this.0.listener.write().await.take();
+                    Ok::<_, Error>(())
+                }
+            }),
+        )?;
+        prototype.set(den_utils::symbol::async_dispose(ctx)?, dispose)?;
+    }
+    Ok(())
+}
+
+#[derive(Trace, JsLifetime, Derivative)]
 #[derivative(Clone, Debug)]
 #[rquickjs::class(rename = "TcpListener")]
 pub struct TcpListenerWrapper {
     #[qjs(skip_trace)]
-    listener: Arc<TcpListener>,
+    listener: Arc<RwLock<Option<TcpListener>>>,
+    #[qjs(skip_trace)]
+    closed:   CancellationToken,
+}
+
+impl From<TcpListener> for TcpListenerWrapper {
+    fn from(listener: TcpListener) -> Self {
+        Self {
+            listener: Arc::new(RwLock::new(Some(listener))),
+            closed:   CancellationToken::new(),
+        }
+    }
 }
 
 #[rquickjs::methods]
@@ -81,18 +130,74 @@ impl TcpListenerWrapper {
 
     #[qjs(get, enumerable)]
     pub fn local_addr(&self) -> Result<SocketAddrWrapper> {
-        Ok(self.deref().local_addr()?.into())
+        let this = self.listener.try_read().map_err(|_| Error::Unknown)?;
+        let listener = this.as_ref().ok_or_else(closed)?;
+        Ok(listener.local_addr()?.into())
     }
 
     pub async fn accept(self) -> Result<List<(TcpStreamWrapper, SocketAddrWrapper)>> {
-        let (stream, addr) = self.deref().accept().await?;
+        let this = self.listener.read().await;
+        let listener = this.as_ref().ok_or_else(closed)?;
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = self.closed.cancelled() => return Err(closed()),
+        };
         let stream = Arc::new(RwLock::new(stream));
         Ok(List((stream.into(), addr.into())))
     }
 
     #[qjs(static)]
     pub async fn listen(addr: String) -> Result<Self> {
-        let listener = TcpListener::bind(addr).await?;
-        Ok(Arc::new(listener).into())
+        Ok(TcpListener::bind(addr).await?.into())
+    }
+}
+
+fn closed() -> Error {
+    io::Error::new(io::ErrorKind::NotConnected, "the listener is closed").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use rquickjs::{
+        async_with,
+        loader::{BuiltinResolver, ModuleLoader},
+        AsyncContext, AsyncRuntime, CatchResultExt, Module,
+    };
+
+    #[tokio::test]
+    async fn shuts_down_streams_and_closes_listeners_when_disposed() {
+        let rt = AsyncRuntime::new().unwrap();
+        rt.set_loader(
+            BuiltinResolver::default().with_module("den:networking"),
+            ModuleLoader::default().with_module("den:networking", crate::js_networking),
+        )
+        .await;
+        let context = AsyncContext::full(&rt).await.unwrap();
+        let result = async_with!(context => |ctx| {
+            let source = concat!(
+                "import { TcpListener, TcpStream } from 'den:networking';\n",
+                "const listener = await TcpListener.listen('127.0.0.1:0');\n",
+                "const [[server], client] = await Promise.all([\n",
+                "  listener.accept(),\n",
+                "  TcpStream.connect(listener.local_addr.toString()),\n",
+                "]);\n",
+                "await client[Symbol.asyncDispose]();\n",
+                "const read = await server.read_to_string();\n",
+                "const pending = listener.accept();\n",
+                "await listener[Symbol.asyncDispose]();\n",
+                "const error = await pending.catch((e) => e.message);\n",
+                "globalThis.result = [read, error];\n",
+            );
+            Module::evaluate(ctx.clone(), "test", source)
+                .catch(&ctx)
+                .unwrap()
+                .into_future::<()>()
+                .await
+                .catch(&ctx)
+                .unwrap();
+            ctx.globals().get::<_, Vec<String>>("result").unwrap()
+        })
+        .await;
+        assert_eq!(result, ["", "IO Error: the listener is closed"]);
     }
 }
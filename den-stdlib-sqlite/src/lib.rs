@@ -7,7 +7,8 @@ use derive_more::{
 };
 use either::Either;
 use rquickjs::{
-    class::Trace, prelude::*, Array, BigInt, Ctx, Exception, JsLifetime, Object, Result, Value,
+    class::Trace, prelude::*, Array, BigInt, Class, Ctx, Exception, Function, JsLifetime, Object,
+    Result, Value,
 };
 use rusqlite::Statement;
 
@@ -105,6 +106,24 @@ impl Connection {
     }
 }
 
+/// Close a connection at the end of a `using` declaration, unless it was
+/// closed already
+fn define_disposal(ctx: &Ctx<'_>) -> Result<()> {
+    let Some(prototype) = Class::<Connection>::prototype(ctx)? else {
+        return Ok(());
+    };
+    let dispose = Function::new(ctx.clone(), |this: This<Connection>, ctx: Ctx<'_>| {
+        match this.0.conn.borrow_mut().take() {
+            Some(conn) => {
+                conn.close()
+                    .map_err(|(_, e)| Exception::throw_internal(&ctx, &format!("{e}")))
+            }
+            None => Ok(()),
+        }
+    })?;
+    prototype.set(den_utils::symbol::dispose(ctx)?, dispose)
+}
+
 fn bind_parameters_from_rquickjs_object<'js>(
     stmt: &mut Statement<'_>,
     params: Object<'js>,
@@ -254,5 +273,45 @@ enum QueryRowError {
     rename_types = "PascalCase"
 )]
 pub mod sqlite {
+    use rquickjs::{module::Exports, Ctx, Result};
+
     pub use super::Connection;
+
+    #[qjs(evaluate)]
+    pub fn evaluate<'js>(ctx: &Ctx<'js>, _: &Exports<'js>) -> Result<()> {
+        super::define_disposal(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rquickjs::{
+        loader::{BuiltinResolver, ModuleLoader},
+        CatchResultExt, Context, Module, Runtime,
+    };
+
+    #[test]
+    fn closes_connections_when_disposed() {
+        let rt = Runtime::new().unwrap();
+        rt.set_loader(
+            BuiltinResolver::default().with_module("den:sqlite"),
+            ModuleLoader::default().with_module("den:sqlite", super::js_sqlite),
+        );
+        let context = Context::full(&rt).unwrap();
+        let result = context.with(|ctx| {
+            let source = concat!(
+                "import { Connection } from 'den:sqlite';\n",
+                "const conn = Connection.open_in_memory();\n",
+                "conn[Symbol.dispose]();\n",
+                "conn[Symbol.dispose]();\n",
+                "try { conn.execute('select 1'); } catch (e) { globalThis.result = e.message; }\n",
+            );
+            Module::evaluate(ctx.clone(), "test", source)
+                .and_then(|promise| promise.finish::<()>())
+                .catch(&ctx)
+                .unwrap();
+            ctx.globals().get::<_, String>("result").unwrap()
+        });
+        assert_eq!(result, "already closed");
+    }
 }
@@ -0,0 +1,213 @@
+use den_transpiler_swc::{
+    get_best_transpiling, infer_transpile_syntax_by_extension, Diagnostic, EasySwcTranspiler,
+    EasySwcTranspilerError, EsVersion, IsModule, JsxRuntime, MinifyOptions, Syntax,
+    TranspilerOptions,
+};
+use den_utils::serde_json::SerdeJsonValue;
+use rquickjs::{prelude::Opt, Array, Ctx, Exception, IntoJs, Object, Result, Value};
+
+/// The name sources are given when the options have no `filename`
+const DEFAULT_NAME: &str = "<transpile>";
+
+/// Transpile `source` into JavaScript, returning the `code`, its source `map`
+/// as JSON when asked for and the `diagnostics`. Code that does not compile
+/// gives a `null` code along with the errors instead of throwing
+#[rquickjs::function]
+pub fn transpile<'js>(
+    ctx: Ctx<'js>,
+    source: String,
+    options: Opt<Object<'js>>,
+) -> Result<Object<'js>> {
+    let options = options_or_default(&ctx, options)?;
+    let syntax = syntax(&ctx, &options)?;
+    let module = options.get::<_, Option<bool>>("module")?.unwrap_or(true);
+    let source_map = options
+        .get::<_, Option<bool>>("sourceMap")?
+        .unwrap_or_default();
+    let name = options
+        .get::<_, Option<String>>("filename")?
+        .unwrap_or_else(|| DEFAULT_NAME.to_string());
+
+    let transpiler = EasySwcTranspiler::new(transpiler_options(&ctx, &options)?);
+    let result = transpiler.transpile_with_warnings(
+        &name,
+        &source,
+        syntax,
+        IsModule::Bool(module),
+        source_map,
+    );
+
+    let output = Object::new(ctx.clone())?;
+    match result {
+        Ok((code, map, warnings)) => {
+            let map = match map {
+                Some(map) => {
+                    let mut buf = vec![];
+                    map.to_writer(&mut buf)
+                        .map_err(|e| Exception::throw_internal(&ctx, &e.to_string()))?;
+                    String::from_utf8_lossy(&buf).into_js(&ctx)?
+                }
+                None => Value::new_null(ctx.clone()),
+            };
+            output.set("code", code)?;
+            output.set("map", map)?;
+            output.set("diagnostics", diagnostics(&ctx, &source, &warnings.0)?)?;
+        }
+        Err(EasySwcTranspilerError::Diagnostics(errors)) => {
+            output.set("code", Value::new_null(ctx.clone()))?;
+            output.set("map", Value::new_null(ctx.clone()))?;
+            output.set("diagnostics", diagnostics(&ctx, &source, &errors.0)?)?;
+        }
+        Err(e) => return Err(Exception::throw_internal(&ctx, &e.to_string())),
+    }
+    Ok(output)
+}
+
+/// Parse `source` into an ESTree AST, with TypeScript and JSX nodes the way
+/// `typescript-estree` has them. Throws a `SyntaxError` when it does not
+/// parse
+#[rquickjs::function]
+pub fn parse<'js>(ctx: Ctx<'js>, source: String, options: Opt<Object<'js>>) -> Result<Value<'js>> {
+    let options = options_or_default(&ctx, options)?;
+    let syntax = syntax(&ctx, &options)?;
+    let module = options.get::<_, Option<bool>>("module")?.unwrap_or(true);
+    let name = options
+        .get::<_, Option<String>>("filename")?
+        .unwrap_or_else(|| DEFAULT_NAME.to_string());
+
+    let ast = EasySwcTranspiler::default()
+        .parse(&name, &source, syntax, IsModule::Bool(module))
+        .map_err(|e| Exception::throw_syntax(&ctx, &e.to_string()))?;
+    rquickjs::IntoJs::into_js(SerdeJsonValue(ast), &ctx)
+}
+
+fn options_or_default<'js>(ctx: &Ctx<'js>, options: Opt<Object<'js>>) -> Result<Object<'js>> {
+    match options.0 {
+        Some(options) => Ok(options),
+        None => Object::new(ctx.clone()),
+    }
+}
+
+/// The `syntax` of the options by extension, the richest one by default
+fn syntax(ctx: &Ctx<'_>, options: &Object<'_>) -> Result<Syntax> {
+    let syntax = options
+        .get::<_, Option<String>>("syntax")?
+        .unwrap_or_else(|| get_best_transpiling().to_string());
+    infer_transpile_syntax_by_extension(&syntax)
+        .ok_or_else(|| Exception::throw_type(ctx, &format!("unsupported syntax {syntax}")))
+}
+
+fn transpiler_options(ctx: &Ctx<'_>, options: &Object<'_>) -> Result<TranspilerOptions> {
+    let jsx = options.get::<_, Option<String>>("jsx")?;
+    let (jsx_runtime, jsx_development) = match jsx.as_deref() {
+        None | Some("react") => (JsxRuntime::Classic, false),
+        Some("react-jsx") => (JsxRuntime::Automatic, false),
+        Some("react-jsxdev") => (JsxRuntime::Automatic, true),
+        Some(jsx) => {
+            return Err(Exception::throw_type(
+                ctx,
+                &format!("unsupported jsx {jsx}"),
+            ))
+        }
+    };
+
+    let target = match options.get::<_, Option<String>>("target")? {
+        Some(target) => {
+            Some(es_version(&target).ok_or_else(|| {
+                Exception::throw_type(ctx, &format!("unsupported target {target}"))
+            })?)
+        }
+        None => None,
+    };
+
+    // `true` or what to keep of the names
+    let minify = options.get::<_, Value>("minify")?;
+    let minify = match minify.as_object() {
+        Some(minify) => {
+            Some(MinifyOptions {
+                keep_fn_names:    minify
+                    .get::<_, Option<bool>>("keepFnames")?
+                    .unwrap_or_default(),
+                keep_class_names: minify
+                    .get::<_, Option<bool>>("keepClassnames")?
+                    .unwrap_or_default(),
+            })
+        }
+        None => minify.as_bool().unwrap_or_default().then(Default::default),
+    };
+
+    Ok(TranspilerOptions {
+        jsx_runtime,
+        jsx_development,
+        jsx_factory: options.get("jsxFactory")?,
+        jsx_fragment_factory: options.get("jsxFragmentFactory")?,
+        jsx_import_source: options.get("jsxImportSource")?,
+        minify,
+        target,
+        ..Default::default()
+    })
+}
+
+fn es_version(target: &str) -> Option<EsVersion> {
+    Some(match &*target.to_ascii_lowercase() {
+        "es3" => EsVersion::Es3,
+        "es5" => EsVersion::Es5,
+        "es2015" | "es6" => EsVersion::Es2015,
+        "es2016" => EsVersion::Es2016,
+        "es2017" => EsVersion::Es2017,
+        "es2018" => EsVersion::Es2018,
+        "es2019" => EsVersion::Es2019,
+        "es2020" => EsVersion::Es2020,
+        "es2021" => EsVersion::Es2021,
+        "es2022" => EsVersion::Es2022,
+        "esnext" => EsVersion::EsNext,
+        _ => return None,
+    })
+}
+
+/// Diagnostics as objects, with offsets in UTF-16 code units like the
+/// indices of the source string
+fn diagnostics<'js>(
+    ctx: &Ctx<'js>,
+    source: &str,
+    diagnostics: &[Diagnostic],
+) -> Result<Array<'js>> {
+    let offset = |byte: usize| {
+        source
+            .get(..byte)
+            .map_or(byte, |x| x.encode_utf16().count())
+    };
+
+    let array = Array::new(ctx.clone())?;
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        let object = Object::new(ctx.clone())?;
+        object.set("file", diagnostic.file.as_str())?;
+        object.set("start", offset(diagnostic.span.start))?;
+        object.set("end", offset(diagnostic.span.end))?;
+        object.set("line", diagnostic.line)?;
+        object.set("column", diagnostic.column)?;
+        object.set("severity", diagnostic.severity.to_string())?;
+        object.set("message", diagnostic.message.as_str())?;
+        object.set("notes", diagnostic.notes.clone())?;
+        array.set(i, object)?;
+    }
+    Ok(array)
+}
+
+#[rquickjs::module]
+pub mod transpiler {
+    use rquickjs::{module::Exports, Ctx, IntoJs, Result};
+
+    #[qjs(declare)]
+    pub fn declare(declare: &rquickjs::module::Declarations) -> Result<()> {
+        declare.declare("transpile")?.declare("parse")?;
+        Ok(())
+    }
+
+    #[qjs(evaluate)]
+    pub fn evaluate<'js>(ctx: &Ctx<'js>, e: &Exports<'js>) -> Result<()> {
+        e.export("transpile", super::js_transpile.into_js(ctx)?)?
+            .export("parse", super::js_parse.into_js(ctx)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1099 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use swc_common::{comments::Comments, FileName, Mark, Span, SyntaxContext, DUMMY_SP};
+use swc_config::IsModule;
+use swc_ecma_ast::{
+    ArrowExpr, AssignOp, AssignTarget, AwaitExpr, BinaryOp, BindingIdent, BlockStmt,
+    BlockStmtOrExpr, CallExpr, Callee, ClassDecl, ClassExpr, Decl, DefaultDecl, ExportDecl,
+    ExportNamedSpecifier, ExportSpecifier, Expr, ExprOrSpread, FnDecl, Function, GetterProp, Id,
+    Ident, IdentName, ImportDecl, ImportNamedSpecifier, ImportSpecifier, ImportStarAsSpecifier,
+    KeyValueProp, Lit, MemberExpr, MemberProp, Module, ModuleDecl, ModuleExportName, ModuleItem,
+    NamedExport, Null, ObjectLit, Program, Prop, PropName, PropOrSpread, ReturnStmt,
+    SimpleAssignTarget, Stmt, Str, VarDecl, VarDeclKind, VarDeclarator,
+};
+use swc_ecma_parser::Syntax;
+use swc_ecma_transforms_base::{fixer::fixer, helpers::inject_helpers, hygiene::hygiene};
+use swc_ecma_utils::{find_pat_ids, ExprFactory};
+use swc_ecma_visit::{VisitMut, VisitMutWith};
+
+use crate::{EasySwcTranspiler, EasySwcTranspilerError, MinifyOptions, Session};
+
+/// A module to put in a bundle
+#[derive(Debug, Clone)]
+pub struct BundleModule {
+    /// What other modules resolve to when importing it, also the name of its
+    /// source in the source map
+    pub name:         String,
+    pub source:       String,
+    pub syntax:       Syntax,
+    /// What the specifiers the module imports resolve to. Imports of modules
+    /// that are not part of the bundle are kept as they are
+    pub dependencies: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BundleOptions {
+    pub minify:     Option<MinifyOptions>,
+    pub source_map: bool,
+}
+
+enum Import {
+    Named(String, String),
+    Namespace(String),
+}
+
+enum Export {
+    Local(Id),
+    Reexport(String, String),
+    Namespace(String),
+}
+
+/// What an imported or exported name ends up referring to in the bundle
+enum Binding {
+    Ident(Id),
+    Namespace(String),
+}
+
+/// A module taken apart into the statements that go into the bundle and the
+/// names it imports and exports
+#[derive(Default)]
+struct Linked {
+    body:         Vec<Stmt>,
+    imports:      HashMap<Id, Import>,
+    exports:      HashMap<String, Export>,
+    // Modules re-exported with `export *`
+    stars:        Vec<String>,
+    // Modules of the bundle that have to run before this one, in order
+    dependencies: Vec<String>,
+    // Modules of the bundle imported with `import()`, and their specifiers
+    dynamic:      Vec<(String, String)>,
+}
+
+fn export_name(name: &ModuleExportName) -> String {
+    match name {
+        ModuleExportName::Ident(x) => x.sym.to_string(),
+        ModuleExportName::Str(x) => x.value.to_string(),
+    }
+}
+
+fn bundle_error(message: String) -> EasySwcTranspilerError {
+    EasySwcTranspilerError::Bundle(message)
+}
+
+struct Bundler<'a> {
+    modules:         HashMap<&'a str, &'a BundleModule>,
+    linked:          HashMap<String, Linked>,
+    // Imports of modules outside of the bundle, along with `export *` of them
+    // by the entry
+    external:        Vec<ModuleItem>,
+    // Objects standing in for modules imported as a whole, created on demand
+    namespaces:      HashMap<String, Ident>,
+    // Functions running the modules that only run once imported dynamically
+    inits:           HashMap<String, Ident>,
+    unresolved_mark: Mark,
+}
+
+impl<'a> Bundler<'a> {
+    /// The module of the bundle `specifier` refers to from `module`
+    fn internal(&self, module: &str, specifier: &str) -> Option<String> {
+        let resolved = self.modules.get(module)?.dependencies.get(specifier)?;
+        self.modules
+            .contains_key(resolved.as_str())
+            .then(|| resolved.clone())
+    }
+
+    fn link(
+        &mut self,
+        name: &str,
+        module: Module,
+        entry: bool,
+    ) -> Result<(), EasySwcTranspilerError> {
+        let mut linked = Linked::default();
+
+        for item in module.body {
+            let decl = match item {
+                ModuleItem::Stmt(stmt) => {
+                    linked.body.push(stmt);
+                    continue;
+                }
+                ModuleItem::ModuleDecl(decl) => decl,
+            };
+
+            match decl {
+                ModuleDecl::Import(import) => {
+                    if import.type_only {
+                        continue;
+                    }
+                    let Some(target) = self.internal(name, &import.src.value) else {
+                        self.external
+                            .push(ModuleItem::ModuleDecl(ModuleDecl::Import(import)));
+                        continue;
+                    };
+                    for specifier in import.specifiers {
+                        let (local, import) = match specifier {
+                            ImportSpecifier::Named(x) if x.is_type_only => continue,
+                            ImportSpecifier::Named(x) => {
+                                let imported = x
+                                    .imported
+                                    .as_ref()
+                                    .map_or_else(|| x.local.sym.to_string(), export_name);
+                                (x.local, Import::Named(target.clone(), imported))
+                            }
+                            ImportSpecifier::Default(x) => {
+                                (
+                                    x.local,
+                                    Import::Named(target.clone(), "default".to_string()),
+                                )
+                            }
+                            ImportSpecifier::Namespace(x) => {
+                                (x.local, Import::Namespace(target.clone()))
+                            }
+                        };
+                        linked.imports.insert(local.to_id(), import);
+                    }
+                    linked.dependencies.push(target);
+                }
+                ModuleDecl::ExportDecl(ExportDecl { decl, .. }) => {
+                    let ids: Vec<Id> = match &decl {
+                        Decl::Fn(x) => vec![x.ident.to_id()],
+                        Decl::Class(x) => vec![x.ident.to_id()],
+                        Decl::Var(x) => find_pat_ids(&x.decls),
+                        _ => vec![],
+                    };
+                    for id in ids {
+                        linked.exports.insert(id.0.to_string(), Export::Local(id));
+                    }
+                    linked.body.push(Stmt::Decl(decl));
+                }
+                ModuleDecl::ExportNamed(NamedExport {
+                    specifiers,
+                    src,
+                    type_only: false,
+                    ..
+                }) => {
+                    let target = src.as_ref().map(|x| (x, self.internal(name, &x.value)));
+                    for specifier in specifiers {
+                        match (specifier, &target) {
+                            (ExportSpecifier::Named(x), _) if x.is_type_only => {}
+                            (ExportSpecifier::Named(x), None) => {
+                                let ModuleExportName::Ident(orig) = &x.orig else {
+                                    continue;
+                                };
+                                let exported = x.exported.as_ref().unwrap_or(&x.orig);
+                                linked
+                                    .exports
+                                    .insert(export_name(exported), Export::Local(orig.to_id()));
+                            }
+                            (ExportSpecifier::Named(x), Some((_, Some(target)))) => {
+                                let exported = x.exported.as_ref().unwrap_or(&x.orig);
+                                linked.exports.insert(
+                                    export_name(exported),
+                                    Export::Reexport(target.clone(), export_name(&x.orig)),
+                                );
+                            }
+                            (ExportSpecifier::Namespace(x), Some((_, Some(target)))) => {
+                                linked.exports.insert(
+                                    export_name(&x.name),
+                                    Export::Namespace(target.clone()),
+                                );
+                            }
+                            // Re-exports of external modules become imports of them
+                            (ExportSpecifier::Named(x), Some((src, None))) => {
+                                let exported = export_name(x.exported.as_ref().unwrap_or(&x.orig));
+                                let local = Ident::new_private(exported.as_str().into(), x.span);
+                                linked
+                                    .exports
+                                    .insert(exported, Export::Local(local.to_id()));
+                                self.external.push(external_import(
+                                    src,
+                                    ImportSpecifier::Named(ImportNamedSpecifier {
+                                        span: x.span,
+                                        local,
+                                        imported: Some(x.orig),
+                                        is_type_only: false,
+                                    }),
+                                ));
+                            }
+                            (ExportSpecifier::Namespace(x), Some((src, None))) => {
+                                let exported = export_name(&x.name);
+                                let local = Ident::new_private(exported.as_str().into(), x.span);
+                                linked
+                                    .exports
+                                    .insert(exported, Export::Local(local.to_id()));
+                                self.external.push(external_import(
+                                    src,
+                                    ImportSpecifier::Namespace(ImportStarAsSpecifier {
+                                        span: x.span,
+                                        local,
+                                    }),
+                                ));
+                            }
+                            _ => {}
+                        }
+                    }
+                    if let Some((_, Some(target))) = target {
+                        linked.dependencies.push(target);
+                    }
+                }
+                ModuleDecl::ExportNamed(_) => {}
+                ModuleDecl::ExportDefaultDecl(x) => {
+                    let ident = match x.decl {
+                        DefaultDecl::Fn(f) => {
+                            let ident = f.ident.unwrap_or_else(|| default_ident(x.span));
+                            linked.body.push(Stmt::Decl(Decl::Fn(FnDecl {
+                                ident:    ident.clone(),
+                                declare:  false,
+                                function: f.function,
+                            })));
+                            ident
+                        }
+                        DefaultDecl::Class(c) => {
+                            let ident = c.ident.unwrap_or_else(|| default_ident(x.span));
+                            linked.body.push(Stmt::Decl(Decl::Class(ClassDecl {
+                                ident:   ident.clone(),
+                                declare: false,
+                                class:   c.class,
+                            })));
+                            ident
+                        }
+                        DefaultDecl::TsInterfaceDecl(_) => continue,
+                    };
+                    linked
+                        .exports
+                        .insert("default".to_string(), Export::Local(ident.to_id()));
+                }
+                ModuleDecl::ExportDefaultExpr(x) => {
+                    let ident = default_ident(x.span);
+                    linked.body.push(const_decl(ident.clone(), x.expr));
+                    linked
+                        .exports
+                        .insert("default".to_string(), Export::Local(ident.to_id()));
+                }
+                ModuleDecl::ExportAll(x) if x.type_only => {}
+                ModuleDecl::ExportAll(x) => {
+                    match self.internal(name, &x.src.value) {
+                        Some(target) => {
+                            linked.stars.push(target.clone());
+                            linked.dependencies.push(target);
+                        }
+                        None if entry => {
+                            self.external
+                                .push(ModuleItem::ModuleDecl(ModuleDecl::ExportAll(x)));
+                        }
+                        None => {
+                            return Err(bundle_error(format!(
+                                "{name} re-exports everything from the external module {}, which \
+                                 only the entry of a bundle can do",
+                                x.src.value
+                            )))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(bundle_error(format!(
+                        "{name} uses a TypeScript module syntax that cannot be bundled"
+                    )))
+                }
+            }
+        }
+
+        self.linked.insert(name.to_string(), linked);
+        Ok(())
+    }
+
+    fn resolve_export(
+        &self,
+        module: &str,
+        name: &str,
+        visited: &mut HashSet<(String, String)>,
+    ) -> Option<Binding> {
+        if !visited.insert((module.to_string(), name.to_string())) {
+            return None;
+        }
+        let linked = self.linked.get(module)?;
+        match linked.exports.get(name) {
+            Some(Export::Local(id)) => self.resolve_local(module, id, visited),
+            Some(Export::Reexport(target, name)) => self.resolve_export(target, name, visited),
+            Some(Export::Namespace(target)) => Some(Binding::Namespace(target.clone())),
+            // `export *` never includes the default export
+            None if name == "default" => None,
+            None => {
+                linked
+                    .stars
+                    .iter()
+                    .find_map(|x| self.resolve_export(x, name, visited))
+            }
+        }
+    }
+
+    fn resolve_local(
+        &self,
+        module: &str,
+        id: &Id,
+        visited: &mut HashSet<(String, String)>,
+    ) -> Option<Binding> {
+        match self.linked[module].imports.get(id) {
+            Some(Import::Named(target, name)) => self.resolve_export(target, name, visited),
+            Some(Import::Namespace(target)) => Some(Binding::Namespace(target.clone())),
+            None => Some(Binding::Ident(id.clone())),
+        }
+    }
+
+    fn export_names(&self, module: &str, visited: &mut HashSet<String>) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        if !visited.insert(module.to_string()) {
+            return names;
+        }
+        let linked = &self.linked[module];
+        names.extend(linked.exports.keys().cloned());
+        for star in &linked.stars {
+            names.extend(
+                self.export_names(star, visited)
+                    .into_iter()
+                    .filter(|x| x != "default"),
+            );
+        }
+        names
+    }
+
+    fn namespace(&mut self, module: &str) -> Ident {
+        self.namespaces
+            .entry(module.to_string())
+            .or_insert_with(|| module_ident(module, ""))
+            .clone()
+    }
+
+    fn init(&mut self, module: &str) -> Ident {
+        self.inits
+            .entry(module.to_string())
+            .or_insert_with(|| module_ident(module, "init_"))
+            .clone()
+    }
+
+    fn binding_ident(&mut self, binding: Binding, span: Span) -> Ident {
+        match binding {
+            Binding::Ident((sym, ctxt)) => Ident::new(sym, span, ctxt),
+            Binding::Namespace(module) => {
+                let mut ident = self.namespace(&module);
+                ident.span = span;
+                ident
+            }
+        }
+    }
+
+    /// `const ns = Object.freeze({ __proto__: null, get x() { return x } })`
+    fn namespace_decl(&mut self, module: &str, ident: Ident) -> Stmt {
+        let mut props = vec![PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+            key:   PropName::Ident(IdentName::new("__proto__".into(), DUMMY_SP)),
+            value: Box::new(Expr::Lit(Lit::Null(Null { span: DUMMY_SP }))),
+        })))];
+        for name in self.export_names(module, &mut HashSet::new()) {
+            let Some(binding) = self.resolve_export(module, &name, &mut HashSet::new()) else {
+                continue;
+            };
+            let value = self.binding_ident(binding, DUMMY_SP);
+            props.push(PropOrSpread::Prop(Box::new(Prop::Getter(GetterProp {
+                span:     DUMMY_SP,
+                key:      if name.starts_with(Ident::is_valid_start)
+                    && name.chars().all(Ident::is_valid_continue)
+                {
+                    PropName::Ident(IdentName::new(name.into(), DUMMY_SP))
+                } else {
+                    PropName::Str(Str::from(name))
+                },
+                type_ann: None,
+                body:     Some(BlockStmt {
+                    stmts: vec![Stmt::Return(ReturnStmt {
+                        span: DUMMY_SP,
+                        arg:  Some(Box::new(Expr::Ident(value))),
+                    })],
+                    ..Default::default()
+                }),
+            }))));
+        }
+
+        let object = self.global("Object");
+        let freeze = Expr::Call(CallExpr {
+            callee: Callee::Expr(Box::new(Expr::Member(MemberExpr {
+                span: DUMMY_SP,
+                obj:  Box::new(Expr::Ident(object)),
+                prop: MemberProp::Ident(IdentName::new("freeze".into(), DUMMY_SP)),
+            }))),
+            args: vec![ExprOrSpread {
+                spread: None,
+                expr:   Box::new(Expr::Object(ObjectLit {
+                    span: DUMMY_SP,
+                    props,
+                })),
+            }],
+            ..Default::default()
+        });
+        const_decl(ident, Box::new(freeze))
+    }
+
+    /// A reference to a global that no binding of the bundle shadows
+    fn global(&self, name: &str) -> Ident {
+        Ident::new(
+            name.into(),
+            DUMMY_SP,
+            SyntaxContext::empty().apply_mark(self.unresolved_mark),
+        )
+    }
+
+    /// Modules in the order they have to run, dependencies first
+    fn order(&self, module: &str, visited: &mut HashSet<String>, order: &mut Vec<String>) {
+        if !visited.insert(module.to_string()) {
+            return;
+        }
+        for dependency in &self.linked[module].dependencies {
+            self.order(dependency, visited, order);
+        }
+        order.push(module.to_string());
+    }
+}
+
+/// An identifier named after a module, e.g. `utils` for `lib/utils.ts`
+fn module_ident(module: &str, prefix: &str) -> Ident {
+    let stem = module
+        .rsplit('/')
+        .next()
+        .and_then(|x| x.split('.').next())
+        .unwrap_or_default();
+    let mut name: String = stem
+        .chars()
+        .map(|x| if x.is_ascii_alphanumeric() { x } else { '_' })
+        .collect();
+    name.insert_str(0, prefix);
+    if !name.starts_with(|x: char| x.is_ascii_alphabetic() || x == '_') {
+        name.insert(0, '_');
+    }
+    Ident::new_private(name.into(), DUMMY_SP)
+}
+
+fn external_import(src: &Str, specifier: ImportSpecifier) -> ModuleItem {
+    ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        span:       DUMMY_SP,
+        specifiers: vec![specifier],
+        src:        Box::new(src.clone()),
+        type_only:  false,
+        with:       None,
+        phase:      Default::default(),
+    }))
+}
+
+fn default_ident(span: Span) -> Ident {
+    Ident::new_private("_default".into(), span)
+}
+
+fn assign(ident: Ident, value: Box<Expr>) -> Expr {
+    value.make_assign_to(
+        AssignOp::Assign,
+        SimpleAssignTarget::from(BindingIdent::from(ident)).into(),
+    )
+}
+
+/// Move the bindings of a module that runs once imported dynamically to the
+/// top level, and what it runs into a function that runs it only the first
+/// time it is called:
+/// `function init() { return _init || (_init = (async () => { ... })()) }`
+fn deferred(stmts: Vec<Stmt>, init: Ident) -> Vec<Stmt> {
+    let mut bindings: Vec<Ident> = vec![];
+    let mut hoisted = vec![];
+    let mut body = vec![];
+    for stmt in stmts {
+        match stmt {
+            // Functions are usable before their module runs anyway
+            Stmt::Decl(Decl::Fn(x)) => hoisted.push(Stmt::Decl(Decl::Fn(x))),
+            Stmt::Decl(Decl::Class(x)) => {
+                bindings.push(x.ident.clone());
+                let class = Expr::Class(ClassExpr {
+                    ident: Some(x.ident.clone()),
+                    class: x.class,
+                });
+                body.push(assign(x.ident, Box::new(class)).into_stmt());
+            }
+            Stmt::Decl(Decl::Var(x)) => {
+                for decl in x.decls {
+                    bindings.extend(find_pat_ids::<_, Ident>(&decl.name));
+                    let Some(init) = decl.init else {
+                        continue;
+                    };
+                    let target = AssignTarget::try_from(decl.name)
+                        .expect("declarations bind patterns that can be assigned to");
+                    body.push(init.make_assign_to(AssignOp::Assign, target).into_stmt());
+                }
+            }
+            stmt => body.push(stmt),
+        }
+    }
+
+    let running = Ident::new_private(format!("_{}", init.sym).into(), DUMMY_SP);
+    bindings.push(running.clone());
+    let run = ArrowExpr {
+        is_async: true,
+        body: Box::new(BlockStmtOrExpr::BlockStmt(BlockStmt {
+            stmts: body,
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .as_iife();
+    let once = Expr::Ident(running.clone()).make_bin(
+        BinaryOp::LogicalOr,
+        assign(running, Box::new(Expr::Call(run))),
+    );
+    hoisted.push(Stmt::Decl(Decl::Fn(FnDecl {
+        ident:    init,
+        declare:  false,
+        function: Box::new(Function {
+            body: Some(BlockStmt {
+                stmts: vec![Stmt::Return(ReturnStmt {
+                    span: DUMMY_SP,
+                    arg:  Some(Box::new(once)),
+                })],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    })));
+
+    let decls = bindings
+        .into_iter()
+        .map(|x| {
+            VarDeclarator {
+                span:     DUMMY_SP,
+                name:     x.into(),
+                init:     None,
+                definite: false,
+            }
+        })
+        .collect();
+    hoisted.insert(
+        0,
+        Stmt::Decl(Decl::Var(Box::new(VarDecl {
+            kind: VarDeclKind::Let,
+            decls,
+            ..Default::default()
+        }))),
+    );
+    hoisted
+}
+
+fn const_decl(ident: Ident, init: Box<Expr>) -> Stmt {
+    Stmt::Decl(Decl::Var(Box::new(VarDecl {
+        kind: VarDeclKind::Const,
+        decls: vec![VarDeclarator {
+            span:     DUMMY_SP,
+            name:     ident.into(),
+            init:     Some(init),
+            definite: false,
+        }],
+        ..Default::default()
+    })))
+}
+
+/// Points imported names at the bindings they refer to and turns `import()`
+/// of bundled modules into their namespace objects, once the modules that
+/// have yet to run did
+struct Rewriter {
+    bindings: HashMap<Id, Ident>,
+    // The namespace and the functions running the modules of every specifier
+    dynamic:  HashMap<String, (Ident, Vec<Ident>)>,
+    promise:  Ident,
+}
+
+impl VisitMut for Rewriter {
+    fn visit_mut_ident(&mut self, node: &mut Ident) {
+        if let Some(target) = self.bindings.get(&node.to_id()) {
+            node.sym = target.sym.clone();
+            node.ctxt = target.ctxt;
+        }
+    }
+
+    fn visit_mut_prop(&mut self, node: &mut Prop) {
+        if let Prop::Shorthand(ident) = node {
+            if self.bindings.contains_key(&ident.to_id()) {
+                let key = PropName::Ident(IdentName::new(ident.sym.clone(), ident.span));
+                let mut value = ident.clone();
+                self.visit_mut_ident(&mut value);
+                *node = Prop::KeyValue(KeyValueProp {
+                    key,
+                    value: Box::new(Expr::Ident(value)),
+                });
+                return;
+            }
+        }
+        node.visit_mut_children_with(self);
+    }
+
+    fn visit_mut_expr(&mut self, node: &mut Expr) {
+        node.visit_mut_children_with(self);
+
+        let Expr::Call(CallExpr {
+            span,
+            callee: Callee::Import(_),
+            args,
+            ..
+        }) = node
+        else {
+            return;
+        };
+        let Some(Expr::Lit(Lit::Str(specifier))) = args.first().map(|x| &*x.expr) else {
+            return;
+        };
+        let Some((namespace, inits)) = self.dynamic.get(&*specifier.value) else {
+            return;
+        };
+
+        if !inits.is_empty() {
+            // `Promise.resolve().then(async () => { await init_a(); return ns })`,
+            // which runs the modules once the importer is done, like `import()`
+            let mut stmts: Vec<_> = inits
+                .iter()
+                .map(|x| {
+                    Expr::Await(AwaitExpr {
+                        span: DUMMY_SP,
+                        arg:  Box::new(Expr::Ident(x.clone()).as_call(DUMMY_SP, vec![])),
+                    })
+                    .into_stmt()
+                })
+                .collect();
+            stmts.push(Stmt::Return(ReturnStmt {
+                span: DUMMY_SP,
+                arg:  Some(Box::new(Expr::Ident(namespace.clone()))),
+            }));
+            let run = ArrowExpr {
+                is_async: true,
+                body: Box::new(BlockStmtOrExpr::BlockStmt(BlockStmt {
+                    stmts,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            };
+            let resolved = Expr::Member(MemberExpr {
+                span: DUMMY_SP,
+                obj:  Box::new(Expr::Ident(self.promise.clone())),
+                prop: MemberProp::Ident(IdentName::new("resolve".into(), DUMMY_SP)),
+            })
+            .as_call(DUMMY_SP, vec![]);
+            *node = Expr::Member(MemberExpr {
+                span: DUMMY_SP,
+                obj:  Box::new(resolved),
+                prop: MemberProp::Ident(IdentName::new("then".into(), DUMMY_SP)),
+            })
+            .as_call(*span, vec![run.as_arg()]);
+            return;
+        }
+
+        // `Promise.resolve(ns)`
+        *node = Expr::Call(CallExpr {
+            span: *span,
+            callee: Callee::Expr(Box::new(Expr::Member(MemberExpr {
+                span: DUMMY_SP,
+                obj:  Box::new(Expr::Ident(self.promise.clone())),
+                prop: MemberProp::Ident(IdentName::new("resolve".into(), DUMMY_SP)),
+            }))),
+            args: vec![ExprOrSpread {
+                spread: None,
+                expr:   Box::new(Expr::Ident(namespace.clone())),
+            }],
+            ..Default::default()
+        });
+    }
+}
+
+/// Collects `import()` of string literals
+struct DynamicImports(Vec<String>);
+
+impl VisitMut for DynamicImports {
+    fn visit_mut_call_expr(&mut self, node: &mut CallExpr) {
+        if let (Callee::Import(_), Some(arg)) = (&node.callee, node.args.first()) {
+            if let Expr::Lit(Lit::Str(specifier)) = &*arg.expr {
+                self.0.push(specifier.value.to_string());
+            }
+        }
+        node.visit_mut_children_with(self);
+    }
+}
+
+impl EasySwcTranspiler {
+    /// Put `entry` and the modules it imports into a single ES module, with
+    /// the top-level bindings of every module in the same scope. Modules only
+    /// imported dynamically still run once they are imported
+    pub fn bundle(
+        &self,
+        entry: &str,
+        modules: &[BundleModule],
+        options: &BundleOptions,
+    ) -> Result<(String, Option<::sourcemap::SourceMap>), EasySwcTranspilerError> {
+        // Helpers are inlined once for all modules
+        let session = Session::new(&self.options);
+        session.run(|| session.bundle(entry, modules, options))
+    }
+}
+
+impl Session<'_> {
+    fn bundle(
+        &self,
+        entry: &str,
+        modules: &[BundleModule],
+        options: &BundleOptions,
+    ) -> Result<(String, Option<::sourcemap::SourceMap>), EasySwcTranspilerError> {
+        let mut bundler = Bundler {
+            modules:         modules.iter().map(|x| (x.name.as_str(), x)).collect(),
+            linked:          HashMap::new(),
+            external:        vec![],
+            namespaces:      HashMap::new(),
+            inits:           HashMap::new(),
+            // Shared so that globals keep their names across modules
+            unresolved_mark: Mark::new(),
+        };
+        if !bundler.modules.contains_key(entry) {
+            return Err(bundle_error(format!(
+                "{entry} is not one of the bundled modules"
+            )));
+        }
+
+        for module in modules {
+            let fm = self.source_map.new_source_file(
+                FileName::Custom(module.name.clone()).into(),
+                module.source.clone(),
+            );
+            let program = self.parse(fm, module.syntax, IsModule::Bool(true))?;
+            let mut program =
+                self.transform(program, module.syntax, bundler.unresolved_mark, Mark::new());
+
+            let mut dynamic = DynamicImports(vec![]);
+            program.visit_mut_with(&mut dynamic);
+            let Program::Module(program) = program else {
+                unreachable!("modules are parsed as modules")
+            };
+            bundler.link(&module.name, program, module.name == entry)?;
+
+            let dynamic = dynamic
+                .0
+                .into_iter()
+                .filter_map(|x| Some((bundler.internal(&module.name, &x)?, x)))
+                .collect();
+            bundler.linked.get_mut(&module.name).unwrap().dynamic = dynamic;
+        }
+
+        let mut order = vec![];
+        let mut eager = HashSet::new();
+        bundler.order(entry, &mut eager, &mut order);
+
+        // What runs when a module that was not imported statically is imported
+        // dynamically, i.e. it and what it imports that did not run yet
+        let mut loads = HashMap::new();
+        let mut lazy = vec![];
+        let mut pending = order.clone();
+        while let Some(name) = pending.pop() {
+            for (target, _) in &bundler.linked[&name].dynamic {
+                if eager.contains(target) || loads.contains_key(target) {
+                    continue;
+                }
+                let mut load = vec![];
+                bundler.order(target, &mut eager.clone(), &mut load);
+                for module in &load {
+                    if !lazy.contains(module) {
+                        lazy.push(module.clone());
+                        pending.push(module.clone());
+                    }
+                }
+                loads.insert(target.clone(), load);
+            }
+        }
+
+        let promise = bundler.global("Promise");
+        let mut body = vec![];
+        let mut deferred_body = vec![];
+        for name in order.iter().chain(&lazy) {
+            let mut resolved = vec![];
+            for (id, import) in &bundler.linked[name].imports {
+                let binding = match import {
+                    Import::Named(target, export) => {
+                        bundler
+                            .resolve_export(target, export, &mut HashSet::new())
+                            .ok_or_else(|| {
+                                bundle_error(format!(
+                                    "{target} does not export {export}, which {name} imports"
+                                ))
+                            })?
+                    }
+                    Import::Namespace(target) => Binding::Namespace(target.clone()),
+                };
+                resolved.push((id.clone(), binding));
+            }
+            let bindings = resolved
+                .into_iter()
+                .map(|(id, binding)| (id, bundler.binding_ident(binding, DUMMY_SP)))
+                .collect();
+            let mut dynamic = HashMap::new();
+            for (target, specifier) in bundler.linked[name].dynamic.clone() {
+                let inits = loads
+                    .get(&target)
+                    .into_iter()
+                    .flatten()
+                    .map(|x| bundler.init(x))
+                    .collect();
+                dynamic.insert(specifier, (bundler.namespace(&target), inits));
+            }
+
+            let mut rewriter = Rewriter {
+                bindings,
+                dynamic,
+                promise: promise.clone(),
+            };
+            let mut stmts = std::mem::take(&mut bundler.linked.get_mut(name).unwrap().body);
+            stmts.visit_mut_with(&mut rewriter);
+            if eager.contains(name) {
+                body.extend(stmts.into_iter().map(ModuleItem::Stmt));
+            } else {
+                let init = bundler.init(name);
+                deferred_body.extend(deferred(stmts, init).into_iter().map(ModuleItem::Stmt));
+            }
+        }
+
+        let mut specifiers = vec![];
+        for name in bundler.export_names(entry, &mut HashSet::new()) {
+            let Some(binding) = bundler.resolve_export(entry, &name, &mut HashSet::new()) else {
+                continue;
+            };
+            let orig = bundler.binding_ident(binding, DUMMY_SP);
+            // Hygiene keeps the exported name when it renames the binding
+            let exported = (orig.sym != name)
+                .then(|| ModuleExportName::Ident(Ident::new_no_ctxt(name.into(), DUMMY_SP)));
+            specifiers.push(ExportSpecifier::Named(ExportNamedSpecifier {
+                span: DUMMY_SP,
+                orig: ModuleExportName::Ident(orig),
+                exported,
+                is_type_only: false,
+            }));
+        }
+
+        // Namespace objects only read bindings when accessed, so they can all
+        // go first. Creating one may ask for others nested in it
+        let mut namespaces = vec![];
+        let mut done = HashSet::new();
+        loop {
+            let pending: Vec<_> = bundler
+                .namespaces
+                .iter()
+                .filter(|(x, _)| !done.contains(*x))
+                .map(|(x, ident)| (x.clone(), ident.clone()))
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            for (module, ident) in pending {
+                namespaces.push(ModuleItem::Stmt(bundler.namespace_decl(&module, ident)));
+                done.insert(module);
+            }
+        }
+
+        let mut items = std::mem::take(&mut bundler.external);
+        let (stars, imports): (Vec<_>, Vec<_>) = items
+            .drain(..)
+            .partition(|x| matches!(x, ModuleItem::ModuleDecl(ModuleDecl::ExportAll(_))));
+        items.extend(imports);
+        items.extend(namespaces);
+        items.extend(deferred_body);
+        items.extend(body);
+        if !specifiers.is_empty() {
+            items.push(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(
+                NamedExport {
+                    span: DUMMY_SP,
+                    specifiers,
+                    src: None,
+                    type_only: false,
+                    with: None,
+                },
+            )));
+        }
+        items.extend(stars);
+
+        let comments: Option<&dyn Comments> = Some(&self.comments);
+        let mut program = Program::Module(Module {
+            span:    DUMMY_SP,
+            body:    items,
+            shebang: None,
+        })
+        .apply(&mut inject_helpers(bundler.unresolved_mark))
+        .apply(&mut hygiene());
+
+        if let Some(minify) = &options.minify {
+            program = self.minify(program, minify);
+        }
+        program = program.apply(&mut fixer(comments));
+        if self.collector.has_errors() {
+            return Err(self.collector.take("cannot bundle").into());
+        }
+
+        self.emit(&program, options.source_map, options.minify.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rquickjs::{convert::Coerced, Context, Module, Runtime};
+
+    use super::BundleModule;
+    use crate::EasySwcTranspiler;
+
+    /// Bundle `modules`, the first of which is the entry, and run the bundle.
+    /// Modules import each other as `./<name>`, and push what they see to a
+    /// global `log`, returned as strings
+    fn run(modules: &[(&str, &str)]) -> (String, Vec<String>) {
+        let dependencies = modules
+            .iter()
+            .map(|(name, _)| (format!("./{name}"), name.to_string()))
+            .collect();
+        let modules: Vec<_> = modules
+            .iter()
+            .map(|(name, source)| {
+                BundleModule {
+                    name:         name.to_string(),
+                    source:       source.to_string(),
+                    syntax:       Default::default(),
+                    dependencies: Clone::clone(&dependencies),
+                }
+            })
+            .collect();
+        let (code, _) = EasySwcTranspiler::default()
+            .bundle(&modules[0].name, &modules, &Default::default())
+            .unwrap();
+
+        let runtime = Runtime::new().unwrap();
+        let context = Context::full(&runtime).unwrap();
+        let log = context.with(|ctx| {
+            ctx.globals().set("log", Vec::<String>::new()).unwrap();
+            Module::evaluate(ctx.clone(), "bundle", code.as_str())
+                .and_then(|x| x.finish::<()>())
+                .unwrap_or_else(|e| panic!("{e}: {:?}\n{code}", ctx.catch()));
+            while ctx.execute_pending_job() {}
+            ctx.globals().get::<_, Vec<Coerced<String>>>("log").unwrap()
+        });
+        (code, log.into_iter().map(|Coerced(x)| x).collect())
+    }
+
+    #[test]
+    fn renames_colliding_bindings() {
+        let (_, log) = run(&[
+            (
+                "main.js",
+                "import { get as a } from './a.js'; import { get as b } from './b.js';
+                 const value = 'main'; log.push(a(), b(), value)",
+            ),
+            (
+                "a.js",
+                "const value = 'a'; export function get() { return value }",
+            ),
+            (
+                "b.js",
+                "const value = 'b'; export function get() { return value }",
+            ),
+        ]);
+        assert_eq!(log, ["a", "b", "main"]);
+    }
+
+    #[test]
+    fn reexports_everything() {
+        let (_, log) = run(&[
+            (
+                "main.js",
+                "import * as all from './all.js'; import { a, b } from './all.js';
+                 log.push(a, b, Object.keys(all).join())",
+            ),
+            (
+                "all.js",
+                "export * from './a.js'; export * from './b.js'; export const c = 'c'",
+            ),
+            (
+                "a.js",
+                "export const a = 'a'; export default 'not re-exported'",
+            ),
+            ("b.js", "export const b = 'b'"),
+        ]);
+        assert_eq!(log, ["a", "b", "a,b,c"]);
+    }
+
+    #[test]
+    fn default_exports() {
+        let (_, log) = run(&[
+            (
+                "main.js",
+                "import x from './expr.js'; import f from './fn.js'; import C from './class.js';
+                 import { default as y } from './expr.js';
+                 log.push(x, y, f(), new C().name)",
+            ),
+            ("expr.js", "export default 1 + 1"),
+            ("fn.js", "export default function () { return 'fn' }"),
+            ("class.js", "export default class { name = 'class' }"),
+        ]);
+        assert_eq!(log, ["2", "2", "fn", "class"]);
+    }
+
+    #[test]
+    fn runs_cycles_once_dependencies_first() {
+        let (_, log) = run(&[
+            (
+                "main.js",
+                "import { a } from './a.js'; log.push('main', a())",
+            ),
+            (
+                "a.js",
+                "import { b } from './b.js'; log.push('a');
+                 export function a() { return 'a' + b() }",
+            ),
+            (
+                "b.js",
+                "import { a } from './a.js'; log.push('b');
+                 export function b() { return 'b' }
+                 export function c() { return a() }",
+            ),
+        ]);
+        assert_eq!(log, ["b", "a", "main", "ab"]);
+    }
+
+    #[test]
+    fn dynamic_imports_run_once_imported() {
+        let (code, log) = run(&[
+            (
+                "main.js",
+                "import { shared } from './shared.js';
+                 log.push('main');
+                 import('./lazy.js').then(x => log.push(x.value, x.Lazy.name));
+                 import('./lazy.js').then(x => log.push(x.value));
+                 import('./shared.js').then(x => log.push(x.shared));
+                 log.push('main done')",
+            ),
+            (
+                "shared.js",
+                "log.push('shared'); export const shared = 'shared'",
+            ),
+            (
+                "lazy.js",
+                "import { dep } from './dep.js'; import { shared } from './shared.js';
+                 log.push('lazy');
+                 export const { value } = { value: dep + ' ' + shared };
+                 export class Lazy {}",
+            ),
+            ("dep.js", "log.push('dep'); export let dep = 'dep'"),
+        ]);
+        assert_eq!(
+            log,
+            [
+                "shared",
+                "main",
+                "main done",
+                "dep",
+                "shared",
+                "lazy",
+                "dep shared",
+                "Lazy",
+                "dep shared"
+            ],
+            "{code}"
+        );
+    }
+}
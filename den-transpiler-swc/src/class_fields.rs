@@ -0,0 +1,86 @@
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::{
+    AssignOp, Callee, Class, ClassMember, ClassProp, Expr, MemberExpr, PropName, Stmt, ThisExpr,
+};
+use swc_ecma_utils::{default_constructor_with_span, prop_name_to_member_prop, ExprFactory};
+use swc_ecma_visit::{VisitMut, VisitMutWith};
+
+/// Turns the instance fields of classes into assignments at the start of the
+/// constructor, right after `super()`, the way TypeScript compiles them
+/// without `useDefineForClassFields`. Fields without an initializer go away,
+/// static and computed ones are left alone. Declared and abstract ones are
+/// left to the TypeScript pass
+pub(crate) struct AssignClassFields;
+
+impl VisitMut for AssignClassFields {
+    fn visit_mut_class(&mut self, node: &mut Class) {
+        node.visit_mut_children_with(self);
+
+        let mut assignments = vec![];
+        node.body.retain_mut(|member| {
+            let ClassMember::ClassProp(ClassProp {
+                key,
+                value,
+                is_static: false,
+                declare: false,
+                is_abstract: false,
+                ..
+            }) = member
+            else {
+                return true;
+            };
+            if matches!(key, PropName::Computed(_)) {
+                return true;
+            }
+
+            if let Some(value) = value.take() {
+                let target = MemberExpr {
+                    span: DUMMY_SP,
+                    obj:  Box::new(Expr::This(ThisExpr { span: DUMMY_SP })),
+                    prop: prop_name_to_member_prop(key.clone()),
+                };
+                assignments.push(
+                    value
+                        .make_assign_to(AssignOp::Assign, target.into())
+                        .into_stmt(),
+                );
+            }
+            false
+        });
+        if assignments.is_empty() {
+            return;
+        }
+
+        let has_super = node.super_class.is_some();
+        let constructor = node.body.iter_mut().find_map(|x| {
+            match x {
+                ClassMember::Constructor(x) => Some(x),
+                _ => None,
+            }
+        });
+        let constructor = match constructor {
+            Some(constructor) => constructor,
+            None => {
+                node.body
+                    .push(ClassMember::Constructor(default_constructor_with_span(
+                        has_super, node.span,
+                    )));
+                let Some(ClassMember::Constructor(constructor)) = node.body.last_mut() else {
+                    unreachable!("a constructor was just added")
+                };
+                constructor
+            }
+        };
+
+        let body = &mut constructor.body.get_or_insert_with(Default::default).stmts;
+        let at = body.iter().position(is_super_call).map_or(0, |x| x + 1);
+        body.splice(at..at, assignments);
+    }
+}
+
+fn is_super_call(stmt: &Stmt) -> bool {
+    let Stmt::Expr(stmt) = stmt else {
+        return false;
+    };
+    matches!(&*stmt.expr, Expr::Call(x) if matches!(x.callee, Callee::Super(_)))
+}
@@ -0,0 +1,197 @@
+use std::{
+    fmt,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use derive_more::{Display, Error};
+use swc_common::{
+    errors::{DiagnosticBuilder, Emitter, Handler, Level},
+    sync::Lrc,
+    FileName, SourceMap as SwcSourceMap,
+};
+
+/// How bad a diagnostic is, only errors make a transpile fail
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    #[display("error")]
+    Error,
+    #[display("warning")]
+    Warning,
+}
+
+/// A problem found in a source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The name the source was transpiled under, empty when unknown
+    pub file:     String,
+    /// Byte offsets of the problem in the source
+    pub span:     Range<usize>,
+    /// The 1-based line the problem starts on, 0 when unknown
+    pub line:     usize,
+    /// The 1-based column, in characters, the problem starts at
+    pub column:   usize,
+    pub severity: Severity,
+    pub message:  String,
+    /// More details, like where a conflicting declaration is
+    pub notes:    Vec<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+        if self.line > 0 {
+            write!(f, "\n  --> {}:{}:{}", self.file, self.line, self.column)?;
+        }
+        for note in &self.notes {
+            write!(f, "\n  = note: {note}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The diagnostics a source failed to transpile with, in the order they
+/// were found
+#[derive(Debug, Error, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostics(#[error(not(source))] pub Vec<Diagnostic>);
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Diagnostics {
+    pub fn has_errors(&self) -> bool {
+        self.0.iter().any(|x| x.severity == Severity::Error)
+    }
+}
+
+/// Gathers what swc reports through a handler instead of printing it
+#[derive(Clone)]
+pub(crate) struct Collector {
+    source_map:  Lrc<SwcSourceMap>,
+    diagnostics: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl Collector {
+    pub(crate) fn new(source_map: Lrc<SwcSourceMap>) -> Self {
+        Self {
+            source_map,
+            diagnostics: Default::default(),
+        }
+    }
+
+    pub(crate) fn handler(&self) -> Handler {
+        Handler::with_emitter(true, false, Box::new(self.clone()))
+    }
+
+    /// What was reported so far, or `fallback` as an error when nothing was
+    pub(crate) fn take(&self, fallback: impl fmt::Display) -> Diagnostics {
+        let mut diagnostics = std::mem::take(&mut *self.diagnostics.lock().unwrap());
+        if diagnostics.is_empty() {
+            diagnostics.push(Diagnostic {
+                file:     String::new(),
+                span:     0..0,
+                line:     0,
+                column:   0,
+                severity: Severity::Error,
+                message:  fallback.to_string(),
+                notes:    vec![],
+            });
+        }
+        Diagnostics(diagnostics)
+    }
+
+    /// Everything reported so far, possibly nothing
+    pub(crate) fn drain(&self) -> Diagnostics {
+        Diagnostics(std::mem::take(&mut *self.diagnostics.lock().unwrap()))
+    }
+
+    pub(crate) fn has_errors(&self) -> bool {
+        self.diagnostics
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|x| x.severity == Severity::Error)
+    }
+}
+
+impl Emitter for Collector {
+    fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+        let severity = match db.level {
+            Level::Bug | Level::Fatal | Level::PhaseFatal | Level::Error => Severity::Error,
+            Level::Warning | Level::Note | Level::Help | Level::FailureNote => Severity::Warning,
+            Level::Cancelled => return,
+        };
+
+        let mut diagnostic = Diagnostic {
+            file: String::new(),
+            span: 0..0,
+            line: 0,
+            column: 0,
+            severity,
+            message: db.message(),
+            notes: db.children.iter().map(|x| x.message()).collect(),
+        };
+        if let Some(span) = db.span.primary_span().filter(|x| !x.is_dummy()) {
+            let loc = self.source_map.lookup_char_pos(span.lo);
+            let start = loc.file.start_pos;
+            diagnostic.file = match &*loc.file.name {
+                FileName::Custom(name) => name.clone(),
+                name => name.to_string(),
+            };
+            diagnostic.span = (span.lo - start).0 as usize..(span.hi - start).0 as usize;
+            diagnostic.line = loc.line;
+            diagnostic.column = loc.col.0 + 1;
+        }
+        self.diagnostics.lock().unwrap().push(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EasySwcTranspiler, EasySwcTranspilerError, IsModule};
+
+    #[test]
+    fn collects_parse_errors() {
+        let source = "const a = 1;\nconst é = ;\n";
+        let error = EasySwcTranspiler::default()
+            .transpile(
+                "broken.js",
+                source,
+                Default::default(),
+                IsModule::Bool(true),
+                false,
+            )
+            .unwrap_err();
+        let EasySwcTranspilerError::Diagnostics(diagnostics) = error else {
+            panic!("{error}");
+        };
+
+        assert!(diagnostics.has_errors());
+        let diagnostic = &diagnostics.0[0];
+        assert_eq!(diagnostic.file, "broken.js");
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 11));
+        assert_eq!(&source[diagnostic.span.clone()], ";");
+        assert_eq!(
+            diagnostic.to_string(),
+            format!("error: {}\n  --> broken.js:2:11", diagnostic.message)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_a_message_without_location() {
+        let collector = Collector::new(Default::default());
+        let diagnostics = collector.take("cannot parse");
+        assert_eq!(diagnostics.to_string(), "error: cannot parse");
+        assert!(!collector.has_errors());
+    }
+}
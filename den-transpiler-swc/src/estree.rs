@@ -0,0 +1,2714 @@
+use serde_json::{json, Map, Value};
+use swc_common::{
+    comments::{Comment, CommentKind},
+    BytePos, FileName, Span, Spanned,
+};
+use swc_ecma_ast::*;
+use swc_ecma_parser::Syntax;
+use swc_node_comments::SwcComments;
+
+use crate::{EasySwcTranspiler, EasySwcTranspilerError, IsModule, Session};
+
+impl EasySwcTranspiler {
+    /// Parse `source` into an [ESTree](https://github.com/estree/estree) AST,
+    /// with TypeScript nodes as `typescript-estree` has them. Positions are
+    /// in UTF-16 code units like the indices of JavaScript strings, and the
+    /// comments are in `comments` of the program
+    pub fn parse(
+        &self,
+        name: &str,
+        source: &str,
+        syntax: Syntax,
+        is_module: IsModule,
+    ) -> Result<Value, EasySwcTranspilerError> {
+        let session = Session::new(&self.options);
+        let fm = session.source_map.new_source_file(
+            FileName::Custom(name.to_string()).into(),
+            source.to_string(),
+        );
+        session.run(|| {
+            let program = session.parse(fm.clone(), syntax, is_module)?;
+            if session.collector.has_errors() {
+                return Err(session.collector.take("cannot parse").into());
+            }
+            Ok(Estree::new(source, fm.start_pos).program(&program, &session.comments))
+        })
+    }
+}
+
+/// Turns swc nodes into ESTree ones, which only differ in shape
+struct Estree<'a> {
+    source: &'a str,
+    start:  BytePos,
+    /// The UTF-16 offset of every byte offset, none for ASCII sources
+    utf16:  Vec<u32>,
+    /// Byte offsets of where lines start
+    lines:  Vec<usize>,
+}
+
+impl<'a> Estree<'a> {
+    fn new(source: &'a str, start: BytePos) -> Self {
+        let mut utf16 = vec![];
+        if !source.is_ascii() {
+            utf16.reserve(source.len() + 1);
+            let mut offset = 0;
+            for c in source.chars() {
+                utf16.extend(std::iter::repeat_n(offset, c.len_utf8()));
+                offset += c.len_utf16() as u32;
+            }
+            utf16.push(offset);
+        }
+
+        let mut lines = vec![0];
+        let mut chars = source.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\r' if chars.peek().is_some_and(|(_, x)| *x == '\n') => {}
+                '\n' | '\r' | '\u{2028}' | '\u{2029}' => lines.push(i + c.len_utf8()),
+                _ => {}
+            }
+        }
+        Self {
+            source,
+            start,
+            utf16,
+            lines,
+        }
+    }
+
+    fn byte(&self, pos: BytePos) -> usize {
+        (pos.0.saturating_sub(self.start.0) as usize).min(self.source.len())
+    }
+
+    fn offset(&self, byte: usize) -> usize {
+        match self.utf16.get(byte) {
+            Some(offset) => *offset as usize,
+            None => byte,
+        }
+    }
+
+    /// Line, 1-based, and column of a position
+    fn position(&self, byte: usize) -> Value {
+        let line = self.lines.partition_point(|x| *x <= byte);
+        let column = self.offset(byte) - self.offset(self.lines[line - 1]);
+        json!({ "line": line, "column": column })
+    }
+
+    fn raw(&self, span: Span) -> &'a str {
+        self.source
+            .get(self.byte(span.lo)..self.byte(span.hi))
+            .unwrap_or_default()
+    }
+
+    fn node(&self, span: Span, kind: &str, fields: Value) -> Value {
+        let (lo, hi) = (self.byte(span.lo), self.byte(span.hi));
+        let (start, end) = (self.offset(lo), self.offset(hi));
+        let mut node = Map::new();
+        node.insert("type".into(), kind.into());
+        node.insert("start".into(), start.into());
+        node.insert("end".into(), end.into());
+        node.insert(
+            "loc".into(),
+            json!({ "start": self.position(lo), "end": self.position(hi) }),
+        );
+        node.insert("range".into(), json!([start, end]));
+        if let Value::Object(fields) = fields {
+            node.extend(fields);
+        }
+        Value::Object(node)
+    }
+
+    fn program(&self, program: &Program, comments: &SwcComments) -> Value {
+        let (span, body, source_type) = match program {
+            Program::Module(module) => {
+                (
+                    module.span,
+                    self.directives(
+                        module.body.iter().map(|x| x.as_stmt()),
+                        module.body.iter().map(|x| self.module_item(x)).collect(),
+                    ),
+                    "module",
+                )
+            }
+            Program::Script(script) => (script.span, self.body(&script.body), "script"),
+        };
+
+        let mut all: Vec<Comment> = vec![];
+        for map in [&comments.leading, &comments.trailing] {
+            for entry in map.iter() {
+                all.extend(entry.value().iter().cloned());
+            }
+        }
+        all.sort_by_key(|x| x.span.lo);
+        all.dedup_by_key(|x| x.span.lo);
+        let comments: Vec<_> = all
+            .iter()
+            .map(|x| {
+                let kind = match x.kind {
+                    CommentKind::Line => "Line",
+                    CommentKind::Block => "Block",
+                };
+                self.node(x.span, kind, json!({ "value": &*x.text }))
+            })
+            .collect();
+
+        // Comments and whitespace around the code belong to the program
+        let span = Span::new(self.start, self.start + BytePos(self.source.len() as u32)).to(span);
+        self.node(
+            span,
+            "Program",
+            json!({ "body": body, "sourceType": source_type, "comments": comments }),
+        )
+    }
+
+    /// Statements of a program or function, marking the directive prologue
+    fn body(&self, stmts: &[Stmt]) -> Vec<Value> {
+        self.directives(
+            stmts.iter().map(Some),
+            stmts.iter().map(|x| self.stmt(x)).collect(),
+        )
+    }
+
+    fn directives<'s>(
+        &self,
+        stmts: impl Iterator<Item = Option<&'s Stmt>>,
+        mut body: Vec<Value>,
+    ) -> Vec<Value> {
+        for (stmt, node) in stmts.zip(&mut body) {
+            let Some(Stmt::Expr(ExprStmt { expr, .. })) = stmt else {
+                break;
+            };
+            let Expr::Lit(Lit::Str(str)) = &**expr else {
+                break;
+            };
+            let raw = self.raw(str.span);
+            node["directive"] = raw[1..raw.len().saturating_sub(1).max(1)].into();
+        }
+        body
+    }
+
+    fn module_item(&self, item: &ModuleItem) -> Value {
+        match item {
+            ModuleItem::ModuleDecl(decl) => self.module_decl(decl),
+            ModuleItem::Stmt(stmt) => self.stmt(stmt),
+        }
+    }
+
+    fn module_decl(&self, decl: &ModuleDecl) -> Value {
+        match decl {
+            ModuleDecl::Import(import) => {
+                let specifiers: Vec<_> = import
+                    .specifiers
+                    .iter()
+                    .map(|x| {
+                        match x {
+                            ImportSpecifier::Named(x) => {
+                                let imported = match &x.imported {
+                                    Some(imported) => self.module_export_name(imported),
+                                    None => self.ident(&x.local),
+                                };
+                                self.node(
+                                    x.span,
+                                    "ImportSpecifier",
+                                    json!({
+                                        "imported": imported,
+                                        "local": self.ident(&x.local),
+                                        "importKind": kind(x.is_type_only),
+                                    }),
+                                )
+                            }
+                            ImportSpecifier::Default(x) => {
+                                self.node(
+                                    x.span,
+                                    "ImportDefaultSpecifier",
+                                    json!({ "local": self.ident(&x.local) }),
+                                )
+                            }
+                            ImportSpecifier::Namespace(x) => {
+                                self.node(
+                                    x.span,
+                                    "ImportNamespaceSpecifier",
+                                    json!({ "local": self.ident(&x.local) }),
+                                )
+                            }
+                        }
+                    })
+                    .collect();
+                let mut node = self.node(
+                    import.span,
+                    "ImportDeclaration",
+                    json!({
+                        "specifiers": specifiers,
+                        "source": self.str(&import.src),
+                        "attributes": self.attributes(import.with.as_deref()),
+                        "importKind": kind(import.type_only),
+                    }),
+                );
+                match import.phase {
+                    ImportPhase::Evaluation => {}
+                    ImportPhase::Source => node["phase"] = "source".into(),
+                    ImportPhase::Defer => node["phase"] = "defer".into(),
+                }
+                node
+            }
+            ModuleDecl::ExportDecl(export) => {
+                self.node(
+                    export.span,
+                    "ExportNamedDeclaration",
+                    json!({
+                        "declaration": self.decl(&export.decl),
+                        "specifiers": [],
+                        "source": null,
+                        "attributes": [],
+                        "exportKind": kind(matches!(
+                            export.decl,
+                            Decl::TsInterface(_) | Decl::TsTypeAlias(_)
+                        )),
+                    }),
+                )
+            }
+            ModuleDecl::ExportNamed(export) => {
+                let source = export.src.as_deref().map(|x| self.str(x));
+                let attributes = self.attributes(export.with.as_deref());
+                if let [ExportSpecifier::Namespace(namespace)] = &*export.specifiers {
+                    return self.node(
+                        export.span,
+                        "ExportAllDeclaration",
+                        json!({
+                            "exported": self.module_export_name(&namespace.name),
+                            "source": source,
+                            "attributes": attributes,
+                            "exportKind": kind(export.type_only),
+                        }),
+                    );
+                }
+
+                let specifiers: Vec<_> = export
+                    .specifiers
+                    .iter()
+                    .map(|x| {
+                        match x {
+                            ExportSpecifier::Named(x) => {
+                                let exported = x.exported.as_ref().unwrap_or(&x.orig);
+                                self.node(
+                                    x.span,
+                                    "ExportSpecifier",
+                                    json!({
+                                        "local": self.module_export_name(&x.orig),
+                                        "exported": self.module_export_name(exported),
+                                        "exportKind": kind(x.is_type_only),
+                                    }),
+                                )
+                            }
+                            ExportSpecifier::Default(x) => {
+                                self.node(
+                                    x.exported.span,
+                                    "ExportSpecifier",
+                                    json!({
+                                        "local": self.ident(&x.exported),
+                                        "exported": self.ident(&x.exported),
+                                        "exportKind": "value",
+                                    }),
+                                )
+                            }
+                            ExportSpecifier::Namespace(x) => {
+                                self.node(
+                                    x.span,
+                                    "ExportNamespaceSpecifier",
+                                    json!({ "exported": self.module_export_name(&x.name) }),
+                                )
+                            }
+                        }
+                    })
+                    .collect();
+                self.node(
+                    export.span,
+                    "ExportNamedDeclaration",
+                    json!({
+                        "declaration": null,
+                        "specifiers": specifiers,
+                        "source": source,
+                        "attributes": attributes,
+                        "exportKind": kind(export.type_only),
+                    }),
+                )
+            }
+            ModuleDecl::ExportDefaultDecl(export) => {
+                let declaration = match &export.decl {
+                    DefaultDecl::Class(x) => {
+                        self.class("ClassDeclaration", x.ident.as_ref(), &x.class)
+                    }
+                    DefaultDecl::Fn(x) => {
+                        self.function("FunctionDeclaration", x.ident.as_ref(), &x.function)
+                    }
+                    DefaultDecl::TsInterfaceDecl(x) => self.ts_interface(x),
+                };
+                self.node(
+                    export.span,
+                    "ExportDefaultDeclaration",
+                    json!({ "declaration": declaration, "exportKind": "value" }),
+                )
+            }
+            ModuleDecl::ExportDefaultExpr(export) => {
+                self.node(
+                    export.span,
+                    "ExportDefaultDeclaration",
+                    json!({ "declaration": self.expr(&export.expr), "exportKind": "value" }),
+                )
+            }
+            ModuleDecl::ExportAll(export) => {
+                self.node(
+                    export.span,
+                    "ExportAllDeclaration",
+                    json!({
+                        "exported": null,
+                        "source": self.str(&export.src),
+                        "attributes": self.attributes(export.with.as_deref()),
+                        "exportKind": kind(export.type_only),
+                    }),
+                )
+            }
+            ModuleDecl::TsImportEquals(import) => {
+                let reference = match &import.module_ref {
+                    TsModuleRef::TsEntityName(x) => self.entity_name(x),
+                    TsModuleRef::TsExternalModuleRef(x) => {
+                        self.node(
+                            x.span,
+                            "TSExternalModuleReference",
+                            json!({ "expression": self.str(&x.expr) }),
+                        )
+                    }
+                };
+                let node = self.node(
+                    import.span,
+                    "TSImportEqualsDeclaration",
+                    json!({
+                        "id": self.ident(&import.id),
+                        "moduleReference": reference,
+                        "importKind": kind(import.is_type_only),
+                    }),
+                );
+                if !import.is_export {
+                    return node;
+                }
+                self.node(
+                    import.span,
+                    "ExportNamedDeclaration",
+                    json!({
+                        "declaration": node,
+                        "specifiers": [],
+                        "source": null,
+                        "attributes": [],
+                        "exportKind": "value",
+                    }),
+                )
+            }
+            ModuleDecl::TsExportAssignment(export) => {
+                self.node(
+                    export.span,
+                    "TSExportAssignment",
+                    json!({ "expression": self.expr(&export.expr) }),
+                )
+            }
+            ModuleDecl::TsNamespaceExport(export) => {
+                self.node(
+                    export.span,
+                    "TSNamespaceExportDeclaration",
+                    json!({ "id": self.ident(&export.id) }),
+                )
+            }
+        }
+    }
+
+    fn module_export_name(&self, name: &ModuleExportName) -> Value {
+        match name {
+            ModuleExportName::Ident(x) => self.ident(x),
+            ModuleExportName::Str(x) => self.str(x),
+        }
+    }
+
+    /// The attributes of `with { type: "json" }`
+    fn attributes(&self, with: Option<&ObjectLit>) -> Vec<Value> {
+        with.iter()
+            .flat_map(|x| &x.props)
+            .filter_map(|x| {
+                match x {
+                    PropOrSpread::Prop(prop) => match &**prop {
+                        Prop::KeyValue(x) => Some(self.node(
+                            x.key.span().to(x.value.span()),
+                            "ImportAttribute",
+                            json!({ "key": self.prop_name(&x.key), "value": self.expr(&x.value) }),
+                        )),
+                        _ => None,
+                    },
+                    PropOrSpread::Spread(_) => None,
+                }
+            })
+            .collect()
+    }
+
+    fn stmt(&self, stmt: &Stmt) -> Value {
+        match stmt {
+            Stmt::Block(x) => self.block(x),
+            Stmt::Empty(x) => self.node(x.span, "EmptyStatement", json!({})),
+            Stmt::Debugger(x) => self.node(x.span, "DebuggerStatement", json!({})),
+            Stmt::With(x) => {
+                self.node(
+                    x.span,
+                    "WithStatement",
+                    json!({ "object": self.expr(&x.obj), "body": self.stmt(&x.body) }),
+                )
+            }
+            Stmt::Return(x) => {
+                self.node(
+                    x.span,
+                    "ReturnStatement",
+                    json!({ "argument": x.arg.as_deref().map(|x| self.expr(x)) }),
+                )
+            }
+            Stmt::Labeled(x) => {
+                self.node(
+                    x.span,
+                    "LabeledStatement",
+                    json!({ "label": self.ident(&x.label), "body": self.stmt(&x.body) }),
+                )
+            }
+            Stmt::Break(x) => {
+                self.node(
+                    x.span,
+                    "BreakStatement",
+                    json!({ "label": x.label.as_ref().map(|x| self.ident(x)) }),
+                )
+            }
+            Stmt::Continue(x) => {
+                self.node(
+                    x.span,
+                    "ContinueStatement",
+                    json!({ "label": x.label.as_ref().map(|x| self.ident(x)) }),
+                )
+            }
+            Stmt::If(x) => {
+                self.node(
+                    x.span,
+                    "IfStatement",
+                    json!({
+                        "test": self.expr(&x.test),
+                        "consequent": self.stmt(&x.cons),
+                        "alternate": x.alt.as_deref().map(|x| self.stmt(x)),
+                    }),
+                )
+            }
+            Stmt::Switch(x) => {
+                let cases: Vec<_> = x
+                    .cases
+                    .iter()
+                    .map(|x| {
+                        self.node(
+                            x.span,
+                            "SwitchCase",
+                            json!({
+                                "test": x.test.as_deref().map(|x| self.expr(x)),
+                                "consequent": x.cons.iter().map(|x| self.stmt(x)).collect::<Vec<_>>(),
+                            }),
+                        )
+                    })
+                    .collect();
+                self.node(
+                    x.span,
+                    "SwitchStatement",
+                    json!({ "discriminant": self.expr(&x.discriminant), "cases": cases }),
+                )
+            }
+            Stmt::Throw(x) => {
+                self.node(
+                    x.span,
+                    "ThrowStatement",
+                    json!({ "argument": self.expr(&x.arg) }),
+                )
+            }
+            Stmt::Try(x) => {
+                let handler = x.handler.as_ref().map(|x| {
+                    self.node(
+                        x.span,
+                        "CatchClause",
+                        json!({
+                            "param": x.param.as_ref().map(|x| self.pat(x)),
+                            "body": self.block(&x.body),
+                        }),
+                    )
+                });
+                self.node(
+                    x.span,
+                    "TryStatement",
+                    json!({
+                        "block": self.block(&x.block),
+                        "handler": handler,
+                        "finalizer": x.finalizer.as_ref().map(|x| self.block(x)),
+                    }),
+                )
+            }
+            Stmt::While(x) => {
+                self.node(
+                    x.span,
+                    "WhileStatement",
+                    json!({ "test": self.expr(&x.test), "body": self.stmt(&x.body) }),
+                )
+            }
+            Stmt::DoWhile(x) => {
+                self.node(
+                    x.span,
+                    "DoWhileStatement",
+                    json!({ "body": self.stmt(&x.body), "test": self.expr(&x.test) }),
+                )
+            }
+            Stmt::For(x) => {
+                let init = x.init.as_ref().map(|x| {
+                    match x {
+                        VarDeclOrExpr::VarDecl(x) => self.var_decl(x),
+                        VarDeclOrExpr::Expr(x) => self.expr(x),
+                    }
+                });
+                self.node(
+                    x.span,
+                    "ForStatement",
+                    json!({
+                        "init": init,
+                        "test": x.test.as_deref().map(|x| self.expr(x)),
+                        "update": x.update.as_deref().map(|x| self.expr(x)),
+                        "body": self.stmt(&x.body),
+                    }),
+                )
+            }
+            Stmt::ForIn(x) => {
+                self.node(
+                    x.span,
+                    "ForInStatement",
+                    json!({
+                        "left": self.for_head(&x.left),
+                        "right": self.expr(&x.right),
+                        "body": self.stmt(&x.body),
+                    }),
+                )
+            }
+            Stmt::ForOf(x) => {
+                self.node(
+                    x.span,
+                    "ForOfStatement",
+                    json!({
+                        "await": x.is_await,
+                        "left": self.for_head(&x.left),
+                        "right": self.expr(&x.right),
+                        "body": self.stmt(&x.body),
+                    }),
+                )
+            }
+            Stmt::Decl(x) => self.decl(x),
+            Stmt::Expr(x) => {
+                self.node(
+                    x.span,
+                    "ExpressionStatement",
+                    json!({ "expression": self.expr(&x.expr) }),
+                )
+            }
+        }
+    }
+
+    fn block(&self, block: &BlockStmt) -> Value {
+        self.node(
+            block.span,
+            "BlockStatement",
+            json!({ "body": self.body(&block.stmts) }),
+        )
+    }
+
+    fn for_head(&self, head: &ForHead) -> Value {
+        match head {
+            ForHead::VarDecl(x) => self.var_decl(x),
+            ForHead::UsingDecl(x) => self.using_decl(x),
+            ForHead::Pat(x) => self.pat(x),
+        }
+    }
+
+    fn decl(&self, decl: &Decl) -> Value {
+        match decl {
+            Decl::Class(x) => {
+                let mut node = self.class("ClassDeclaration", Some(&x.ident), &x.class);
+                set(&mut node, "declare", x.declare.then_some(true.into()));
+                node
+            }
+            Decl::Fn(x) => {
+                let mut node = self.function("FunctionDeclaration", Some(&x.ident), &x.function);
+                set(&mut node, "declare", x.declare.then_some(true.into()));
+                node
+            }
+            Decl::Var(x) => self.var_decl(x),
+            Decl::Using(x) => self.using_decl(x),
+            Decl::TsInterface(x) => self.ts_interface(x),
+            Decl::TsTypeAlias(x) => {
+                let mut node = self.node(
+                    x.span,
+                    "TSTypeAliasDeclaration",
+                    json!({
+                        "id": self.ident(&x.id),
+                        "typeAnnotation": self.ts_type(&x.type_ann),
+                        "declare": x.declare,
+                    }),
+                );
+                set(
+                    &mut node,
+                    "typeParameters",
+                    self.type_params(&x.type_params),
+                );
+                node
+            }
+            Decl::TsEnum(x) => {
+                let members: Vec<_> = x
+                    .members
+                    .iter()
+                    .map(|x| {
+                        let id = match &x.id {
+                            TsEnumMemberId::Ident(x) => self.ident(x),
+                            TsEnumMemberId::Str(x) => self.str(x),
+                        };
+                        self.node(
+                            x.span,
+                            "TSEnumMember",
+                            json!({
+                                "id": id,
+                                "initializer": x.init.as_deref().map(|x| self.expr(x)),
+                            }),
+                        )
+                    })
+                    .collect();
+                self.node(
+                    x.span,
+                    "TSEnumDeclaration",
+                    json!({
+                        "id": self.ident(&x.id),
+                        "members": members,
+                        "const": x.is_const,
+                        "declare": x.declare,
+                    }),
+                )
+            }
+            Decl::TsModule(x) => {
+                let id = match &x.id {
+                    TsModuleName::Ident(x) => self.ident(x),
+                    TsModuleName::Str(x) => self.str(x),
+                };
+                let kind = if x.global {
+                    "global"
+                } else if matches!(x.id, TsModuleName::Str(_))
+                    || self
+                        .raw(x.span)
+                        .trim_start_matches("declare")
+                        .trim_start()
+                        .starts_with("module")
+                {
+                    "module"
+                } else {
+                    "namespace"
+                };
+                self.node(
+                    x.span,
+                    "TSModuleDeclaration",
+                    json!({
+                        "id": id,
+                        "body": x.body.as_ref().map(|x| self.namespace_body(x)),
+                        "kind": kind,
+                        "declare": x.declare,
+                    }),
+                )
+            }
+        }
+    }
+
+    fn var_decl(&self, decl: &VarDecl) -> Value {
+        let kind = match decl.kind {
+            VarDeclKind::Var => "var",
+            VarDeclKind::Let => "let",
+            VarDeclKind::Const => "const",
+        };
+        self.node(
+            decl.span,
+            "VariableDeclaration",
+            json!({
+                "declarations": self.declarators(&decl.decls),
+                "kind": kind,
+                "declare": decl.declare,
+            }),
+        )
+    }
+
+    fn using_decl(&self, decl: &UsingDecl) -> Value {
+        let kind = if decl.is_await {
+            "await using"
+        } else {
+            "using"
+        };
+        self.node(
+            decl.span,
+            "VariableDeclaration",
+            json!({
+                "declarations": self.declarators(&decl.decls),
+                "kind": kind,
+                "declare": false,
+            }),
+        )
+    }
+
+    fn declarators(&self, decls: &[VarDeclarator]) -> Vec<Value> {
+        decls
+            .iter()
+            .map(|x| {
+                self.node(
+                    x.span,
+                    "VariableDeclarator",
+                    json!({
+                        "id": self.pat(&x.name),
+                        "init": x.init.as_deref().map(|x| self.expr(x)),
+                        "definite": x.definite,
+                    }),
+                )
+            })
+            .collect()
+    }
+
+    /// A function, which is only declared without a body
+    fn function(&self, kind: &str, id: Option<&Ident>, function: &Function) -> Value {
+        let kind = match &function.body {
+            None if kind == "FunctionDeclaration" => "TSDeclareFunction",
+            None => "TSEmptyBodyFunctionExpression",
+            Some(_) => kind,
+        };
+        let params: Vec<_> = function.params.iter().map(|x| self.param(x)).collect();
+        let mut node = self.node(
+            function.span,
+            kind,
+            json!({
+                "id": id.map(|x| self.ident(x)),
+                "params": params,
+                "body": function.body.as_ref().map(|x| self.block(x)),
+                "generator": function.is_generator,
+                "async": function.is_async,
+                "expression": false,
+            }),
+        );
+        set(
+            &mut node,
+            "typeParameters",
+            self.type_params(&function.type_params),
+        );
+        set(
+            &mut node,
+            "returnType",
+            function.return_type.as_deref().map(|x| self.type_ann(x)),
+        );
+        node
+    }
+
+    fn param(&self, param: &Param) -> Value {
+        let mut node = self.pat(&param.pat);
+        set(&mut node, "decorators", self.decorators(&param.decorators));
+        node
+    }
+
+    fn class(&self, kind: &str, id: Option<&Ident>, class: &Class) -> Value {
+        let members: Vec<_> = class
+            .body
+            .iter()
+            .filter_map(|x| self.class_member(x))
+            .collect();
+
+        // The body starts at the first brace after everything before it
+        let header = [
+            id.map(|x| x.span.hi),
+            class.super_class.as_ref().map(|x| x.span().hi),
+            class.type_params.as_ref().map(|x| x.span.hi),
+            class.super_type_params.as_ref().map(|x| x.span.hi),
+            class.implements.last().map(|x| x.span.hi),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(class.span.lo);
+        let brace = self.source[self.byte(header)..]
+            .find('{')
+            .map_or(header, |x| header + BytePos(x as u32));
+        let body = self.node(
+            Span::new(brace, class.span.hi),
+            "ClassBody",
+            json!({ "body": members }),
+        );
+
+        let mut node = self.node(
+            class.span,
+            kind,
+            json!({
+                "id": id.map(|x| self.ident(x)),
+                "superClass": class.super_class.as_deref().map(|x| self.expr(x)),
+                "body": body,
+            }),
+        );
+        set(&mut node, "decorators", self.decorators(&class.decorators));
+        set(
+            &mut node,
+            "abstract",
+            class.is_abstract.then_some(true.into()),
+        );
+        set(
+            &mut node,
+            "typeParameters",
+            self.type_params(&class.type_params),
+        );
+        set(
+            &mut node,
+            "superTypeArguments",
+            self.type_args(&class.super_type_params),
+        );
+        if !class.implements.is_empty() {
+            let implements: Vec<_> = class
+                .implements
+                .iter()
+                .map(|x| self.heritage("TSClassImplements", x))
+                .collect();
+            node["implements"] = implements.into();
+        }
+        node
+    }
+
+    fn class_member(&self, member: &ClassMember) -> Option<Value> {
+        Some(match member {
+            ClassMember::Constructor(x) => {
+                let params: Vec<_> = x
+                    .params
+                    .iter()
+                    .map(|x| {
+                        match x {
+                            ParamOrTsParamProp::TsParamProp(x) => self.param_prop(x),
+                            ParamOrTsParamProp::Param(x) => self.param(x),
+                        }
+                    })
+                    .collect();
+                // The function starts at the parenthesis after the key
+                let lo = self.source[self.byte(x.key.span().hi)..]
+                    .find('(')
+                    .map_or(x.span.lo, |i| x.key.span().hi + BytePos(i as u32));
+                let value_span = x.span.with_lo(lo);
+                let value = self.node(
+                    value_span,
+                    if x.body.is_some() {
+                        "FunctionExpression"
+                    } else {
+                        "TSEmptyBodyFunctionExpression"
+                    },
+                    json!({
+                        "id": null,
+                        "params": params,
+                        "body": x.body.as_ref().map(|x| self.block(x)),
+                        "generator": false,
+                        "async": false,
+                        "expression": false,
+                    }),
+                );
+                let mut node = self.node(
+                    x.span,
+                    "MethodDefinition",
+                    json!({
+                        "key": self.prop_name(&x.key),
+                        "value": value,
+                        "kind": "constructor",
+                        "computed": false,
+                        "static": false,
+                    }),
+                );
+                set(&mut node, "accessibility", accessibility(x.accessibility));
+                node
+            }
+            ClassMember::Method(x) => {
+                self.method(
+                    x.span,
+                    self.prop_name(&x.key),
+                    matches!(x.key, PropName::Computed(_)),
+                    &x.function,
+                    x.kind,
+                    x.is_static,
+                    x.accessibility,
+                    x.is_abstract,
+                    x.is_optional,
+                    x.is_override,
+                )
+            }
+            ClassMember::PrivateMethod(x) => {
+                self.method(
+                    x.span,
+                    self.private_name(&x.key),
+                    false,
+                    &x.function,
+                    x.kind,
+                    x.is_static,
+                    x.accessibility,
+                    x.is_abstract,
+                    x.is_optional,
+                    x.is_override,
+                )
+            }
+            ClassMember::ClassProp(x) => {
+                let mut node = self.node(
+                    x.span,
+                    if x.is_abstract {
+                        "TSAbstractPropertyDefinition"
+                    } else {
+                        "PropertyDefinition"
+                    },
+                    json!({
+                        "key": self.prop_name(&x.key),
+                        "value": x.value.as_deref().map(|x| self.expr(x)),
+                        "computed": matches!(x.key, PropName::Computed(_)),
+                        "static": x.is_static,
+                    }),
+                );
+                set(&mut node, "decorators", self.decorators(&x.decorators));
+                set(
+                    &mut node,
+                    "typeAnnotation",
+                    x.type_ann.as_deref().map(|x| self.type_ann(x)),
+                );
+                set(&mut node, "accessibility", accessibility(x.accessibility));
+                set(&mut node, "readonly", x.readonly.then_some(true.into()));
+                set(&mut node, "declare", x.declare.then_some(true.into()));
+                set(&mut node, "optional", x.is_optional.then_some(true.into()));
+                set(&mut node, "override", x.is_override.then_some(true.into()));
+                set(&mut node, "definite", x.definite.then_some(true.into()));
+                node
+            }
+            ClassMember::PrivateProp(x) => {
+                let mut node = self.node(
+                    x.span,
+                    "PropertyDefinition",
+                    json!({
+                        "key": self.private_name(&x.key),
+                        "value": x.value.as_deref().map(|x| self.expr(x)),
+                        "computed": false,
+                        "static": x.is_static,
+                    }),
+                );
+                set(&mut node, "decorators", self.decorators(&x.decorators));
+                set(
+                    &mut node,
+                    "typeAnnotation",
+                    x.type_ann.as_deref().map(|x| self.type_ann(x)),
+                );
+                set(&mut node, "readonly", x.readonly.then_some(true.into()));
+                set(&mut node, "optional", x.is_optional.then_some(true.into()));
+                set(&mut node, "override", x.is_override.then_some(true.into()));
+                set(&mut node, "definite", x.definite.then_some(true.into()));
+                node
+            }
+            ClassMember::TsIndexSignature(x) => self.index_signature(x),
+            ClassMember::Empty(_) => return None,
+            ClassMember::StaticBlock(x) => {
+                self.node(
+                    x.span,
+                    "StaticBlock",
+                    json!({ "body": self.body(&x.body.stmts) }),
+                )
+            }
+            ClassMember::AutoAccessor(x) => {
+                let (key, computed) = match &x.key {
+                    Key::Private(key) => (self.private_name(key), false),
+                    Key::Public(key) => (self.prop_name(key), matches!(key, PropName::Computed(_))),
+                };
+                let mut node = self.node(
+                    x.span,
+                    if x.is_abstract {
+                        "TSAbstractAccessorProperty"
+                    } else {
+                        "AccessorProperty"
+                    },
+                    json!({
+                        "key": key,
+                        "value": x.value.as_deref().map(|x| self.expr(x)),
+                        "computed": computed,
+                        "static": x.is_static,
+                    }),
+                );
+                set(&mut node, "decorators", self.decorators(&x.decorators));
+                set(
+                    &mut node,
+                    "typeAnnotation",
+                    x.type_ann.as_deref().map(|x| self.type_ann(x)),
+                );
+                set(&mut node, "accessibility", accessibility(x.accessibility));
+                node
+            }
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn method(
+        &self,
+        span: Span,
+        key: Value,
+        computed: bool,
+        function: &Function,
+        kind: MethodKind,
+        is_static: bool,
+        access: Option<Accessibility>,
+        is_abstract: bool,
+        is_optional: bool,
+        is_override: bool,
+    ) -> Value {
+        let kind = match kind {
+            MethodKind::Method => "method",
+            MethodKind::Getter => "get",
+            MethodKind::Setter => "set",
+        };
+        let mut node = self.node(
+            span,
+            if is_abstract {
+                "TSAbstractMethodDefinition"
+            } else {
+                "MethodDefinition"
+            },
+            json!({
+                "key": key,
+                "value": self.function("FunctionExpression", None, function),
+                "kind": kind,
+                "computed": computed,
+                "static": is_static,
+            }),
+        );
+        set(
+            &mut node,
+            "decorators",
+            self.decorators(&function.decorators),
+        );
+        set(&mut node, "accessibility", accessibility(access));
+        set(&mut node, "optional", is_optional.then_some(true.into()));
+        set(&mut node, "override", is_override.then_some(true.into()));
+        node
+    }
+
+    fn param_prop(&self, prop: &TsParamProp) -> Value {
+        let parameter = match &prop.param {
+            TsParamPropParam::Ident(x) => self.binding(x),
+            TsParamPropParam::Assign(x) => self.assign_pat(x),
+        };
+        let mut node = self.node(
+            prop.span,
+            "TSParameterProperty",
+            json!({
+                "parameter": parameter,
+                "readonly": prop.readonly,
+                "override": prop.is_override,
+                "static": false,
+            }),
+        );
+        set(
+            &mut node,
+            "accessibility",
+            accessibility(prop.accessibility),
+        );
+        set(&mut node, "decorators", self.decorators(&prop.decorators));
+        node
+    }
+
+    fn decorators(&self, decorators: &[Decorator]) -> Option<Value> {
+        if decorators.is_empty() {
+            return None;
+        }
+        let decorators: Vec<_> = decorators
+            .iter()
+            .map(|x| {
+                self.node(
+                    x.span,
+                    "Decorator",
+                    json!({ "expression": self.expr(&x.expr) }),
+                )
+            })
+            .collect();
+        Some(decorators.into())
+    }
+
+    fn expr(&self, expr: &Expr) -> Value {
+        match expr {
+            Expr::This(x) => self.node(x.span, "ThisExpression", json!({})),
+            Expr::Array(x) => {
+                let elements: Vec<_> = x
+                    .elems
+                    .iter()
+                    .map(|x| x.as_ref().map(|x| self.expr_or_spread(x)))
+                    .collect();
+                self.node(x.span, "ArrayExpression", json!({ "elements": elements }))
+            }
+            Expr::Object(x) => {
+                let properties: Vec<_> = x.props.iter().map(|x| self.prop_or_spread(x)).collect();
+                self.node(
+                    x.span,
+                    "ObjectExpression",
+                    json!({ "properties": properties }),
+                )
+            }
+            Expr::Fn(x) => self.function("FunctionExpression", x.ident.as_ref(), &x.function),
+            Expr::Unary(x) => {
+                self.node(
+                    x.span,
+                    "UnaryExpression",
+                    json!({
+                        "operator": x.op.as_str(),
+                        "prefix": true,
+                        "argument": self.expr(&x.arg),
+                    }),
+                )
+            }
+            Expr::Update(x) => {
+                self.node(
+                    x.span,
+                    "UpdateExpression",
+                    json!({
+                        "operator": x.op.as_str(),
+                        "prefix": x.prefix,
+                        "argument": self.expr(&x.arg),
+                    }),
+                )
+            }
+            Expr::Bin(x) => {
+                let kind = match x.op {
+                    BinaryOp::LogicalOr | BinaryOp::LogicalAnd | BinaryOp::NullishCoalescing => {
+                        "LogicalExpression"
+                    }
+                    _ => "BinaryExpression",
+                };
+                self.node(
+                    x.span,
+                    kind,
+                    json!({
+                        "operator": x.op.as_str(),
+                        "left": self.expr(&x.left),
+                        "right": self.expr(&x.right),
+                    }),
+                )
+            }
+            Expr::Assign(x) => {
+                let left = match &x.left {
+                    AssignTarget::Simple(x) => self.simple_assign_target(x),
+                    AssignTarget::Pat(AssignTargetPat::Array(x)) => self.array_pat(x),
+                    AssignTarget::Pat(AssignTargetPat::Object(x)) => self.object_pat(x),
+                    AssignTarget::Pat(AssignTargetPat::Invalid(x)) => {
+                        self.node(x.span, "Invalid", json!({}))
+                    }
+                };
+                self.node(
+                    x.span,
+                    "AssignmentExpression",
+                    json!({
+                        "operator": x.op.as_str(),
+                        "left": left,
+                        "right": self.expr(&x.right),
+                    }),
+                )
+            }
+            Expr::Member(x) => self.member(x, false),
+            Expr::SuperProp(x) => self.super_prop(x),
+            Expr::Cond(x) => {
+                self.node(
+                    x.span,
+                    "ConditionalExpression",
+                    json!({
+                        "test": self.expr(&x.test),
+                        "consequent": self.expr(&x.cons),
+                        "alternate": self.expr(&x.alt),
+                    }),
+                )
+            }
+            Expr::Call(x) => {
+                let callee = match &x.callee {
+                    Callee::Super(x) => self.node(x.span, "Super", json!({})),
+                    Callee::Import(_) => {
+                        return self.node(
+                            x.span,
+                            "ImportExpression",
+                            json!({
+                                "source": x.args.first().map(|x| self.expr(&x.expr)),
+                                "options": x.args.get(1).map(|x| self.expr(&x.expr)),
+                            }),
+                        );
+                    }
+                    Callee::Expr(x) => self.expr(x),
+                };
+                self.call(x.span, callee, &x.args, &x.type_args, false)
+            }
+            Expr::New(x) => {
+                let mut node = self.node(
+                    x.span,
+                    "NewExpression",
+                    json!({
+                        "callee": self.expr(&x.callee),
+                        "arguments": self.args(x.args.as_deref().unwrap_or_default()),
+                    }),
+                );
+                set(&mut node, "typeArguments", self.type_args(&x.type_args));
+                node
+            }
+            Expr::Seq(x) => {
+                let expressions: Vec<_> = x.exprs.iter().map(|x| self.expr(x)).collect();
+                self.node(
+                    x.span,
+                    "SequenceExpression",
+                    json!({ "expressions": expressions }),
+                )
+            }
+            Expr::Ident(x) => self.ident(x),
+            Expr::Lit(x) => self.lit(x),
+            Expr::Tpl(x) => self.tpl(x),
+            Expr::TaggedTpl(x) => {
+                let mut node = self.node(
+                    x.span,
+                    "TaggedTemplateExpression",
+                    json!({ "tag": self.expr(&x.tag), "quasi": self.tpl(&x.tpl) }),
+                );
+                set(&mut node, "typeArguments", self.type_args(&x.type_params));
+                node
+            }
+            Expr::Arrow(x) => {
+                let params: Vec<_> = x.params.iter().map(|x| self.pat(x)).collect();
+                let (body, expression) = match &*x.body {
+                    BlockStmtOrExpr::BlockStmt(x) => (self.block(x), false),
+                    BlockStmtOrExpr::Expr(x) => (self.expr(x), true),
+                };
+                let mut node = self.node(
+                    x.span,
+                    "ArrowFunctionExpression",
+                    json!({
+                        "id": null,
+                        "params": params,
+                        "body": body,
+                        "generator": false,
+                        "async": x.is_async,
+                        "expression": expression,
+                    }),
+                );
+                set(
+                    &mut node,
+                    "typeParameters",
+                    self.type_params(&x.type_params),
+                );
+                set(
+                    &mut node,
+                    "returnType",
+                    x.return_type.as_deref().map(|x| self.type_ann(x)),
+                );
+                node
+            }
+            Expr::Class(x) => self.class("ClassExpression", x.ident.as_ref(), &x.class),
+            Expr::Yield(x) => {
+                self.node(
+                    x.span,
+                    "YieldExpression",
+                    json!({
+                        "argument": x.arg.as_deref().map(|x| self.expr(x)),
+                        "delegate": x.delegate,
+                    }),
+                )
+            }
+            Expr::MetaProp(x) => {
+                let (meta, property) = match x.kind {
+                    MetaPropKind::NewTarget => ("new", "target"),
+                    MetaPropKind::ImportMeta => ("import", "meta"),
+                };
+                let meta = self.node(
+                    x.span.with_hi(x.span.lo + BytePos(meta.len() as u32)),
+                    "Identifier",
+                    json!({ "name": meta }),
+                );
+                let property = self.node(
+                    x.span.with_lo(x.span.hi - BytePos(property.len() as u32)),
+                    "Identifier",
+                    json!({ "name": property }),
+                );
+                self.node(
+                    x.span,
+                    "MetaProperty",
+                    json!({ "meta": meta, "property": property }),
+                )
+            }
+            Expr::Await(x) => {
+                self.node(
+                    x.span,
+                    "AwaitExpression",
+                    json!({ "argument": self.expr(&x.arg) }),
+                )
+            }
+            // Parentheses only group, ESTree does not keep them
+            Expr::Paren(x) => self.expr(&x.expr),
+            Expr::JSXMember(x) => self.jsx_member(x),
+            Expr::JSXNamespacedName(x) => self.jsx_namespaced_name(x),
+            Expr::JSXEmpty(x) => self.node(x.span, "JSXEmptyExpression", json!({})),
+            Expr::JSXElement(x) => self.jsx_element(x),
+            Expr::JSXFragment(x) => self.jsx_fragment(x),
+            Expr::TsTypeAssertion(x) => {
+                self.node(
+                    x.span,
+                    "TSTypeAssertion",
+                    json!({
+                        "typeAnnotation": self.ts_type(&x.type_ann),
+                        "expression": self.expr(&x.expr),
+                    }),
+                )
+            }
+            Expr::TsConstAssertion(x) => {
+                let name = self.node(
+                    x.span.with_lo(x.span.hi - BytePos(5)),
+                    "Identifier",
+                    json!({ "name": "const" }),
+                );
+                let type_annotation = self.node(
+                    x.span.with_lo(x.span.hi - BytePos(5)),
+                    "TSTypeReference",
+                    json!({ "typeName": name }),
+                );
+                self.node(
+                    x.span,
+                    "TSAsExpression",
+                    json!({
+                        "expression": self.expr(&x.expr),
+                        "typeAnnotation": type_annotation,
+                    }),
+                )
+            }
+            Expr::TsNonNull(x) => {
+                self.node(
+                    x.span,
+                    "TSNonNullExpression",
+                    json!({ "expression": self.expr(&x.expr) }),
+                )
+            }
+            Expr::TsAs(x) => {
+                self.node(
+                    x.span,
+                    "TSAsExpression",
+                    json!({
+                        "expression": self.expr(&x.expr),
+                        "typeAnnotation": self.ts_type(&x.type_ann),
+                    }),
+                )
+            }
+            Expr::TsInstantiation(x) => {
+                self.node(
+                    x.span,
+                    "TSInstantiationExpression",
+                    json!({
+                        "expression": self.expr(&x.expr),
+                        "typeArguments": self.type_args(&Some(x.type_args.clone())),
+                    }),
+                )
+            }
+            Expr::TsSatisfies(x) => {
+                self.node(
+                    x.span,
+                    "TSSatisfiesExpression",
+                    json!({
+                        "expression": self.expr(&x.expr),
+                        "typeAnnotation": self.ts_type(&x.type_ann),
+                    }),
+                )
+            }
+            Expr::PrivateName(x) => self.private_name(x),
+            Expr::OptChain(x) => {
+                self.node(
+                    x.span,
+                    "ChainExpression",
+                    json!({ "expression": self.chain(x) }),
+                )
+            }
+            Expr::Invalid(x) => self.node(x.span, "Invalid", json!({})),
+        }
+    }
+
+    fn simple_assign_target(&self, target: &SimpleAssignTarget) -> Value {
+        match target {
+            SimpleAssignTarget::Ident(x) => self.binding(x),
+            SimpleAssignTarget::Member(x) => self.member(x, false),
+            SimpleAssignTarget::SuperProp(x) => self.super_prop(x),
+            SimpleAssignTarget::Paren(x) => self.expr(&x.expr),
+            SimpleAssignTarget::OptChain(x) => self.expr(&Expr::OptChain(x.clone())),
+            SimpleAssignTarget::TsAs(x) => self.expr(&Expr::TsAs(x.clone())),
+            SimpleAssignTarget::TsSatisfies(x) => self.expr(&Expr::TsSatisfies(x.clone())),
+            SimpleAssignTarget::TsNonNull(x) => self.expr(&Expr::TsNonNull(x.clone())),
+            SimpleAssignTarget::TsTypeAssertion(x) => self.expr(&Expr::TsTypeAssertion(x.clone())),
+            SimpleAssignTarget::TsInstantiation(x) => self.expr(&Expr::TsInstantiation(x.clone())),
+            SimpleAssignTarget::Invalid(x) => self.node(x.span, "Invalid", json!({})),
+        }
+    }
+
+    fn member(&self, member: &MemberExpr, optional: bool) -> Value {
+        let (property, computed) = match &member.prop {
+            MemberProp::Ident(x) => (self.ident_name(x), false),
+            MemberProp::PrivateName(x) => (self.private_name(x), false),
+            MemberProp::Computed(x) => (self.expr(&x.expr), true),
+        };
+        self.node(
+            member.span,
+            "MemberExpression",
+            json!({
+                "object": self.chain_object(&member.obj),
+                "property": property,
+                "computed": computed,
+                "optional": optional,
+            }),
+        )
+    }
+
+    fn super_prop(&self, member: &SuperPropExpr) -> Value {
+        let (property, computed) = match &member.prop {
+            SuperProp::Ident(x) => (self.ident_name(x), false),
+            SuperProp::Computed(x) => (self.expr(&x.expr), true),
+        };
+        self.node(
+            member.span,
+            "MemberExpression",
+            json!({
+                "object": self.node(member.obj.span, "Super", json!({})),
+                "property": property,
+                "computed": computed,
+                "optional": false,
+            }),
+        )
+    }
+
+    fn call(
+        &self,
+        span: Span,
+        callee: Value,
+        args: &[ExprOrSpread],
+        type_args: &Option<Box<TsTypeParamInstantiation>>,
+        optional: bool,
+    ) -> Value {
+        let mut node = self.node(
+            span,
+            "CallExpression",
+            json!({
+                "callee": callee,
+                "arguments": self.args(args),
+                "optional": optional,
+            }),
+        );
+        set(&mut node, "typeArguments", self.type_args(type_args));
+        node
+    }
+
+    /// The inside of an optional chain, which is only wrapped in a
+    /// `ChainExpression` once
+    fn chain(&self, chain: &OptChainExpr) -> Value {
+        match &*chain.base {
+            OptChainBase::Member(x) => {
+                let mut node = self.member(x, chain.optional);
+                node["start"] = self.offset(self.byte(chain.span.lo)).into();
+                node
+            }
+            OptChainBase::Call(x) => {
+                self.call(
+                    chain.span,
+                    self.chain_object(&x.callee),
+                    &x.args,
+                    &x.type_args,
+                    chain.optional,
+                )
+            }
+        }
+    }
+
+    fn chain_object(&self, expr: &Expr) -> Value {
+        match expr {
+            Expr::OptChain(x) => self.chain(x),
+            _ => self.expr(expr),
+        }
+    }
+
+    fn args(&self, args: &[ExprOrSpread]) -> Vec<Value> {
+        args.iter().map(|x| self.expr_or_spread(x)).collect()
+    }
+
+    fn expr_or_spread(&self, expr: &ExprOrSpread) -> Value {
+        match expr.spread {
+            Some(spread) => {
+                self.node(
+                    spread.to(expr.expr.span()),
+                    "SpreadElement",
+                    json!({ "argument": self.expr(&expr.expr) }),
+                )
+            }
+            None => self.expr(&expr.expr),
+        }
+    }
+
+    fn prop_or_spread(&self, prop: &PropOrSpread) -> Value {
+        let prop = match prop {
+            PropOrSpread::Spread(x) => {
+                return self.node(
+                    x.dot3_token.to(x.expr.span()),
+                    "SpreadElement",
+                    json!({ "argument": self.expr(&x.expr) }),
+                );
+            }
+            PropOrSpread::Prop(x) => &**x,
+        };
+        let property = |span: Span, key: &PropName, value: Value, kind: &str, method: bool| {
+            self.node(
+                span,
+                "Property",
+                json!({
+                    "key": self.prop_name(key),
+                    "value": value,
+                    "kind": kind,
+                    "method": method,
+                    "shorthand": false,
+                    "computed": matches!(key, PropName::Computed(_)),
+                }),
+            )
+        };
+        let accessor = |span: Span, params: Vec<Value>, body: Option<&BlockStmt>| {
+            self.node(
+                span,
+                "FunctionExpression",
+                json!({
+                    "id": null,
+                    "params": params,
+                    "body": body.map(|x| self.block(x)),
+                    "generator": false,
+                    "async": false,
+                    "expression": false,
+                }),
+            )
+        };
+
+        match prop {
+            Prop::Shorthand(x) => {
+                self.node(
+                    x.span,
+                    "Property",
+                    json!({
+                        "key": self.ident(x),
+                        "value": self.ident(x),
+                        "kind": "init",
+                        "method": false,
+                        "shorthand": true,
+                        "computed": false,
+                    }),
+                )
+            }
+            Prop::KeyValue(x) => {
+                property(
+                    x.key.span().to(x.value.span()),
+                    &x.key,
+                    self.expr(&x.value),
+                    "init",
+                    false,
+                )
+            }
+            Prop::Assign(x) => {
+                let value = self.node(
+                    x.span,
+                    "AssignmentPattern",
+                    json!({ "left": self.ident(&x.key), "right": self.expr(&x.value) }),
+                );
+                self.node(
+                    x.span,
+                    "Property",
+                    json!({
+                        "key": self.ident(&x.key),
+                        "value": value,
+                        "kind": "init",
+                        "method": false,
+                        "shorthand": true,
+                        "computed": false,
+                    }),
+                )
+            }
+            Prop::Getter(x) => {
+                let value = accessor(x.key.span().with_hi(x.span.hi), vec![], x.body.as_ref());
+                property(x.span, &x.key, value, "get", false)
+            }
+            Prop::Setter(x) => {
+                let params = vec![self.pat(&x.param)];
+                let value = accessor(x.key.span().with_hi(x.span.hi), params, x.body.as_ref());
+                property(x.span, &x.key, value, "set", false)
+            }
+            Prop::Method(x) => {
+                property(
+                    x.key.span().to(x.function.span),
+                    &x.key,
+                    self.function("FunctionExpression", None, &x.function),
+                    "init",
+                    true,
+                )
+            }
+        }
+    }
+
+    fn prop_name(&self, name: &PropName) -> Value {
+        match name {
+            PropName::Ident(x) => self.ident_name(x),
+            PropName::Str(x) => self.str(x),
+            PropName::Num(x) => self.lit(&Lit::Num(x.clone())),
+            PropName::Computed(x) => self.expr(&x.expr),
+            PropName::BigInt(x) => self.lit(&Lit::BigInt(x.clone())),
+        }
+    }
+
+    fn tpl(&self, tpl: &Tpl) -> Value {
+        let quasis: Vec<_> = tpl.quasis.iter().map(|x| self.tpl_element(x)).collect();
+        let expressions: Vec<_> = tpl.exprs.iter().map(|x| self.expr(x)).collect();
+        self.node(
+            tpl.span,
+            "TemplateLiteral",
+            json!({ "quasis": quasis, "expressions": expressions }),
+        )
+    }
+
+    fn tpl_element(&self, element: &TplElement) -> Value {
+        self.node(
+            element.span,
+            "TemplateElement",
+            json!({
+                "value": { "raw": &*element.raw, "cooked": element.cooked.as_deref() },
+                "tail": element.tail,
+            }),
+        )
+    }
+
+    fn lit(&self, lit: &Lit) -> Value {
+        match lit {
+            Lit::Str(x) => self.str(x),
+            Lit::Bool(x) => {
+                self.node(
+                    x.span,
+                    "Literal",
+                    json!({ "value": x.value, "raw": self.raw(x.span) }),
+                )
+            }
+            Lit::Null(x) => self.node(x.span, "Literal", json!({ "value": null, "raw": "null" })),
+            Lit::Num(x) => {
+                self.node(
+                    x.span,
+                    "Literal",
+                    json!({ "value": x.value, "raw": self.raw(x.span) }),
+                )
+            }
+            // Neither has a JSON value, `bigint` and `regex` describe them
+            Lit::BigInt(x) => {
+                self.node(
+                    x.span,
+                    "Literal",
+                    json!({
+                        "value": null,
+                        "raw": self.raw(x.span),
+                        "bigint": x.value.to_string(),
+                    }),
+                )
+            }
+            Lit::Regex(x) => {
+                self.node(
+                    x.span,
+                    "Literal",
+                    json!({
+                        "value": null,
+                        "raw": self.raw(x.span),
+                        "regex": { "pattern": &*x.exp, "flags": &*x.flags },
+                    }),
+                )
+            }
+            Lit::JSXText(x) => {
+                self.node(
+                    x.span,
+                    "JSXText",
+                    json!({ "value": &*x.value, "raw": &*x.raw }),
+                )
+            }
+        }
+    }
+
+    fn str(&self, str: &Str) -> Value {
+        self.node(
+            str.span,
+            "Literal",
+            json!({ "value": &*str.value, "raw": self.raw(str.span) }),
+        )
+    }
+
+    fn ident(&self, ident: &Ident) -> Value {
+        let mut node = self.node(ident.span, "Identifier", json!({ "name": &*ident.sym }));
+        set(&mut node, "optional", ident.optional.then_some(true.into()));
+        node
+    }
+
+    fn ident_name(&self, ident: &IdentName) -> Value {
+        self.node(ident.span, "Identifier", json!({ "name": &*ident.sym }))
+    }
+
+    fn private_name(&self, name: &PrivateName) -> Value {
+        self.node(
+            name.span,
+            "PrivateIdentifier",
+            json!({ "name": &*name.name }),
+        )
+    }
+
+    fn binding(&self, binding: &BindingIdent) -> Value {
+        let mut node = self.ident(&binding.id);
+        set(
+            &mut node,
+            "typeAnnotation",
+            binding.type_ann.as_deref().map(|x| self.type_ann(x)),
+        );
+        node
+    }
+
+    fn pat(&self, pat: &Pat) -> Value {
+        match pat {
+            Pat::Ident(x) => self.binding(x),
+            Pat::Array(x) => self.array_pat(x),
+            Pat::Rest(x) => self.rest_pat(x),
+            Pat::Object(x) => self.object_pat(x),
+            Pat::Assign(x) => self.assign_pat(x),
+            Pat::Invalid(x) => self.node(x.span, "Invalid", json!({})),
+            Pat::Expr(x) => self.expr(x),
+        }
+    }
+
+    fn array_pat(&self, pat: &ArrayPat) -> Value {
+        let elements: Vec<_> = pat
+            .elems
+            .iter()
+            .map(|x| x.as_ref().map(|x| self.pat(x)))
+            .collect();
+        let mut node = self.node(pat.span, "ArrayPattern", json!({ "elements": elements }));
+        set(
+            &mut node,
+            "typeAnnotation",
+            pat.type_ann.as_deref().map(|x| self.type_ann(x)),
+        );
+        set(&mut node, "optional", pat.optional.then_some(true.into()));
+        node
+    }
+
+    fn object_pat(&self, pat: &ObjectPat) -> Value {
+        let properties: Vec<_> = pat
+            .props
+            .iter()
+            .map(|x| {
+                match x {
+                    ObjectPatProp::KeyValue(x) => {
+                        self.node(
+                            x.key.span().to(x.value.span()),
+                            "Property",
+                            json!({
+                                "key": self.prop_name(&x.key),
+                                "value": self.pat(&x.value),
+                                "kind": "init",
+                                "method": false,
+                                "shorthand": false,
+                                "computed": matches!(x.key, PropName::Computed(_)),
+                            }),
+                        )
+                    }
+                    ObjectPatProp::Assign(x) => {
+                        let value = match &x.value {
+                            Some(value) => self.node(
+                                x.span,
+                                "AssignmentPattern",
+                                json!({ "left": self.binding(&x.key), "right": self.expr(value) }),
+                            ),
+                            None => self.binding(&x.key),
+                        };
+                        self.node(
+                            x.span,
+                            "Property",
+                            json!({
+                                "key": self.ident(&x.key.id),
+                                "value": value,
+                                "kind": "init",
+                                "method": false,
+                                "shorthand": true,
+                                "computed": false,
+                            }),
+                        )
+                    }
+                    ObjectPatProp::Rest(x) => self.rest_pat(x),
+                }
+            })
+            .collect();
+        let mut node = self.node(
+            pat.span,
+            "ObjectPattern",
+            json!({ "properties": properties }),
+        );
+        set(
+            &mut node,
+            "typeAnnotation",
+            pat.type_ann.as_deref().map(|x| self.type_ann(x)),
+        );
+        set(&mut node, "optional", pat.optional.then_some(true.into()));
+        node
+    }
+
+    fn rest_pat(&self, pat: &RestPat) -> Value {
+        let mut node = self.node(
+            pat.span,
+            "RestElement",
+            json!({ "argument": self.pat(&pat.arg) }),
+        );
+        set(
+            &mut node,
+            "typeAnnotation",
+            pat.type_ann.as_deref().map(|x| self.type_ann(x)),
+        );
+        node
+    }
+
+    fn assign_pat(&self, pat: &AssignPat) -> Value {
+        self.node(
+            pat.span,
+            "AssignmentPattern",
+            json!({ "left": self.pat(&pat.left), "right": self.expr(&pat.right) }),
+        )
+    }
+
+    fn jsx_element(&self, element: &JSXElement) -> Value {
+        let opening = &element.opening;
+        let attributes: Vec<_> = opening
+            .attrs
+            .iter()
+            .map(|x| {
+                match x {
+                    JSXAttrOrSpread::JSXAttr(x) => {
+                        let name = match &x.name {
+                            JSXAttrName::Ident(x) => self.jsx_ident(x.span, &x.sym),
+                            JSXAttrName::JSXNamespacedName(x) => self.jsx_namespaced_name(x),
+                        };
+                        let value = x.value.as_ref().map(|x| {
+                            match x {
+                                JSXAttrValue::Lit(x) => self.lit(x),
+                                JSXAttrValue::JSXExprContainer(x) => self.jsx_expr_container(x),
+                                JSXAttrValue::JSXElement(x) => self.jsx_element(x),
+                                JSXAttrValue::JSXFragment(x) => self.jsx_fragment(x),
+                            }
+                        });
+                        self.node(
+                            x.span,
+                            "JSXAttribute",
+                            json!({ "name": name, "value": value }),
+                        )
+                    }
+                    JSXAttrOrSpread::SpreadElement(x) => {
+                        // The braces around the spread are part of the attribute
+                        let lo = self.source[..self.byte(x.dot3_token.lo)]
+                            .rfind('{')
+                            .map_or(x.dot3_token.lo, |i| self.start + BytePos(i as u32));
+                        let hi = self.source[self.byte(x.expr.span().hi)..]
+                            .find('}')
+                            .map_or(x.expr.span().hi, |i| {
+                                x.expr.span().hi + BytePos(i as u32 + 1)
+                            });
+                        self.node(
+                            Span::new(lo, hi),
+                            "JSXSpreadAttribute",
+                            json!({ "argument": self.expr(&x.expr) }),
+                        )
+                    }
+                }
+            })
+            .collect();
+        let mut opening_node = self.node(
+            opening.span,
+            "JSXOpeningElement",
+            json!({
+                "name": self.jsx_element_name(&opening.name),
+                "attributes": attributes,
+                "selfClosing": opening.self_closing,
+            }),
+        );
+        set(
+            &mut opening_node,
+            "typeArguments",
+            self.type_args(&opening.type_args),
+        );
+        let closing = element.closing.as_ref().map(|x| {
+            self.node(
+                x.span,
+                "JSXClosingElement",
+                json!({ "name": self.jsx_element_name(&x.name) }),
+            )
+        });
+        self.node(
+            element.span,
+            "JSXElement",
+            json!({
+                "openingElement": opening_node,
+                "children": self.jsx_children(&element.children),
+                "closingElement": closing,
+            }),
+        )
+    }
+
+    fn jsx_fragment(&self, fragment: &JSXFragment) -> Value {
+        self.node(
+            fragment.span,
+            "JSXFragment",
+            json!({
+                "openingFragment": self.node(fragment.opening.span, "JSXOpeningFragment", json!({})),
+                "children": self.jsx_children(&fragment.children),
+                "closingFragment": self.node(fragment.closing.span, "JSXClosingFragment", json!({})),
+            }),
+        )
+    }
+
+    fn jsx_children(&self, children: &[JSXElementChild]) -> Vec<Value> {
+        children
+            .iter()
+            .map(|x| {
+                match x {
+                    JSXElementChild::JSXText(x) => self.lit(&Lit::JSXText(x.clone())),
+                    JSXElementChild::JSXExprContainer(x) => self.jsx_expr_container(x),
+                    JSXElementChild::JSXSpreadChild(x) => {
+                        self.node(
+                            x.span,
+                            "JSXSpreadChild",
+                            json!({ "expression": self.expr(&x.expr) }),
+                        )
+                    }
+                    JSXElementChild::JSXElement(x) => self.jsx_element(x),
+                    JSXElementChild::JSXFragment(x) => self.jsx_fragment(x),
+                }
+            })
+            .collect()
+    }
+
+    fn jsx_expr_container(&self, container: &JSXExprContainer) -> Value {
+        let expression = match &container.expr {
+            JSXExpr::JSXEmptyExpr(x) => self.node(x.span, "JSXEmptyExpression", json!({})),
+            JSXExpr::Expr(x) => self.expr(x),
+        };
+        self.node(
+            container.span,
+            "JSXExpressionContainer",
+            json!({ "expression": expression }),
+        )
+    }
+
+    fn jsx_element_name(&self, name: &JSXElementName) -> Value {
+        match name {
+            JSXElementName::Ident(x) => self.jsx_ident(x.span, &x.sym),
+            JSXElementName::JSXMemberExpr(x) => self.jsx_member(x),
+            JSXElementName::JSXNamespacedName(x) => self.jsx_namespaced_name(x),
+        }
+    }
+
+    fn jsx_member(&self, member: &JSXMemberExpr) -> Value {
+        let object = match &member.obj {
+            JSXObject::JSXMemberExpr(x) => self.jsx_member(x),
+            JSXObject::Ident(x) => self.jsx_ident(x.span, &x.sym),
+        };
+        self.node(
+            member.span,
+            "JSXMemberExpression",
+            json!({
+                "object": object,
+                "property": self.jsx_ident(member.prop.span, &member.prop.sym),
+            }),
+        )
+    }
+
+    fn jsx_namespaced_name(&self, name: &JSXNamespacedName) -> Value {
+        self.node(
+            name.span,
+            "JSXNamespacedName",
+            json!({
+                "namespace": self.jsx_ident(name.ns.span, &name.ns.sym),
+                "name": self.jsx_ident(name.name.span, &name.name.sym),
+            }),
+        )
+    }
+
+    fn jsx_ident(&self, span: Span, name: &str) -> Value {
+        self.node(span, "JSXIdentifier", json!({ "name": name }))
+    }
+
+    fn ts_interface(&self, decl: &TsInterfaceDecl) -> Value {
+        let extends: Vec<_> = decl
+            .extends
+            .iter()
+            .map(|x| self.heritage("TSInterfaceHeritage", x))
+            .collect();
+        let body = self.node(
+            decl.body.span,
+            "TSInterfaceBody",
+            json!({ "body": self.type_elements(&decl.body.body) }),
+        );
+        let mut node = self.node(
+            decl.span,
+            "TSInterfaceDeclaration",
+            json!({
+                "id": self.ident(&decl.id),
+                "body": body,
+                "extends": extends,
+                "declare": decl.declare,
+            }),
+        );
+        set(
+            &mut node,
+            "typeParameters",
+            self.type_params(&decl.type_params),
+        );
+        node
+    }
+
+    fn heritage(&self, kind: &str, heritage: &TsExprWithTypeArgs) -> Value {
+        let mut node = self.node(
+            heritage.span,
+            kind,
+            json!({ "expression": self.expr(&heritage.expr) }),
+        );
+        set(
+            &mut node,
+            "typeArguments",
+            self.type_args(&heritage.type_args),
+        );
+        node
+    }
+
+    fn namespace_body(&self, body: &TsNamespaceBody) -> Value {
+        match body {
+            TsNamespaceBody::TsModuleBlock(x) => {
+                let body: Vec<_> = x.body.iter().map(|x| self.module_item(x)).collect();
+                self.node(x.span, "TSModuleBlock", json!({ "body": body }))
+            }
+            TsNamespaceBody::TsNamespaceDecl(x) => {
+                self.node(
+                    x.span,
+                    "TSModuleDeclaration",
+                    json!({
+                        "id": self.ident(&x.id),
+                        "body": self.namespace_body(&x.body),
+                        "kind": "namespace",
+                        "declare": x.declare,
+                    }),
+                )
+            }
+        }
+    }
+
+    fn type_ann(&self, ann: &TsTypeAnn) -> Value {
+        self.node(
+            ann.span,
+            "TSTypeAnnotation",
+            json!({ "typeAnnotation": self.ts_type(&ann.type_ann) }),
+        )
+    }
+
+    fn type_params(&self, params: &Option<Box<TsTypeParamDecl>>) -> Option<Value> {
+        let params = params.as_deref()?;
+        let list: Vec<_> = params.params.iter().map(|x| self.type_param(x)).collect();
+        Some(self.node(
+            params.span,
+            "TSTypeParameterDeclaration",
+            json!({ "params": list }),
+        ))
+    }
+
+    fn type_param(&self, param: &TsTypeParam) -> Value {
+        self.node(
+            param.span,
+            "TSTypeParameter",
+            json!({
+                "name": self.ident(&param.name),
+                "constraint": param.constraint.as_deref().map(|x| self.ts_type(x)),
+                "default": param.default.as_deref().map(|x| self.ts_type(x)),
+                "in": param.is_in,
+                "out": param.is_out,
+                "const": param.is_const,
+            }),
+        )
+    }
+
+    fn type_args(&self, args: &Option<Box<TsTypeParamInstantiation>>) -> Option<Value> {
+        let args = args.as_deref()?;
+        let list: Vec<_> = args.params.iter().map(|x| self.ts_type(x)).collect();
+        Some(self.node(
+            args.span,
+            "TSTypeParameterInstantiation",
+            json!({ "params": list }),
+        ))
+    }
+
+    fn entity_name(&self, name: &TsEntityName) -> Value {
+        match name {
+            TsEntityName::TsQualifiedName(x) => self.node(
+                x.span,
+                "TSQualifiedName",
+                json!({ "left": self.entity_name(&x.left), "right": self.ident_name(&x.right) }),
+            ),
+            TsEntityName::Ident(x) => self.ident(x),
+        }
+    }
+
+    fn fn_params(&self, params: &[TsFnParam]) -> Vec<Value> {
+        params
+            .iter()
+            .map(|x| {
+                match x {
+                    TsFnParam::Ident(x) => self.binding(x),
+                    TsFnParam::Array(x) => self.array_pat(x),
+                    TsFnParam::Rest(x) => self.rest_pat(x),
+                    TsFnParam::Object(x) => self.object_pat(x),
+                }
+            })
+            .collect()
+    }
+
+    fn signature(
+        &self,
+        span: Span,
+        kind: &str,
+        params: &[TsFnParam],
+        type_ann: Option<&TsTypeAnn>,
+        type_params: &Option<Box<TsTypeParamDecl>>,
+        fields: Value,
+    ) -> Value {
+        let mut node = self.node(span, kind, fields);
+        node["params"] = self.fn_params(params).into();
+        set(&mut node, "returnType", type_ann.map(|x| self.type_ann(x)));
+        set(&mut node, "typeParameters", self.type_params(type_params));
+        node
+    }
+
+    fn type_elements(&self, elements: &[TsTypeElement]) -> Vec<Value> {
+        elements
+            .iter()
+            .map(|x| {
+                match x {
+                    TsTypeElement::TsCallSignatureDecl(x) => {
+                        self.signature(
+                            x.span,
+                            "TSCallSignatureDeclaration",
+                            &x.params,
+                            x.type_ann.as_deref(),
+                            &x.type_params,
+                            json!({}),
+                        )
+                    }
+                    TsTypeElement::TsConstructSignatureDecl(x) => {
+                        self.signature(
+                            x.span,
+                            "TSConstructSignatureDeclaration",
+                            &x.params,
+                            x.type_ann.as_deref(),
+                            &x.type_params,
+                            json!({}),
+                        )
+                    }
+                    TsTypeElement::TsPropertySignature(x) => {
+                        let mut node = self.node(
+                            x.span,
+                            "TSPropertySignature",
+                            json!({
+                                "key": self.expr(&x.key),
+                                "computed": x.computed,
+                                "optional": x.optional,
+                                "readonly": x.readonly,
+                            }),
+                        );
+                        set(
+                            &mut node,
+                            "typeAnnotation",
+                            x.type_ann.as_deref().map(|x| self.type_ann(x)),
+                        );
+                        node
+                    }
+                    TsTypeElement::TsGetterSignature(x) => self.signature(
+                        x.span,
+                        "TSMethodSignature",
+                        &[],
+                        x.type_ann.as_deref(),
+                        &None,
+                        json!({ "key": self.expr(&x.key), "computed": x.computed, "kind": "get" }),
+                    ),
+                    TsTypeElement::TsSetterSignature(x) => self.signature(
+                        x.span,
+                        "TSMethodSignature",
+                        std::slice::from_ref(&x.param),
+                        None,
+                        &None,
+                        json!({ "key": self.expr(&x.key), "computed": x.computed, "kind": "set" }),
+                    ),
+                    TsTypeElement::TsMethodSignature(x) => {
+                        self.signature(
+                            x.span,
+                            "TSMethodSignature",
+                            &x.params,
+                            x.type_ann.as_deref(),
+                            &x.type_params,
+                            json!({
+                                "key": self.expr(&x.key),
+                                "computed": x.computed,
+                                "optional": x.optional,
+                                "kind": "method",
+                            }),
+                        )
+                    }
+                    TsTypeElement::TsIndexSignature(x) => self.index_signature(x),
+                }
+            })
+            .collect()
+    }
+
+    fn index_signature(&self, signature: &TsIndexSignature) -> Value {
+        let mut node = self.node(
+            signature.span,
+            "TSIndexSignature",
+            json!({
+                "parameters": self.fn_params(&signature.params),
+                "readonly": signature.readonly,
+                "static": signature.is_static,
+            }),
+        );
+        set(
+            &mut node,
+            "typeAnnotation",
+            signature.type_ann.as_deref().map(|x| self.type_ann(x)),
+        );
+        node
+    }
+
+    fn ts_type(&self, ty: &TsType) -> Value {
+        match ty {
+            TsType::TsKeywordType(x) => {
+                let kind = match x.kind {
+                    TsKeywordTypeKind::TsAnyKeyword => "TSAnyKeyword",
+                    TsKeywordTypeKind::TsUnknownKeyword => "TSUnknownKeyword",
+                    TsKeywordTypeKind::TsNumberKeyword => "TSNumberKeyword",
+                    TsKeywordTypeKind::TsObjectKeyword => "TSObjectKeyword",
+                    TsKeywordTypeKind::TsBooleanKeyword => "TSBooleanKeyword",
+                    TsKeywordTypeKind::TsBigIntKeyword => "TSBigIntKeyword",
+                    TsKeywordTypeKind::TsStringKeyword => "TSStringKeyword",
+                    TsKeywordTypeKind::TsSymbolKeyword => "TSSymbolKeyword",
+                    TsKeywordTypeKind::TsVoidKeyword => "TSVoidKeyword",
+                    TsKeywordTypeKind::TsUndefinedKeyword => "TSUndefinedKeyword",
+                    TsKeywordTypeKind::TsNullKeyword => "TSNullKeyword",
+                    TsKeywordTypeKind::TsNeverKeyword => "TSNeverKeyword",
+                    TsKeywordTypeKind::TsIntrinsicKeyword => "TSIntrinsicKeyword",
+                };
+                self.node(x.span, kind, json!({}))
+            }
+            TsType::TsThisType(x) => self.node(x.span, "TSThisType", json!({})),
+            TsType::TsFnOrConstructorType(TsFnOrConstructorType::TsFnType(x)) => {
+                self.signature(
+                    x.span,
+                    "TSFunctionType",
+                    &x.params,
+                    Some(&x.type_ann),
+                    &x.type_params,
+                    json!({}),
+                )
+            }
+            TsType::TsFnOrConstructorType(TsFnOrConstructorType::TsConstructorType(x)) => {
+                self.signature(
+                    x.span,
+                    "TSConstructorType",
+                    &x.params,
+                    Some(&x.type_ann),
+                    &x.type_params,
+                    json!({ "abstract": x.is_abstract }),
+                )
+            }
+            TsType::TsTypeRef(x) => {
+                let mut node = self.node(
+                    x.span,
+                    "TSTypeReference",
+                    json!({ "typeName": self.entity_name(&x.type_name) }),
+                );
+                set(&mut node, "typeArguments", self.type_args(&x.type_params));
+                node
+            }
+            TsType::TsTypeQuery(x) => {
+                let name = match &x.expr_name {
+                    TsTypeQueryExpr::TsEntityName(x) => self.entity_name(x),
+                    TsTypeQueryExpr::Import(x) => self.import_type(x),
+                };
+                let mut node = self.node(x.span, "TSTypeQuery", json!({ "exprName": name }));
+                set(&mut node, "typeArguments", self.type_args(&x.type_args));
+                node
+            }
+            TsType::TsTypeLit(x) => {
+                self.node(
+                    x.span,
+                    "TSTypeLiteral",
+                    json!({ "members": self.type_elements(&x.members) }),
+                )
+            }
+            TsType::TsArrayType(x) => {
+                self.node(
+                    x.span,
+                    "TSArrayType",
+                    json!({ "elementType": self.ts_type(&x.elem_type) }),
+                )
+            }
+            TsType::TsTupleType(x) => {
+                let elements: Vec<_> = x
+                    .elem_types
+                    .iter()
+                    .map(|x| {
+                        match &x.label {
+                            Some(label) => {
+                                self.node(
+                                    x.span,
+                                    "TSNamedTupleMember",
+                                    json!({
+                                        "label": self.pat(label),
+                                        "elementType": self.ts_type(&x.ty),
+                                        "optional": false,
+                                    }),
+                                )
+                            }
+                            None => self.ts_type(&x.ty),
+                        }
+                    })
+                    .collect();
+                self.node(x.span, "TSTupleType", json!({ "elementTypes": elements }))
+            }
+            TsType::TsOptionalType(x) => {
+                self.node(
+                    x.span,
+                    "TSOptionalType",
+                    json!({ "typeAnnotation": self.ts_type(&x.type_ann) }),
+                )
+            }
+            TsType::TsRestType(x) => {
+                self.node(
+                    x.span,
+                    "TSRestType",
+                    json!({ "typeAnnotation": self.ts_type(&x.type_ann) }),
+                )
+            }
+            TsType::TsUnionOrIntersectionType(TsUnionOrIntersectionType::TsUnionType(x)) => {
+                let types: Vec<_> = x.types.iter().map(|x| self.ts_type(x)).collect();
+                self.node(x.span, "TSUnionType", json!({ "types": types }))
+            }
+            TsType::TsUnionOrIntersectionType(TsUnionOrIntersectionType::TsIntersectionType(x)) => {
+                let types: Vec<_> = x.types.iter().map(|x| self.ts_type(x)).collect();
+                self.node(x.span, "TSIntersectionType", json!({ "types": types }))
+            }
+            TsType::TsConditionalType(x) => {
+                self.node(
+                    x.span,
+                    "TSConditionalType",
+                    json!({
+                        "checkType": self.ts_type(&x.check_type),
+                        "extendsType": self.ts_type(&x.extends_type),
+                        "trueType": self.ts_type(&x.true_type),
+                        "falseType": self.ts_type(&x.false_type),
+                    }),
+                )
+            }
+            TsType::TsInferType(x) => {
+                self.node(
+                    x.span,
+                    "TSInferType",
+                    json!({ "typeParameter": self.type_param(&x.type_param) }),
+                )
+            }
+            TsType::TsParenthesizedType(x) => self.ts_type(&x.type_ann),
+            TsType::TsTypeOperator(x) => {
+                let operator = match x.op {
+                    TsTypeOperatorOp::KeyOf => "keyof",
+                    TsTypeOperatorOp::Unique => "unique",
+                    TsTypeOperatorOp::ReadOnly => "readonly",
+                };
+                self.node(
+                    x.span,
+                    "TSTypeOperator",
+                    json!({ "operator": operator, "typeAnnotation": self.ts_type(&x.type_ann) }),
+                )
+            }
+            TsType::TsIndexedAccessType(x) => {
+                self.node(
+                    x.span,
+                    "TSIndexedAccessType",
+                    json!({
+                        "objectType": self.ts_type(&x.obj_type),
+                        "indexType": self.ts_type(&x.index_type),
+                    }),
+                )
+            }
+            TsType::TsMappedType(x) => {
+                let modifier = |x: Option<TruePlusMinus>| {
+                    match x {
+                        None => Value::Bool(false),
+                        Some(TruePlusMinus::True) => Value::Bool(true),
+                        Some(TruePlusMinus::Plus) => "+".into(),
+                        Some(TruePlusMinus::Minus) => "-".into(),
+                    }
+                };
+                self.node(
+                    x.span,
+                    "TSMappedType",
+                    json!({
+                        "typeParameter": self.type_param(&x.type_param),
+                        "nameType": x.name_type.as_deref().map(|x| self.ts_type(x)),
+                        "typeAnnotation": x.type_ann.as_deref().map(|x| self.ts_type(x)),
+                        "optional": modifier(x.optional),
+                        "readonly": modifier(x.readonly),
+                    }),
+                )
+            }
+            TsType::TsLitType(x) => {
+                let literal = match &x.lit {
+                    TsLit::Number(x) => self.lit(&Lit::Num(x.clone())),
+                    TsLit::Str(x) => self.str(x),
+                    TsLit::Bool(x) => self.lit(&Lit::Bool(*x)),
+                    TsLit::BigInt(x) => self.lit(&Lit::BigInt(x.clone())),
+                    TsLit::Tpl(x) => {
+                        let quasis: Vec<_> = x.quasis.iter().map(|x| self.tpl_element(x)).collect();
+                        let types: Vec<_> = x.types.iter().map(|x| self.ts_type(x)).collect();
+                        return self.node(
+                            x.span,
+                            "TSTemplateLiteralType",
+                            json!({ "quasis": quasis, "types": types }),
+                        );
+                    }
+                };
+                self.node(x.span, "TSLiteralType", json!({ "literal": literal }))
+            }
+            TsType::TsTypePredicate(x) => {
+                let name = match &x.param_name {
+                    TsThisTypeOrIdent::TsThisType(x) => self.node(x.span, "TSThisType", json!({})),
+                    TsThisTypeOrIdent::Ident(x) => self.ident(x),
+                };
+                self.node(
+                    x.span,
+                    "TSTypePredicate",
+                    json!({
+                        "asserts": x.asserts,
+                        "parameterName": name,
+                        "typeAnnotation": x.type_ann.as_deref().map(|x| self.type_ann(x)),
+                    }),
+                )
+            }
+            TsType::TsImportType(x) => self.import_type(x),
+        }
+    }
+
+    fn import_type(&self, import: &TsImportType) -> Value {
+        let argument = self.node(
+            import.arg.span,
+            "TSLiteralType",
+            json!({ "literal": self.str(&import.arg) }),
+        );
+        let mut node = self.node(
+            import.span,
+            "TSImportType",
+            json!({
+                "argument": argument,
+                "qualifier": import.qualifier.as_ref().map(|x| self.entity_name(x)),
+            }),
+        );
+        set(
+            &mut node,
+            "typeArguments",
+            self.type_args(&import.type_args),
+        );
+        node
+    }
+}
+
+/// Add a field only some nodes have
+fn set(node: &mut Value, key: &str, value: Option<Value>) {
+    if let Some(value) = value {
+        node[key] = value;
+    }
+}
+
+fn kind(type_only: bool) -> &'static str {
+    if type_only {
+        "type"
+    } else {
+        "value"
+    }
+}
+
+fn accessibility(accessibility: Option<Accessibility>) -> Option<Value> {
+    Some(
+        match accessibility? {
+            Accessibility::Public => "public",
+            Accessibility::Protected => "protected",
+            Accessibility::Private => "private",
+        }
+        .into(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infer_transpile_syntax_by_extension;
+
+    fn parse(source: &str) -> Value {
+        let syntax = infer_transpile_syntax_by_extension("js").unwrap();
+        EasySwcTranspiler::new(Default::default())
+            .parse("test.js", source, syntax, IsModule::Bool(true))
+            .unwrap()
+    }
+
+    /// The program without positions, which most tests do not care about
+    fn shape(source: &str) -> Value {
+        fn strip(value: &mut Value) {
+            match value {
+                Value::Object(map) => {
+                    for key in ["start", "end", "loc", "range"] {
+                        map.remove(key);
+                    }
+                    map.values_mut().for_each(strip);
+                }
+                Value::Array(values) => values.iter_mut().for_each(strip),
+                _ => {}
+            }
+        }
+        let mut program = parse(source);
+        strip(&mut program);
+        program
+    }
+
+    /// The expression of the first statement
+    fn expr(source: &str) -> Value {
+        shape(source)["body"][0]["expression"].take()
+    }
+
+    #[test]
+    fn describes_regex_and_bigint_literals() {
+        assert_eq!(
+            expr("/a+/gi"),
+            json!({
+                "type": "Literal",
+                "value": null,
+                "raw": "/a+/gi",
+                "regex": { "pattern": "a+", "flags": "gi" },
+            })
+        );
+        assert_eq!(
+            expr("10n"),
+            json!({ "type": "Literal", "value": null, "raw": "10n", "bigint": "10" })
+        );
+    }
+
+    #[test]
+    fn splits_templates_into_quasis() {
+        assert_eq!(
+            expr("`a${b}\\u0041`"),
+            json!({
+                "type": "TemplateLiteral",
+                "quasis": [
+                    { "type": "TemplateElement", "value": { "raw": "a", "cooked": "a" }, "tail": false },
+                    { "type": "TemplateElement", "value": { "raw": "\\u0041", "cooked": "A" }, "tail": true },
+                ],
+                "expressions": [{ "type": "Identifier", "name": "b" }],
+            })
+        );
+    }
+
+    #[test]
+    fn wraps_optional_chains() {
+        let chain = expr("a?.b.c()");
+        assert_eq!(chain["type"], "ChainExpression");
+        let call = &chain["expression"];
+        assert_eq!(call["type"], "CallExpression");
+        assert_eq!(call["optional"], false);
+        assert_eq!(call["callee"]["optional"], false);
+        assert_eq!(call["callee"]["object"]["optional"], true);
+        assert_eq!(call["callee"]["object"]["object"]["name"], "a");
+
+        // Only the outermost node of a chain is wrapped
+        let chain = expr("(a?.b).c");
+        assert_eq!(chain["type"], "MemberExpression");
+        assert_eq!(chain["object"]["type"], "ChainExpression");
+    }
+
+    #[test]
+    fn tells_kinds_of_properties_apart() {
+        let object = expr("({ a, b: 1, c() {}, get d() {}, set d(x) {}, [e]: 2 })");
+        let properties: Vec<_> = object["properties"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| {
+                (
+                    x["kind"].as_str().unwrap(),
+                    x["method"].as_bool().unwrap(),
+                    x["shorthand"].as_bool().unwrap(),
+                    x["computed"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            properties,
+            [
+                ("init", false, true, false),
+                ("init", false, false, false),
+                ("init", true, false, false),
+                ("get", false, false, false),
+                ("set", false, false, false),
+                ("init", false, false, true),
+            ]
+        );
+        assert_eq!(object["properties"][0]["value"]["name"], "a");
+        assert_eq!(
+            object["properties"][2]["value"]["type"],
+            "FunctionExpression"
+        );
+    }
+
+    #[test]
+    fn counts_positions_in_utf16() {
+        // Two bytes and one code unit, then four bytes and two code units
+        let program = parse("let a = 1;\n  \u{e9} + '\u{1f600}' + b");
+        let binary = &program["body"][1]["expression"];
+        assert_eq!(binary["start"], 13);
+        assert_eq!(binary["end"], 25);
+        assert_eq!(binary["range"], json!([13, 25]));
+        assert_eq!(
+            binary["loc"],
+            json!({ "start": { "line": 2, "column": 2 }, "end": { "line": 2, "column": 14 } })
+        );
+        assert_eq!(binary["left"]["right"]["range"], json!([17, 21]));
+        assert_eq!(binary["right"]["range"], json!([24, 25]));
+        assert_eq!(
+            binary["right"]["loc"]["start"],
+            json!({ "line": 2, "column": 13 })
+        );
+    }
+
+    #[test]
+    fn keeps_private_names_of_classes() {
+        let class = shape(
+            "class A { #x = 1; static #y() {} get #z() { return this.#x } has(o) { return #x in o \
+             } }",
+        );
+        let members = &class["body"][0]["body"]["body"];
+        assert_eq!(members[0]["type"], "PropertyDefinition");
+        assert_eq!(
+            members[0]["key"],
+            json!({ "type": "PrivateIdentifier", "name": "x" })
+        );
+        assert_eq!(members[1]["type"], "MethodDefinition");
+        assert_eq!(members[1]["static"], true);
+        assert_eq!(members[1]["key"]["type"], "PrivateIdentifier");
+        assert_eq!(members[2]["kind"], "get");
+        assert_eq!(
+            members[2]["value"]["body"]["body"][0]["argument"]["property"],
+            json!({ "type": "PrivateIdentifier", "name": "x" })
+        );
+        let brand_check = &members[3]["value"]["body"]["body"][0]["argument"];
+        assert_eq!(brand_check["operator"], "in");
+        assert_eq!(brand_check["left"]["type"], "PrivateIdentifier");
+    }
+}
@@ -0,0 +1,76 @@
+use swc_common::{sync::Lrc, FileName, SourceMap as SwcSourceMap};
+use swc_ecma_ast::{
+    CallExpr, Callee, EsVersion, ExportAll, Expr, ImportDecl, Lit, NamedExport, Str,
+};
+use swc_ecma_parser::{parse_file_as_module, Syntax};
+use swc_ecma_visit::{Visit, VisitWith};
+
+use crate::{diagnostics::Collector, EasySwcTranspilerError};
+
+/// A module specifier referenced by a source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub specifier: String,
+    /// Whether it comes from an `import()` call rather than a declaration
+    pub dynamic:   bool,
+}
+
+#[derive(Default)]
+struct ImportCollector(Vec<Import>);
+
+impl ImportCollector {
+    fn push(&mut self, specifier: &Str, dynamic: bool) {
+        self.0.push(Import {
+            specifier: specifier.value.to_string(),
+            dynamic,
+        });
+    }
+}
+
+impl Visit for ImportCollector {
+    fn visit_import_decl(&mut self, node: &ImportDecl) {
+        if !node.type_only {
+            self.push(&node.src, false);
+        }
+    }
+
+    fn visit_export_all(&mut self, node: &ExportAll) {
+        if !node.type_only {
+            self.push(&node.src, false);
+        }
+    }
+
+    fn visit_named_export(&mut self, node: &NamedExport) {
+        if let Some(src) = node.src.as_deref().filter(|_| !node.type_only) {
+            self.push(src, false);
+        }
+    }
+
+    fn visit_call_expr(&mut self, node: &CallExpr) {
+        // Only `import()` of a literal can be known without running the code
+        if let (Callee::Import(_), Some(arg)) = (&node.callee, node.args.first()) {
+            if let Expr::Lit(Lit::Str(src)) = &*arg.expr {
+                self.push(src, true);
+            }
+        }
+        node.visit_children_with(self);
+    }
+}
+
+/// Find the modules a source imports, in the order they appear, without
+/// transpiling it
+pub fn parse_imports(source: &str, syntax: Syntax) -> Result<Vec<Import>, EasySwcTranspilerError> {
+    let source_map: Lrc<SwcSourceMap> = Default::default();
+    let fm = source_map.new_source_file(FileName::Anon.into(), source.to_string());
+
+    let module =
+        parse_file_as_module(&fm, syntax, EsVersion::EsNext, None, &mut vec![]).map_err(|e| {
+            let collector = Collector::new(source_map.clone());
+            e.into_diagnostic(&collector.handler()).emit();
+            collector.take("cannot parse")
+        })?;
+
+    let mut collector = ImportCollector::default();
+    module.visit_with(&mut collector);
+    Ok(collector.0)
+}
@@ -5,122 +5,328 @@ use std::{io, string::FromUtf8Error};
 use derive_more::{Debug, Display, Error, From, Into};
 pub use sourcemap::SourceMap;
 use swc_common::{
-    comments::Comments,
-    errors::{ColorConfig, Handler},
-    sync::Lrc,
-    BytePos, FileName, Globals, LineCol, Mark, SourceFile, SourceMap as SwcSourceMap, GLOBALS,
+    comments::Comments, errors::HANDLER, source_map::SourceMapGenConfig, sync::Lrc, BytePos,
+    FileName, Globals, LineCol, Mark, SourceFile, SourceMap as SwcSourceMap, GLOBALS,
 };
 pub use swc_config::IsModule;
-use swc_ecma_ast::EsVersion;
+pub use swc_ecma_ast::EsVersion;
+use swc_ecma_ast::Program;
 use swc_ecma_codegen::{self, text_writer::JsWriter, Emitter};
 pub use swc_ecma_parser::Syntax;
 use swc_ecma_parser::{EsSyntax, TsSyntax};
-use swc_ecma_transforms_base::{fixer::fixer, hygiene::hygiene, resolver};
+use swc_ecma_transforms_base::{
+    fixer::fixer,
+    helpers::{inject_helpers, Helpers, HELPERS},
+    hygiene::hygiene,
+    resolver,
+};
+use swc_ecma_transforms_proposal::{
+    decorator_2022_03::decorator_2022_03,
+    decorators::{self, decorators},
+    explicit_resource_management::explicit_resource_management,
+};
 #[cfg(feature = "react")]
 use swc_ecma_transforms_react::react;
 #[cfg(feature = "typescript")]
 use swc_ecma_transforms_typescript::typescript;
+#[cfg(all(feature = "typescript", feature = "react"))]
+use swc_ecma_transforms_typescript::{tsx, TsxConfig};
+use swc_ecma_visit::VisitMutWith;
 use swc_node_comments::SwcComments;
-pub struct EasySwcTranspiler {
-    source_map: Lrc<SwcSourceMap>,
-    comments:   SwcComments,
-    handler:    Handler,
-    globals:    Globals,
-}
 
-impl Default for EasySwcTranspiler {
-    fn default() -> Self {
-        let source_map: Lrc<SwcSourceMap> = Default::default();
+pub use crate::{
+    bundle::{BundleModule, BundleOptions},
+    diagnostics::{Diagnostic, Diagnostics, Severity},
+    imports::{parse_imports, Import},
+    lint::{apply_lint_fixes, LintFix, LintProblem, LINT_RULES},
+    minify::MinifyOptions,
+};
+use crate::{class_fields::AssignClassFields, diagnostics::Collector};
 
-        let handler =
-            Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(source_map.clone()));
-        let globals = Globals::new();
-        let comments = SwcComments::default();
+mod bundle;
+mod class_fields;
+mod diagnostics;
+mod estree;
+mod imports;
+mod lint;
+mod minify;
 
-        Self {
-            source_map,
-            comments,
-            handler,
-            globals,
-        }
-    }
+/// How JSX elements are compiled, a source can pick another one with a
+/// `@jsxRuntime` or `@jsxImportSource` pragma
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsxRuntime {
+    /// Calls to the factory in scope, `React.createElement` by default
+    #[default]
+    Classic,
+    /// Calls to `jsx` and `jsxs` imported from `<import source>/jsx-runtime`
+    Automatic,
+}
+
+/// Options of a transpiler that apply to every source it transpiles
+#[derive(Debug, Clone, Default)]
+pub struct TranspilerOptions {
+    pub jsx_runtime:             JsxRuntime,
+    /// Pass the source location of each element, to `jsxDEV` from
+    /// `<import source>/jsx-dev-runtime` with the automatic runtime
+    pub jsx_development:         bool,
+    /// The function JSX elements are turned into, `React.createElement` by
+    /// default
+    pub jsx_factory:             Option<String>,
+    /// The component JSX fragments are turned into, `React.Fragment` by
+    /// default
+    pub jsx_fragment_factory:    Option<String>,
+    /// The module JSX factories are imported from with the automatic runtime,
+    /// `react` by default
+    pub jsx_import_source:       Option<String>,
+    /// Compile decorators the way `experimentalDecorators` of TypeScript
+    /// does instead of following the TC39 proposal
+    pub legacy_decorators:       bool,
+    /// Record the types of decorated declarations with `Reflect.metadata`,
+    /// only with legacy decorators
+    pub emit_decorator_metadata: bool,
+    /// Initialize class fields with assignments in the constructor instead of
+    /// defining them, like TypeScript does without `useDefineForClassFields`
+    pub assign_class_fields:     bool,
+    /// Only drop imports and exports marked with `type`, even when the others
+    /// are only used as types
+    pub verbatim_module_syntax:  bool,
+    /// Minify the output, source maps then map the minified code
+    pub minify:                  Option<MinifyOptions>,
+    /// The version the output is written for, ES2022 by default. Only how
+    /// code is printed follows it, newer syntax is kept as is
+    pub target:                  Option<EsVersion>,
+}
+
+/// Transpiles sources with the same options. Nothing is kept between
+/// sources, so any number of them can be transpiled at once from different
+/// threads
+#[derive(Debug, Clone, Default)]
+pub struct EasySwcTranspiler {
+    options: TranspilerOptions,
 }
 
 impl EasySwcTranspiler {
+    pub fn new(options: TranspilerOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn options(&self) -> &TranspilerOptions {
+        &self.options
+    }
+
+    /// Transpile `source`, named `name` in diagnostics, source maps and the
+    /// locations the JSX development runtime is given
     pub fn transpile(
         &self,
+        name: &str,
         source: &str,
         syntax: Syntax,
         is_module: IsModule,
         emit_sourcemap: bool,
     ) -> Result<(String, Option<::sourcemap::SourceMap>), EasySwcTranspilerError> {
-        let fm = self
-            .source_map
-            .new_source_file_from(FileName::Anon.into(), source.to_string().into());
+        self.transpile_with_warnings(name, source, syntax, is_module, emit_sourcemap)
+            .map(|(code, source_map, _)| (code, source_map))
+    }
+
+    /// [`Self::transpile`], also returning the warnings a successful
+    /// transpile reported
+    pub fn transpile_with_warnings(
+        &self,
+        name: &str,
+        source: &str,
+        syntax: Syntax,
+        is_module: IsModule,
+        emit_sourcemap: bool,
+    ) -> Result<(String, Option<::sourcemap::SourceMap>, Diagnostics), EasySwcTranspilerError> {
+        let session = Session::new(&self.options);
+        let fm = session.source_map.new_source_file(
+            FileName::Custom(name.to_string()).into(),
+            source.to_string(),
+        );
+        let (code, source_map) =
+            session.run(|| session.transpile(fm, syntax, is_module, emit_sourcemap))?;
+        Ok((code, source_map, session.collector.drain()))
+    }
+}
+
+/// What transpiling or bundling a set of sources keeps track of, dropped
+/// along with them once done
+struct Session<'a> {
+    options:    &'a TranspilerOptions,
+    source_map: Lrc<SwcSourceMap>,
+    comments:   SwcComments,
+    collector:  Collector,
+}
+
+impl<'a> Session<'a> {
+    fn new(options: &'a TranspilerOptions) -> Self {
+        let source_map: Lrc<SwcSourceMap> = Default::default();
+        Self {
+            options,
+            collector: Collector::new(source_map.clone()),
+            source_map,
+            comments: SwcComments::default(),
+        }
+    }
 
-        GLOBALS.set(&self.globals, || {
-            self.do_transpile(syntax, is_module, emit_sourcemap, fm)
+    /// Run `f` with the state swc passes expect. Parse errors, the minifier
+    /// and JSX pragmas report through the handler, helpers of decorators and
+    /// `using` are inlined
+    fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        GLOBALS.set(&Globals::new(), || {
+            HANDLER.set(&self.collector.handler(), || {
+                HELPERS.set(&Helpers::new(false), f)
+            })
         })
     }
 
-    fn do_transpile(
+    fn transpile(
         &self,
+        fm: Lrc<SourceFile>,
         syntax: Syntax,
         is_module: IsModule,
         emit_sourcemap: bool,
-        fm: Lrc<SourceFile>,
     ) -> Result<(String, Option<::sourcemap::SourceMap>), EasySwcTranspilerError> {
-        let mut program = swc_compiler_base::parse_js(
-            self.source_map.clone(),
-            fm,
-            &self.handler,
-            EsVersion::EsNext,
-            syntax,
-            is_module,
-            Some(&self.comments),
-        )
-        .map_err(EasySwcTranspilerError::SwcParse)?;
+        let program = self.parse(fm, syntax, is_module)?;
 
         let unresolved_mark = Mark::new();
         let top_level_mark = Mark::new();
+        let comments: Option<&dyn Comments> = Some(&self.comments);
+
+        let mut program = self
+            .transform(program, syntax, unresolved_mark, top_level_mark)
+            .apply(&mut inject_helpers(unresolved_mark))
+            .apply(&mut hygiene());
+        if let Some(minify) = &self.options.minify {
+            program = self.minify(program, minify);
+        }
+        let program = program.apply(&mut fixer(comments));
+        if self.collector.has_errors() {
+            return Err(self.collector.take("cannot transpile").into());
+        }
+
+        self.emit(&program, emit_sourcemap, self.options.minify.is_some())
+    }
+
+    fn parse(
+        &self,
+        fm: Lrc<SourceFile>,
+        syntax: Syntax,
+        is_module: IsModule,
+    ) -> Result<Program, EasySwcTranspilerError> {
+        HANDLER
+            .with(|handler| {
+                swc_compiler_base::parse_js(
+                    self.source_map.clone(),
+                    fm,
+                    handler,
+                    EsVersion::EsNext,
+                    syntax,
+                    is_module,
+                    Some(&self.comments),
+                )
+            })
+            .map_err(|e| self.collector.take(e).into())
+    }
+
+    /// Resolve the scopes of a parsed program and turn it into plain
+    /// JavaScript, leaving renaming and parenthesizing to the caller
+    fn transform(
+        &self,
+        mut program: Program,
+        syntax: Syntax,
+        unresolved_mark: Mark,
+        top_level_mark: Mark,
+    ) -> Program {
+        program = program.apply(&mut resolver(
+            unresolved_mark,
+            top_level_mark,
+            syntax.typescript(),
+        ));
+
+        // Decorators go first, metadata is taken from the types they annotate
+        program = if self.options.legacy_decorators {
+            program.apply(&mut decorators(decorators::Config {
+                legacy:                      true,
+                emit_metadata:               self.options.emit_decorator_metadata,
+                use_define_for_class_fields: !self.options.assign_class_fields,
+            }))
+        } else {
+            program.apply(&mut decorator_2022_03())
+        };
+        program = program.apply(&mut explicit_resource_management());
+        if self.options.assign_class_fields && syntax.typescript() {
+            program.visit_mut_with(&mut AssignClassFields);
+        }
 
         program = match syntax {
+            // Imports only used by the JSX factory must not be taken for types
+            #[cfg(all(feature = "typescript", feature = "react"))]
+            Syntax::Typescript(TsSyntax { tsx: true, .. }) => {
+                program.apply(&mut tsx(
+                    self.source_map.clone(),
+                    self.typescript_config(),
+                    TsxConfig {
+                        pragma:      self.options.jsx_factory.clone(),
+                        pragma_frag: self.options.jsx_fragment_factory.clone(),
+                    },
+                    &self.comments,
+                    unresolved_mark,
+                    top_level_mark,
+                ))
+            }
             #[cfg(feature = "typescript")]
             Syntax::Typescript(_) => {
-                program
-                    .apply(&mut resolver(unresolved_mark, top_level_mark, true))
-                    .apply(&mut typescript(
-                        {
-                            let mut config = swc_ecma_transforms_typescript::Config::default();
-                            config.native_class_properties = true;
-                            config
-                        },
-                        unresolved_mark,
-                        top_level_mark,
-                    ))
+                program.apply(&mut typescript(
+                    self.typescript_config(),
+                    unresolved_mark,
+                    top_level_mark,
+                ))
             }
-            Syntax::Es(_) => program.apply(&mut resolver(unresolved_mark, top_level_mark, false)),
-            // This is needed because we are left with one case, so if we disabled typescript it is
-            // left with an...interesting case effectively we are left with the
-            // ECMAScript option, i.e. have exhaustive match already
-            #[allow(unreachable_patterns)]
             _ => program,
         };
 
-        let comments: Option<&dyn Comments> = Some(&self.comments);
-
         #[cfg(feature = "react")]
         {
+            let comments: Option<&dyn Comments> = Some(&self.comments);
             program = program.apply(&mut react::<&dyn Comments>(
                 self.source_map.clone(),
                 comments,
-                Default::default(),
+                swc_ecma_transforms_react::Options {
+                    runtime: Some(match self.options.jsx_runtime {
+                        JsxRuntime::Classic => swc_ecma_transforms_react::Runtime::Classic,
+                        JsxRuntime::Automatic => swc_ecma_transforms_react::Runtime::Automatic,
+                    }),
+                    development: Some(self.options.jsx_development),
+                    pragma: self.options.jsx_factory.clone(),
+                    pragma_frag: self.options.jsx_fragment_factory.clone(),
+                    import_source: self.options.jsx_import_source.clone(),
+                    ..Default::default()
+                },
                 top_level_mark,
                 unresolved_mark,
             ));
         }
 
-        program = program.apply(&mut hygiene()).apply(&mut fixer(comments));
+        program
+    }
+
+    #[cfg(feature = "typescript")]
+    fn typescript_config(&self) -> swc_ecma_transforms_typescript::Config {
+        swc_ecma_transforms_typescript::Config {
+            native_class_properties: !self.options.assign_class_fields,
+            verbatim_module_syntax: self.options.verbatim_module_syntax,
+            ..Default::default()
+        }
+    }
+
+    fn emit(
+        &self,
+        program: &Program,
+        emit_sourcemap: bool,
+        minify: bool,
+    ) -> Result<(String, Option<::sourcemap::SourceMap>), EasySwcTranspilerError> {
+        let comments: Option<&dyn Comments> = (!minify).then_some(&self.comments);
 
         let mut buf = vec![];
         let mut srcmap: Vec<(BytePos, LineCol)> = vec![];
@@ -137,8 +343,9 @@ impl EasySwcTranspiler {
         );
 
         let mut cfg = swc_ecma_codegen::Config::default();
-        cfg.target = EsVersion::Es2022;
+        cfg.target = self.options.target.unwrap_or(EsVersion::Es2022);
         cfg.omit_last_semi = true;
+        cfg.minify = minify;
 
         let mut emitter = Emitter {
             cfg,
@@ -148,45 +355,66 @@ impl EasySwcTranspiler {
         };
 
         emitter
-            .emit_program(&program)
+            .emit_program(program)
             .map_err(EasySwcTranspilerError::SwcEmitProgram)?;
 
-        let source_map = emit_sourcemap.then(|| self.source_map.build_source_map(srcmap.as_ref()));
+        let source_map = emit_sourcemap.then(|| {
+            self.source_map
+                .build_source_map_with_config(&srcmap, None, SourceMapConfig)
+        });
         Ok((String::from_utf8(buf)?, source_map))
     }
 }
 
+/// Names sources after the modules they come from and embeds their content,
+/// so that a source map works without the original files around
+struct SourceMapConfig;
+
+impl SourceMapGenConfig for SourceMapConfig {
+    fn file_name_to_source(&self, f: &FileName) -> String {
+        match f {
+            FileName::Custom(name) => name.clone(),
+            _ => f.to_string(),
+        }
+    }
+
+    fn inline_sources_content(&self, _: &FileName) -> bool {
+        true
+    }
+}
+
 #[derive(From, Error, Display, Debug)]
 pub enum EasySwcTranspilerError {
     #[from]
-    SwcParse(anyhow::Error),
+    Diagnostics(Diagnostics),
     #[from]
     SwcEmitProgram(io::Error),
     #[from]
     Utf8(FromUtf8Error),
+    Bundle(#[error(not(source))] String),
 }
 
 pub fn infer_transpile_syntax_by_extension(extension: &str) -> Option<Syntax> {
     trie_match::trie_match! {
         match extension {
-            "js" | "mjs" => { Some(Syntax::Es(Default::default())) }
+            "js" | "mjs" => { Some(Syntax::Es(es_syntax())) }
             "jsx" | "mjsx" => {
                 if cfg!(feature = "react") {
-                    Some(Syntax::Es(EsSyntax { jsx: true, ..Default::default() }))
+                    Some(Syntax::Es(EsSyntax { jsx: true, ..es_syntax() }))
                 } else {
                     None
                 }
             }
             "ts" => {
                 if cfg!(feature = "typescript") {
-                    Some(Syntax::Typescript(Default::default()))
+                    Some(Syntax::Typescript(TsSyntax { decorators: true, ..Default::default() }))
                 } else {
                     None
                 }
             }
             "tsx" => {
                 if cfg!(all(feature = "typescript", feature = "react")) {
-                    Some(Syntax::Typescript(TsSyntax { tsx: true, ..Default::default() }))
+                    Some(Syntax::Typescript(TsSyntax { tsx: true, decorators: true, ..Default::default() }))
                 } else {
                     None
                 }
@@ -196,6 +424,17 @@ pub fn infer_transpile_syntax_by_extension(extension: &str) -> Option<Syntax> {
     }
 }
 
+/// JavaScript with the proposals the transpiler compiles away
+fn es_syntax() -> EsSyntax {
+    EsSyntax {
+        decorators: true,
+        decorators_before_export: true,
+        auto_accessors: true,
+        explicit_resource_management: true,
+        ..Default::default()
+    }
+}
+
 #[derive(Display, From, Error, Debug)]
 pub enum InferTranspileSyntaxError {
     InvalidExtension,
@@ -209,3 +448,218 @@ pub const fn get_best_transpiling() -> &'static str {
         (true, true) => "tsx",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rquickjs::{Context, Runtime};
+
+    use super::*;
+
+    fn transpile_as(
+        options: TranspilerOptions,
+        extension: &str,
+        is_module: bool,
+        source: &str,
+    ) -> String {
+        let syntax = infer_transpile_syntax_by_extension(extension).unwrap();
+        EasySwcTranspiler::new(options)
+            .transpile(
+                &format!("test.{extension}"),
+                source,
+                syntax,
+                IsModule::Bool(is_module),
+                false,
+            )
+            .unwrap()
+            .0
+    }
+
+    #[cfg(feature = "react")]
+    fn transpile(options: TranspilerOptions, source: &str) -> String {
+        transpile_as(options, "jsx", true, source)
+    }
+
+    /// Run a transpiled script and return its completion value
+    fn run(code: &str) -> String {
+        let rt = Runtime::new().unwrap();
+        let context = Context::full(&rt).unwrap();
+        context.with(|ctx| {
+            ctx.eval::<(), _>("Symbol.dispose = Symbol.for('Symbol.dispose');")
+                .unwrap();
+            ctx.eval::<String, _>(code).unwrap()
+        })
+    }
+
+    #[cfg(feature = "react")]
+    const ELEMENT: &str = "export const a = <><b x='1'>hi</b></>;";
+
+    #[cfg(feature = "react")]
+    #[test]
+    fn classic_jsx_calls_the_factories() {
+        let code = transpile(Default::default(), ELEMENT);
+        assert!(
+            code.contains("React.createElement(React.Fragment, null"),
+            "{code}"
+        );
+
+        let options = TranspilerOptions {
+            jsx_factory: Some("h".to_string()),
+            jsx_fragment_factory: Some("Fragment".to_string()),
+            ..Default::default()
+        };
+        let code = transpile(options, ELEMENT);
+        assert!(
+            code.contains("h(Fragment, null, /*#__PURE__*/ h(\"b\""),
+            "{code}"
+        );
+    }
+
+    #[cfg(feature = "react")]
+    #[test]
+    fn automatic_jsx_imports_the_runtime() {
+        let options = TranspilerOptions {
+            jsx_runtime: JsxRuntime::Automatic,
+            jsx_import_source: Some("den:jsx".to_string()),
+            ..Default::default()
+        };
+        let code = transpile(options, ELEMENT);
+        assert!(
+            code.contains(
+                "import { jsx as _jsx, Fragment as _Fragment } from \"den:jsx/jsx-runtime\""
+            ),
+            "{code}"
+        );
+
+        let options = TranspilerOptions {
+            jsx_runtime: JsxRuntime::Automatic,
+            jsx_development: true,
+            ..Default::default()
+        };
+        let code = transpile(options, ELEMENT);
+        assert!(code.contains("from \"react/jsx-dev-runtime\""), "{code}");
+        assert!(code.contains("fileName: \"test.jsx\""), "{code}");
+    }
+
+    #[cfg(feature = "react")]
+    #[test]
+    fn jsx_pragmas_override_the_options() {
+        let code = transpile(
+            Default::default(),
+            &format!("/** @jsxImportSource preact */\n{ELEMENT}"),
+        );
+        assert!(code.contains("from \"preact/jsx-runtime\""), "{code}");
+
+        let options = TranspilerOptions {
+            jsx_runtime: JsxRuntime::Automatic,
+            ..Default::default()
+        };
+        let code = transpile(
+            options,
+            &format!("/** @jsxRuntime classic @jsx h */\n{ELEMENT}"),
+        );
+        assert!(!code.contains("jsx-runtime"), "{code}");
+        assert!(code.contains("h(React.Fragment, null"), "{code}");
+    }
+
+    #[test]
+    fn transpiles_on_several_threads() {
+        let transpiler = EasySwcTranspiler::new(Default::default());
+        let syntax = infer_transpile_syntax_by_extension("js").unwrap();
+        let transpile = |name: &str, lines: usize| {
+            let source = format!("{}export const {name} = 1;", "\n".repeat(lines));
+            (0..20)
+                .map(|_| {
+                    let (_, map) = transpiler
+                        .transpile(
+                            &format!("{name}.js"),
+                            &source,
+                            syntax,
+                            IsModule::Bool(true),
+                            true,
+                        )
+                        .unwrap();
+                    let map = map.unwrap();
+                    let sources: Vec<_> = map.sources().collect();
+                    let line = map.get_token(0).unwrap().get_src_line();
+                    (sources.join(","), line)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let (a, b) = std::thread::scope(|s| {
+            let a = s.spawn(|| transpile("a", 0));
+            let b = s.spawn(|| transpile("b", 3));
+            (a.join().unwrap(), b.join().unwrap())
+        });
+        assert!(a.iter().all(|x| *x == ("a.js".to_string(), 0)), "{a:?}");
+        assert!(b.iter().all(|x| *x == ("b.js".to_string(), 3)), "{b:?}");
+    }
+
+    #[test]
+    fn compiles_decorators_of_the_proposal() {
+        let source = concat!(
+            "function twice(method, context) {\n",
+            "  context.addInitializer(function () { this.named = context.name; });\n",
+            "  return function () { return method.call(this) * 2; };\n",
+            "}\n",
+            "class A { @twice answer() { return 21; } }\n",
+            "const a = new A();\n",
+            "`${a.answer()} ${a.named}`;\n",
+        );
+        let code = transpile_as(Default::default(), "js", false, source);
+        assert!(!code.contains('@'), "{code}");
+        assert_eq!(run(&code), "42 answer");
+    }
+
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn compiles_legacy_decorators_with_metadata() {
+        let source = concat!(
+            "const calls: unknown[] = [];\n",
+            "function log(target: object, key: string, descriptor: PropertyDescriptor) {\n",
+            "  calls.push(key);\n",
+            "}\n",
+            "class A { @log greet(name: string): number { return 1; } }\n",
+            "calls.join();\n",
+        );
+        let options = TranspilerOptions {
+            legacy_decorators: true,
+            emit_decorator_metadata: true,
+            ..Default::default()
+        };
+        let code = transpile_as(options, "ts", false, source);
+        assert!(
+            code.contains("_ts_metadata(\"design:paramtypes\", [\n        String\n    ])"),
+            "{code}"
+        );
+        assert!(
+            code.contains("_ts_metadata(\"design:returntype\", Number)"),
+            "{code}"
+        );
+        assert_eq!(run(&code), "greet");
+    }
+
+    #[test]
+    fn lowers_using_declarations() {
+        let source = concat!(
+            "const log = [];\n",
+            "function resource(name) {\n",
+            "  return { [Symbol.dispose]() { log.push(`dispose ${name}`); } };\n",
+            "}\n",
+            "{\n",
+            "  using a = resource('a');\n",
+            "  using b = resource('b');\n",
+            "  log.push('body');\n",
+            "}\n",
+            "log.join();\n",
+        );
+        let code = transpile_as(Default::default(), "js", false, source);
+        assert!(!code.contains("using "), "{code}");
+        assert_eq!(run(&code), "body,dispose b,dispose a");
+
+        let source = "async function f() { await using a = resource(); }";
+        let code = transpile_as(Default::default(), "js", false, source);
+        assert!(!code.contains("using "), "{code}");
+        assert!(code.contains("await"), "{code}");
+    }
+}
@@ -0,0 +1,886 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use swc_common::{
+    comments::{Comment, CommentKind},
+    BytePos, FileName, Mark, Span, Spanned,
+};
+use swc_ecma_ast::*;
+use swc_ecma_parser::Syntax;
+use swc_ecma_transforms_base::resolver;
+use swc_ecma_utils::find_pat_ids;
+use swc_ecma_visit::{Visit, VisitWith};
+
+use crate::{EasySwcTranspiler, EasySwcTranspilerError, IsModule, Session};
+
+/// Every lint rule, all of them recommended
+pub const LINT_RULES: &[&str] = &[
+    "ban-ts-comment",
+    "eqeqeq",
+    "no-await-in-loop",
+    "no-explicit-any",
+    "no-floating-promises",
+    "no-unused-vars",
+    "prefer-const",
+];
+
+/// A problem a lint rule found in a source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintProblem {
+    pub rule:    &'static str,
+    /// Byte offsets of the problem in the source
+    pub span:    Range<usize>,
+    /// The 1-based line the problem starts on
+    pub line:    usize,
+    /// The 1-based column, in characters, the problem starts at
+    pub column:  usize,
+    pub message: String,
+    /// How to fix the problem, for rules that can do it safely
+    pub fix:     Option<LintFix>,
+}
+
+/// Replaces a range of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFix {
+    /// Byte offsets of the replaced text
+    pub span: Range<usize>,
+    pub text: String,
+}
+
+impl EasySwcTranspiler {
+    /// Run the lint `rules` over `source`, in the order the problems appear.
+    /// A `// den-lint-ignore [rules]` comment silences the line after it and
+    /// `// den-lint-ignore-file [rules]` the whole source, for every rule
+    /// when none are listed
+    pub fn lint(
+        &self,
+        name: &str,
+        source: &str,
+        syntax: Syntax,
+        rules: &[&str],
+    ) -> Result<Vec<LintProblem>, EasySwcTranspilerError> {
+        let session = Session::new(&self.options);
+        let fm = session.source_map.new_source_file(
+            FileName::Custom(name.to_string()).into(),
+            source.to_string(),
+        );
+        session.run(|| {
+            let program = session.parse(fm.clone(), syntax, IsModule::Unknown)?;
+            if session.collector.has_errors() {
+                return Err(session.collector.take("cannot parse").into());
+            }
+            let program =
+                program.apply(&mut resolver(Mark::new(), Mark::new(), syntax.typescript()));
+
+            let mut comments: Vec<Comment> = vec![];
+            for map in [&session.comments.leading, &session.comments.trailing] {
+                for entry in map.iter() {
+                    comments.extend(entry.value().iter().cloned());
+                }
+            }
+            comments.sort_by_key(|x| x.span.lo);
+            comments.dedup_by_key(|x| x.span.lo);
+
+            let mut found = Found::default();
+            for rule in rules {
+                match *rule {
+                    "ban-ts-comment" => ban_ts_comment(&comments, &mut found),
+                    "eqeqeq" => {
+                        program.visit_with(&mut EqEqEq {
+                            source,
+                            start: fm.start_pos.0,
+                            found: &mut found,
+                        })
+                    }
+                    "no-await-in-loop" => {
+                        program.visit_with(&mut NoAwaitInLoop {
+                            in_loop: false,
+                            found:   &mut found,
+                        })
+                    }
+                    "no-explicit-any" => program.visit_with(&mut NoExplicitAny(&mut found)),
+                    "no-floating-promises" => no_floating_promises(&program, &mut found),
+                    "no-unused-vars" => no_unused_vars(&program, &mut found),
+                    "prefer-const" => prefer_const(&program, source, fm.start_pos.0, &mut found),
+                    _ => {}
+                }
+            }
+
+            let line = |span: Span| session.source_map.lookup_char_pos(span.lo);
+            let ignores: Vec<_> = comments
+                .iter()
+                .filter_map(|x| Some((ignore(x)?, session.source_map.lookup_char_pos(x.span.hi))))
+                .collect();
+            let ignored = |rule: &str, at: usize| {
+                ignores.iter().any(|((file, rules), loc)| {
+                    (*file || loc.line + 1 == at)
+                        && (rules.is_empty() || rules.iter().any(|x| x == rule))
+                })
+            };
+
+            let start = fm.start_pos.0 as usize;
+            let bytes = |span: Span| span.lo.0 as usize - start..span.hi.0 as usize - start;
+            let mut problems: Vec<_> = found
+                .0
+                .into_iter()
+                .filter_map(|(rule, span, message, fix)| {
+                    let loc = line(span);
+                    if ignored(rule, loc.line) {
+                        return None;
+                    }
+                    Some(LintProblem {
+                        rule,
+                        span: bytes(span),
+                        line: loc.line,
+                        column: loc.col.0 + 1,
+                        message,
+                        fix: fix.map(|(span, text)| {
+                            LintFix {
+                                span: bytes(span),
+                                text,
+                            }
+                        }),
+                    })
+                })
+                .collect();
+            problems.sort_by_key(|x| (x.span.start, x.rule));
+            Ok(problems)
+        })
+    }
+}
+
+/// Apply the fixes of `problems` to `source`, skipping those overlapping one
+/// applied already
+pub fn apply_lint_fixes(source: &str, problems: &[LintProblem]) -> String {
+    let mut fixes: Vec<_> = problems.iter().filter_map(|x| x.fix.as_ref()).collect();
+    fixes.sort_by_key(|x| x.span.start);
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut at = 0;
+    for fix in fixes {
+        if fix.span.start < at {
+            continue;
+        }
+        fixed.push_str(&source[at..fix.span.start]);
+        fixed.push_str(&fix.text);
+        at = fix.span.end;
+    }
+    fixed.push_str(&source[at..]);
+    fixed
+}
+
+/// Whether a comment ignores the whole source or the next line, and for
+/// which rules
+fn ignore(comment: &Comment) -> Option<(bool, Vec<String>)> {
+    let text = comment.text.trim();
+    let (file, rules) = match text.strip_prefix("den-lint-ignore-file") {
+        Some(rules) => (true, rules),
+        None => (false, text.strip_prefix("den-lint-ignore")?),
+    };
+    if !rules.is_empty() && !rules.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some((file, rules.split_whitespace().map(String::from).collect()))
+}
+
+type Fix = Option<(Span, String)>;
+
+/// What the rules found, in no particular order
+#[derive(Default)]
+struct Found(Vec<(&'static str, Span, String, Fix)>);
+
+impl Found {
+    fn report(&mut self, rule: &'static str, span: Span, message: impl Into<String>, fix: Fix) {
+        self.0.push((rule, span, message.into(), fix));
+    }
+}
+
+/// `@ts-ignore` and `@ts-nocheck` hide errors, `@ts-expect-error` is
+/// allowed with a description of why
+fn ban_ts_comment(comments: &[Comment], found: &mut Found) {
+    for comment in comments {
+        let text = match comment.kind {
+            CommentKind::Line => comment.text.trim_start_matches('/'),
+            CommentKind::Block => comment.text.trim_start_matches('*'),
+        };
+        let text = text.trim_start();
+        let directive = ["@ts-ignore", "@ts-nocheck", "@ts-expect-error"]
+            .into_iter()
+            .find(|x| {
+                text.strip_prefix(x).is_some_and(|rest| {
+                    !rest.starts_with(|c: char| c.is_alphanumeric() || c == '-')
+                })
+            });
+        match directive {
+            Some("@ts-expect-error") => {
+                let description = text["@ts-expect-error".len()..]
+                    .trim_start_matches(|c: char| c.is_whitespace() || c == ':' || c == '-');
+                if description.trim().is_empty() {
+                    found.report(
+                        "ban-ts-comment",
+                        comment.span,
+                        "`@ts-expect-error` needs a description of why",
+                        None,
+                    );
+                }
+            }
+            Some(directive) => {
+                found.report(
+                    "ban-ts-comment",
+                    comment.span,
+                    format!("Do not use `{directive}`, it hides errors"),
+                    None,
+                )
+            }
+            None => {}
+        }
+    }
+}
+
+/// `==` and `!=` coerce their operands, the fix is only offered for
+/// `typeof` checks where that makes no difference
+struct EqEqEq<'a> {
+    source: &'a str,
+    start:  u32,
+    found:  &'a mut Found,
+}
+
+impl Visit for EqEqEq<'_> {
+    fn visit_bin_expr(&mut self, node: &BinExpr) {
+        node.visit_children_with(self);
+        let (strict, op) = match node.op {
+            BinaryOp::EqEq => ("===", "=="),
+            BinaryOp::NotEq => ("!==", "!="),
+            _ => return,
+        };
+
+        let is_typeof = |x: &Expr| {
+            matches!(
+                x.unwrap_parens(),
+                Expr::Unary(UnaryExpr {
+                    op: UnaryOp::TypeOf,
+                    ..
+                })
+            )
+        };
+        let is_str = |x: &Expr| matches!(x.unwrap_parens(), Expr::Lit(Lit::Str(_)));
+        let safe = (is_typeof(&node.left) && is_str(&node.right))
+            || (is_str(&node.left) && is_typeof(&node.right));
+
+        // The operator is the only `==` or `!=` between the operands
+        let between = node.left.span().hi.0 - self.start..node.right.span().lo.0 - self.start;
+        let fix = self
+            .source
+            .get(between.start as usize..between.end as usize)
+            .and_then(|x| x.find(op))
+            .filter(|_| safe)
+            .map(|i| {
+                let lo = node.left.span().hi + BytePos(i as u32);
+                (
+                    Span::new(lo, lo + BytePos(op.len() as u32)),
+                    strict.to_string(),
+                )
+            });
+        self.found.report(
+            "eqeqeq",
+            node.span,
+            format!("Expected `{strict}` instead of `{op}`"),
+            fix,
+        );
+    }
+}
+
+/// Awaiting in a loop runs what could run at once one after another
+struct NoAwaitInLoop<'a> {
+    in_loop: bool,
+    found:   &'a mut Found,
+}
+
+impl NoAwaitInLoop<'_> {
+    fn in_loop(&mut self, in_loop: bool, f: impl FnOnce(&mut Self)) {
+        let outer = std::mem::replace(&mut self.in_loop, in_loop);
+        f(self);
+        self.in_loop = outer;
+    }
+}
+
+impl Visit for NoAwaitInLoop<'_> {
+    fn visit_await_expr(&mut self, node: &AwaitExpr) {
+        if self.in_loop {
+            self.found.report(
+                "no-await-in-loop",
+                node.span,
+                "Unexpected `await` inside a loop",
+                None,
+            );
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_for_of_stmt(&mut self, node: &ForOfStmt) {
+        if node.is_await && self.in_loop {
+            self.found.report(
+                "no-await-in-loop",
+                node.span,
+                "Unexpected `for await` inside a loop",
+                None,
+            );
+        }
+        node.left.visit_with(self);
+        node.right.visit_with(self);
+        // The body of `for await` awaits one element at a time anyway
+        self.in_loop(!node.is_await, |x| node.body.visit_with(x));
+    }
+
+    fn visit_for_in_stmt(&mut self, node: &ForInStmt) {
+        node.left.visit_with(self);
+        node.right.visit_with(self);
+        self.in_loop(true, |x| node.body.visit_with(x));
+    }
+
+    fn visit_for_stmt(&mut self, node: &ForStmt) {
+        node.init.visit_with(self);
+        self.in_loop(true, |x| {
+            node.test.visit_with(x);
+            node.update.visit_with(x);
+            node.body.visit_with(x);
+        });
+    }
+
+    fn visit_while_stmt(&mut self, node: &WhileStmt) {
+        self.in_loop(true, |x| node.visit_children_with(x));
+    }
+
+    fn visit_do_while_stmt(&mut self, node: &DoWhileStmt) {
+        self.in_loop(true, |x| node.visit_children_with(x));
+    }
+
+    fn visit_function(&mut self, node: &Function) {
+        self.in_loop(false, |x| node.visit_children_with(x));
+    }
+
+    fn visit_arrow_expr(&mut self, node: &ArrowExpr) {
+        self.in_loop(false, |x| node.visit_children_with(x));
+    }
+
+    fn visit_class(&mut self, node: &Class) {
+        self.in_loop(false, |x| node.visit_children_with(x));
+    }
+}
+
+struct NoExplicitAny<'a>(&'a mut Found);
+
+impl Visit for NoExplicitAny<'_> {
+    fn visit_ts_keyword_type(&mut self, node: &TsKeywordType) {
+        if node.kind == TsKeywordTypeKind::TsAnyKeyword {
+            self.0.report(
+                "no-explicit-any",
+                node.span,
+                "Unexpected `any`, use `unknown` or a more specific type",
+                None,
+            );
+        }
+    }
+}
+
+/// Promises created by a statement and dropped, so that their rejections go
+/// unhandled. Without types only calls that evidently return promises count:
+/// async functions of the source, `fetch`, `Promise` and `.then` or
+/// `.finally` without a rejection handler
+fn no_floating_promises(program: &Program, found: &mut Found) {
+    #[derive(Default)]
+    struct AsyncFunctions(HashSet<Id>);
+
+    impl Visit for AsyncFunctions {
+        fn visit_fn_decl(&mut self, node: &FnDecl) {
+            if node.function.is_async {
+                self.0.insert(node.ident.to_id());
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_var_declarator(&mut self, node: &VarDeclarator) {
+            if let (Pat::Ident(name), Some(init)) = (&node.name, &node.init) {
+                let is_async = match init.unwrap_parens() {
+                    Expr::Fn(x) => x.function.is_async,
+                    Expr::Arrow(x) => x.is_async,
+                    _ => false,
+                };
+                if is_async {
+                    self.0.insert(name.to_id());
+                }
+            }
+            node.visit_children_with(self);
+        }
+    }
+
+    struct Floating<'a> {
+        async_functions: HashSet<Id>,
+        found:           &'a mut Found,
+    }
+
+    impl Floating<'_> {
+        fn is_promise(&self, expr: &Expr) -> bool {
+            match expr.unwrap_parens() {
+                Expr::New(x) => matches!(&*x.callee, Expr::Ident(x) if x.sym == "Promise"),
+                Expr::Call(CallExpr {
+                    callee: Callee::Expr(callee),
+                    args,
+                    ..
+                }) => {
+                    match callee.unwrap_parens() {
+                        Expr::Ident(x) => {
+                            x.sym == "fetch" || self.async_functions.contains(&x.to_id())
+                        }
+                        Expr::Fn(x) => x.function.is_async,
+                        Expr::Arrow(x) => x.is_async,
+                        Expr::Member(MemberExpr {
+                            obj,
+                            prop: MemberProp::Ident(prop),
+                            ..
+                        }) => {
+                            match &*prop.sym {
+                                "then" => args.len() < 2,
+                                "finally" => true,
+                                "all" | "allSettled" | "any" | "race" | "reject" | "resolve" => {
+                                    matches!(obj.unwrap_parens(), Expr::Ident(x) if x.sym == "Promise")
+                                }
+                                _ => false,
+                            }
+                        }
+                        _ => false,
+                    }
+                }
+                _ => false,
+            }
+        }
+    }
+
+    impl Visit for Floating<'_> {
+        fn visit_expr_stmt(&mut self, node: &ExprStmt) {
+            if self.is_promise(&node.expr) {
+                self.found.report(
+                    "no-floating-promises",
+                    node.span,
+                    "Promises must be awaited, handled with `.catch` or ignored with `void`",
+                    None,
+                );
+            }
+            node.visit_children_with(self);
+        }
+    }
+
+    let mut async_functions = AsyncFunctions::default();
+    program.visit_with(&mut async_functions);
+    program.visit_with(&mut Floating {
+        async_functions: async_functions.0,
+        found,
+    });
+}
+
+/// Variables, functions, classes and imports declared and never referred
+/// to. Exports count as used, and so do names starting with `_` and `React`
+/// in sources with JSX, which is used by the classic runtime
+fn no_unused_vars(program: &Program, found: &mut Found) {
+    #[derive(Default)]
+    struct Usage {
+        declared:    Vec<Ident>,
+        exported:    HashSet<Id>,
+        occurrences: HashMap<Id, usize>,
+        has_jsx:     bool,
+    }
+
+    impl Visit for Usage {
+        fn visit_ident(&mut self, node: &Ident) {
+            *self.occurrences.entry(node.to_id()).or_default() += 1;
+        }
+
+        fn visit_var_decl(&mut self, node: &VarDecl) {
+            if !node.declare {
+                for declarator in &node.decls {
+                    self.declared
+                        .extend(find_pat_ids::<_, Ident>(&declarator.name));
+                }
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_fn_decl(&mut self, node: &FnDecl) {
+            if !node.declare {
+                self.declared.push(node.ident.clone());
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_class_decl(&mut self, node: &ClassDecl) {
+            if !node.declare {
+                self.declared.push(node.ident.clone());
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_import_decl(&mut self, node: &ImportDecl) {
+            for specifier in &node.specifiers {
+                self.declared.push(specifier.local().clone());
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_export_decl(&mut self, node: &ExportDecl) {
+            match &node.decl {
+                Decl::Class(x) => {
+                    self.exported.insert(x.ident.to_id());
+                }
+                Decl::Fn(x) => {
+                    self.exported.insert(x.ident.to_id());
+                }
+                Decl::Var(x) => {
+                    for declarator in &x.decls {
+                        self.exported
+                            .extend(find_pat_ids::<_, Id>(&declarator.name));
+                    }
+                }
+                _ => {}
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_ts_module_decl(&mut self, node: &TsModuleDecl) {
+            // Everything in ambient modules only describes what exists
+            if !node.declare {
+                node.visit_children_with(self);
+            }
+        }
+
+        fn visit_jsx_element(&mut self, node: &JSXElement) {
+            self.has_jsx = true;
+            node.visit_children_with(self);
+        }
+
+        fn visit_jsx_fragment(&mut self, node: &JSXFragment) {
+            self.has_jsx = true;
+            node.visit_children_with(self);
+        }
+    }
+
+    let mut usage = Usage::default();
+    program.visit_with(&mut usage);
+
+    let mut declarations: HashMap<Id, usize> = HashMap::new();
+    for ident in &usage.declared {
+        *declarations.entry(ident.to_id()).or_default() += 1;
+    }
+    let mut reported = HashSet::new();
+    for ident in &usage.declared {
+        let id = ident.to_id();
+        if ident.sym.starts_with('_')
+            || (usage.has_jsx && ident.sym == "React")
+            || usage.exported.contains(&id)
+            || usage.occurrences.get(&id) > declarations.get(&id)
+            || !reported.insert(id)
+        {
+            continue;
+        }
+        found.report(
+            "no-unused-vars",
+            ident.span,
+            format!("`{}` is declared but never used", ident.sym),
+            None,
+        );
+    }
+}
+
+/// `let` declarations never assigned again, which are fixed into `const`
+fn prefer_const(program: &Program, source: &str, start: u32, found: &mut Found) {
+    /// `let` declarations that could be `const` as far as their initializers
+    /// go, with what they declare, and everything assigned anywhere
+    #[derive(Default)]
+    struct Walk {
+        candidates: Vec<(Span, Vec<Id>)>,
+        assigned:   HashSet<Id>,
+    }
+
+    impl Walk {
+        fn declaration(&mut self, decl: &VarDecl, in_head: bool) {
+            if decl.kind == VarDeclKind::Let
+                && !decl.declare
+                && (in_head || decl.decls.iter().all(|x| x.init.is_some()))
+            {
+                let ids = decl
+                    .decls
+                    .iter()
+                    .flat_map(|x| find_pat_ids::<_, Id>(&x.name))
+                    .collect();
+                self.candidates.push((decl.span, ids));
+            }
+        }
+
+        fn head(&mut self, head: &ForHead) {
+            match head {
+                ForHead::VarDecl(x) => {
+                    self.declaration(x, true);
+                    x.visit_children_with(self);
+                }
+                ForHead::Pat(x) => {
+                    self.assigned.extend(find_pat_ids::<_, Id>(&**x));
+                    x.visit_with(self);
+                }
+                ForHead::UsingDecl(x) => x.visit_with(self),
+            }
+        }
+    }
+
+    impl Visit for Walk {
+        fn visit_var_decl(&mut self, node: &VarDecl) {
+            self.declaration(node, false);
+            node.visit_children_with(self);
+        }
+
+        fn visit_for_in_stmt(&mut self, node: &ForInStmt) {
+            self.head(&node.left);
+            node.right.visit_with(self);
+            node.body.visit_with(self);
+        }
+
+        fn visit_for_of_stmt(&mut self, node: &ForOfStmt) {
+            self.head(&node.left);
+            node.right.visit_with(self);
+            node.body.visit_with(self);
+        }
+
+        fn visit_assign_expr(&mut self, node: &AssignExpr) {
+            match &node.left {
+                AssignTarget::Simple(SimpleAssignTarget::Ident(x)) => {
+                    self.assigned.insert(x.to_id());
+                }
+                AssignTarget::Pat(AssignTargetPat::Array(x)) => {
+                    self.assigned.extend(find_pat_ids::<_, Id>(x));
+                }
+                AssignTarget::Pat(AssignTargetPat::Object(x)) => {
+                    self.assigned.extend(find_pat_ids::<_, Id>(x));
+                }
+                _ => {}
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_update_expr(&mut self, node: &UpdateExpr) {
+            if let Expr::Ident(x) = node.arg.unwrap_parens() {
+                self.assigned.insert(x.to_id());
+            }
+            node.visit_children_with(self);
+        }
+    }
+
+    let mut walk = Walk::default();
+    program.visit_with(&mut walk);
+    for (span, ids) in walk.candidates {
+        if ids.is_empty() || ids.iter().any(|x| walk.assigned.contains(x)) {
+            continue;
+        }
+        let lo = (span.lo.0 - start) as usize;
+        let keyword = span.with_hi(span.lo + BytePos(3));
+        let fix = source
+            .get(lo..lo + 3)
+            .filter(|x| *x == "let")
+            .map(|_| (keyword, "const".to_string()));
+        found.report(
+            "prefer-const",
+            keyword,
+            "Never assigned again, use `const` instead of `let`",
+            fix,
+        );
+    }
+}
+
+#[cfg(all(test, feature = "typescript", feature = "react"))]
+mod tests {
+    use super::*;
+    use crate::infer_transpile_syntax_by_extension;
+
+    fn lint(rule: &str, source: &str) -> Vec<LintProblem> {
+        let syntax = infer_transpile_syntax_by_extension("tsx").unwrap();
+        EasySwcTranspiler::default()
+            .lint("test.tsx", source, syntax, &[rule])
+            .unwrap()
+    }
+
+    /// The lines `rule` reports problems on
+    fn lines(rule: &str, source: &str) -> Vec<usize> {
+        lint(rule, source).iter().map(|x| x.line).collect()
+    }
+
+    fn fixed(rule: &str, source: &str) -> String {
+        apply_lint_fixes(source, &lint(rule, source))
+    }
+
+    #[test]
+    fn ban_ts_comment() {
+        let source = concat!(
+            "// @ts-ignore\n",
+            "/* @ts-nocheck */\n",
+            "// @ts-expect-error\n",
+            "// @ts-expect-error: the types are wrong\n",
+            "// @ts-ignored is not a directive\n",
+        );
+        assert_eq!(lines("ban-ts-comment", source), [1, 2, 3]);
+    }
+
+    #[test]
+    fn eqeqeq() {
+        let source = concat!(
+            "declare const a: unknown, b: unknown;\n",
+            "a == b;\n",
+            "a != null;\n",
+            "typeof a == 'string';\n",
+            "a === b;\n",
+        );
+        assert_eq!(lines("eqeqeq", source), [2, 3, 4]);
+        assert_eq!(
+            fixed("eqeqeq", source),
+            source.replace("typeof a == ", "typeof a === ")
+        );
+    }
+
+    #[test]
+    fn no_await_in_loop() {
+        let source = concat!(
+            "declare const xs: number[], f: (x: number) => Promise<void>;\n",
+            "for (const x of xs) await f(x);\n",
+            "while (true) { for await (const x of xs) {} }\n",
+            "for (const x of xs) xs.map(async () => await f(x));\n",
+            "for await (const x of xs) await f(x);\n",
+            "await Promise.all(xs.map(f));\n",
+        );
+        assert_eq!(lines("no-await-in-loop", source), [2, 3]);
+    }
+
+    #[test]
+    fn no_explicit_any() {
+        let source = "let a: any; let b: unknown; let c: Array<any>;\nexport { a, b, c }\n";
+        let problems = lint("no-explicit-any", source);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(&source[problems[1].span.clone()], "any");
+        assert_eq!(problems[1].column, 42);
+    }
+
+    #[test]
+    fn no_floating_promises() {
+        let source = concat!(
+            "async function f() {}\n",
+            "const g = async () => {};\n",
+            "f();\n",
+            "g();\n",
+            "fetch('/');\n",
+            "f().then(() => {});\n",
+            "Promise.resolve();\n",
+            "void f();\n",
+            "await g();\n",
+            "f().then(() => {}, () => {});\n",
+            "f().catch(() => {});\n",
+            "const p = f();\n",
+            "export { p }\n",
+        );
+        assert_eq!(lines("no-floating-promises", source), [3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn no_unused_vars() {
+        let source = concat!(
+            "import { a, b } from './x.ts';\n",
+            "const c = 1, _d = 2;\n",
+            "function e() { return b }\n",
+            "class F {}\n",
+            "export const g = e();\n",
+            "declare const h: number;\n",
+        );
+        let problems = lint("no-unused-vars", source);
+        let messages: Vec<_> = problems.iter().map(|x| x.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "`a` is declared but never used",
+                "`c` is declared but never used",
+                "`F` is declared but never used",
+            ]
+        );
+        assert!(lines("no-unused-vars", "import React from 'react';\n<div />;\n").is_empty());
+    }
+
+    #[test]
+    fn prefer_const() {
+        let source = concat!(
+            "let a = 1;\n",
+            "let b = 1;\n",
+            "b++;\n",
+            "let c;\n",
+            "c = 1;\n",
+            "for (let x of [a, b, c]) console.log(x);\n",
+        );
+        assert_eq!(lines("prefer-const", source), [1, 6]);
+        assert_eq!(
+            fixed("prefer-const", source),
+            source
+                .replacen("let a", "const a", 1)
+                .replacen("let x", "const x", 1)
+        );
+    }
+
+    #[test]
+    fn ignore_comments() {
+        let source = concat!(
+            "declare const a: unknown;\n",
+            "// den-lint-ignore eqeqeq\n",
+            "a == 1;\n",
+            "// den-lint-ignore prefer-const\n",
+            "a == 2;\n",
+            "// den-lint-ignore\n",
+            "a == 3;\n",
+        );
+        assert_eq!(lines("eqeqeq", source), [5]);
+        let source = format!("// den-lint-ignore-file eqeqeq\n{source}");
+        assert!(lines("eqeqeq", &source).is_empty());
+    }
+
+    #[test]
+    fn fixes_destructuring_only_when_nothing_is_reassigned() {
+        let source = concat!(
+            "declare const o: { a: number, b: number };\n",
+            "let { a, b } = o;\n",
+            "a = 2;\n",
+            "let [c, d] = [a, b];\n",
+            "export { a, b, c, d }\n",
+        );
+        assert_eq!(lines("prefer-const", source), [4]);
+        assert_eq!(
+            fixed("prefer-const", source),
+            source.replace("let [c, d]", "const [c, d]")
+        );
+    }
+
+    #[test]
+    fn skips_fixes_overlapping_applied_ones() {
+        let problem = |span: Range<usize>, text: &str| {
+            LintProblem {
+                rule:    "test",
+                span:    span.clone(),
+                line:    1,
+                column:  span.start + 1,
+                message: String::new(),
+                fix:     Some(LintFix {
+                    span,
+                    text: text.to_string(),
+                }),
+            }
+        };
+        let problems = [
+            problem(4..8, "X"),
+            problem(0..3, "a"),
+            problem(2..6, "Y"),
+            problem(8..9, "!"),
+        ];
+        assert_eq!(apply_lint_fixes("abc defgh ij", &problems), "a X! ij");
+    }
+}
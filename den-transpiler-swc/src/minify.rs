@@ -0,0 +1,203 @@
+use swc_common::{Mark, SyntaxContext};
+use swc_ecma_ast::Program;
+use swc_ecma_minifier::{
+    optimize,
+    option::{CompressOptions, ExtraOptions, MangleOptions, MinifyOptions as SwcMinifyOptions},
+};
+use swc_ecma_transforms_base::resolver;
+use swc_ecma_visit::{VisitMut, VisitMutWith};
+
+use crate::Session;
+
+/// How output is minified: compressed, with dead code removed, then with
+/// names mangled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MinifyOptions {
+    /// Keep the names of functions, for code that relies on `Function.name`
+    pub keep_fn_names:    bool,
+    /// Keep the names of classes, for code that relies on `Class.name`
+    pub keep_class_names: bool,
+}
+
+impl MinifyOptions {
+    fn swc(&self, module: bool) -> SwcMinifyOptions {
+        #[allow(deprecated)]
+        let mangle = MangleOptions {
+            props:              None,
+            // The top level of a script is the global scope
+            top_level:          Some(module),
+            keep_class_names:   self.keep_class_names,
+            keep_fn_names:      self.keep_fn_names,
+            keep_private_props: false,
+            ie8:                false,
+            safari10:           false,
+            reserved:           vec![],
+            eval:               false,
+        };
+        SwcMinifyOptions {
+            compress: Some(CompressOptions {
+                module,
+                keep_classnames: self.keep_class_names,
+                keep_fnames: self.keep_fn_names,
+                ..Default::default()
+            }),
+            mangle: Some(mangle),
+            ..Default::default()
+        }
+    }
+}
+
+/// Forgets which scope every identifier belongs to, which is safe once
+/// hygiene has given conflicting ones different names
+struct ResetContext;
+
+impl VisitMut for ResetContext {
+    fn visit_mut_syntax_context(&mut self, node: &mut SyntaxContext) {
+        *node = SyntaxContext::empty();
+    }
+}
+
+impl Session<'_> {
+    /// Minify a program that went through hygiene already. Spans are kept, so
+    /// source maps still point to the original sources
+    pub(crate) fn minify(&self, mut program: Program, options: &MinifyOptions) -> Program {
+        let module = program.is_module();
+        program.visit_mut_with(&mut ResetContext);
+        let unresolved_mark = Mark::new();
+        let top_level_mark = Mark::new();
+        program = program.apply(&mut resolver(unresolved_mark, top_level_mark, false));
+        optimize(
+            program,
+            self.source_map.clone(),
+            None,
+            None,
+            &options.swc(module),
+            &ExtraOptions {
+                unresolved_mark,
+                top_level_mark,
+                mangle_name_cache: None,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rquickjs::{Context, Module, Runtime};
+
+    use super::*;
+    use crate::{EasySwcTranspiler, IsModule, TranspilerOptions};
+
+    const SOURCE: &str = "
+        function greeting(name) {
+            const message = 'hello ' + name;
+            return message;
+        }
+        class Greeter {
+            greet() { return greeting('den') }
+        }
+        globalThis.result = [new Greeter().greet(), greeting.name, Greeter.name].join();
+    ";
+
+    fn minify(minify: MinifyOptions, is_module: bool) -> String {
+        EasySwcTranspiler::new(TranspilerOptions {
+            minify: Some(minify),
+            ..Default::default()
+        })
+        .transpile(
+            "test.js",
+            SOURCE,
+            Default::default(),
+            IsModule::Bool(is_module),
+            false,
+        )
+        .unwrap()
+        .0
+    }
+
+    /// What the minified module sets `result` to
+    fn run(code: &str) -> String {
+        let runtime = Runtime::new().unwrap();
+        let context = Context::full(&runtime).unwrap();
+        context.with(|ctx| {
+            Module::evaluate(ctx.clone(), "test", code)
+                .and_then(|x| x.finish::<()>())
+                .unwrap();
+            ctx.globals().get("result").unwrap()
+        })
+    }
+
+    #[test]
+    fn mangles_module_names() {
+        let code = minify(MinifyOptions::default(), true);
+        assert!(
+            !code.contains("greeting") && !code.contains("message"),
+            "{code}"
+        );
+        assert_eq!(run(&code), "hello den,e,n");
+    }
+
+    #[test]
+    fn keeps_names_when_asked() {
+        let options = MinifyOptions {
+            keep_fn_names:    true,
+            keep_class_names: true,
+        };
+        let code = minify(options, true);
+        assert!(!code.contains("message"), "{code}");
+        assert_eq!(run(&code), "hello den,greeting,Greeter");
+    }
+
+    #[test]
+    fn keeps_the_globals_of_scripts() {
+        let code = minify(MinifyOptions::default(), false);
+        assert!(
+            code.contains("function greeting(") && code.contains("class Greeter"),
+            "{code}"
+        );
+        assert!(!code.contains("message"), "{code}");
+    }
+
+    /// The 0-based line and column of the first `text` in `code`
+    fn position(code: &str, text: &str) -> (u32, u32) {
+        let offset = code
+            .find(text)
+            .unwrap_or_else(|| panic!("no {text} in {code}"));
+        let line = code[..offset].matches('\n').count();
+        let column = offset - code[..offset].rfind('\n').map_or(0, |x| x + 1);
+        (line as u32, column as u32)
+    }
+
+    #[test]
+    fn maps_minified_code_to_the_source() {
+        let transpiler = EasySwcTranspiler::new(TranspilerOptions {
+            minify: Some(MinifyOptions::default()),
+            ..Default::default()
+        });
+        let (code, map) = transpiler
+            .transpile(
+                "test.js",
+                SOURCE,
+                Default::default(),
+                IsModule::Bool(true),
+                true,
+            )
+            .unwrap();
+        let map = map.unwrap();
+
+        // The inlined `message` and the mangled `greeting`
+        for (minified, source) in [
+            ("\"hello \"", "'hello '"),
+            ("e(\"den\")", "greeting('den')"),
+        ] {
+            let (line, column) = position(&code, minified);
+            let token = map.lookup_token(line, column).unwrap();
+            assert_eq!(
+                (token.get_src_line(), token.get_src_col()),
+                position(SOURCE, source),
+                "{minified} in {code}"
+            );
+            assert_eq!(token.get_source(), Some("test.js"));
+        }
+    }
+}
@@ -1 +1,2 @@
 #[cfg(feature = "serde_json")] pub mod serde_json;
+pub mod symbol;
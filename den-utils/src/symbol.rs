@@ -0,0 +1,26 @@
+use rquickjs::{object::Property, Ctx, Function, Object, Result, Symbol};
+
+/// `Symbol.dispose`, called when a `using` declaration goes out of scope
+pub fn dispose<'js>(ctx: &Ctx<'js>) -> Result<Symbol<'js>> {
+    well_known(ctx, "dispose")
+}
+
+/// `Symbol.asyncDispose`, awaited when an `await using` declaration goes out
+/// of scope
+pub fn async_dispose<'js>(ctx: &Ctx<'js>) -> Result<Symbol<'js>> {
+    well_known(ctx, "asyncDispose")
+}
+
+/// A well-known symbol, defined on `Symbol` when the engine lacks it. It is
+/// the registered `Symbol.<name>`, which is what transpiled code falls back to
+fn well_known<'js>(ctx: &Ctx<'js>, name: &str) -> Result<Symbol<'js>> {
+    let symbol: Object = ctx.globals().get("Symbol")?;
+    if let Some(existing) = symbol.get::<_, Option<Symbol>>(name)? {
+        return Ok(existing);
+    }
+
+    let registered: Function = symbol.get("for")?;
+    let value: Symbol = registered.call((format!("Symbol.{name}"),))?;
+    symbol.prop(name, Property::from(value.clone()))?;
+    Ok(value)
+}
@@ -1,9 +1,22 @@
-use den_core::engine::{Engine, EngineError};
+use std::path::PathBuf;
+
+use den_core::{
+    config::Config,
+    engine::{Engine, EngineError},
+    standalone::Payload,
+};
 use futures::prelude::*;
-use rquickjs::{async_with, convert::Coerced};
-use tokio::{signal, sync::mpsc, task::yield_now};
+use rquickjs::{async_with, convert::Coerced, Ctx, FromJs, Object, Type, Value};
+use tokio::{
+    signal,
+    sync::{mpsc, oneshot},
+};
+use tokio_util::sync::CancellationToken;
 
-use crate::repl;
+use crate::{
+    repl::{self, ReplSession},
+    transcript::{self, Transcript},
+};
 
 pub struct App {
     pub(crate) engine:      Engine,
@@ -11,23 +24,126 @@ pub struct App {
 }
 
 impl App {
-    pub async fn new() -> Self {
-        Self {
-            engine:                 Engine::new().await,
+    pub async fn new(config: &Config) -> Result<Self, EngineError> {
+        Ok(Self {
+            engine:                 Engine::with_config(config).await?,
             wait_for_cancel_signal: false,
+        })
+    }
+
+    /// An app running a compiled program
+    pub async fn standalone(payload: Payload) -> Result<Self, EngineError> {
+        Ok(Self {
+            engine:                 Engine::standalone(payload).await?,
+            wait_for_cancel_signal: false,
+        })
+    }
+}
+
+/// What the REPL front end asks the evaluator to do
+#[derive(Debug)]
+pub enum ReplRequest {
+    /// Evaluate a complete piece of script in the session
+    Eval(String),
+    /// Evaluate an expression and only report its `typeof`
+    TypeOf(String),
+    /// Throw away the current context and start with a fresh one
+    Clear,
+}
+
+/// What the evaluator answers to a [`ReplRequest`]
+#[derive(Debug)]
+pub enum ReplReply {
+    Value(String),
+    Error(String),
+    /// The evaluation was aborted through [`ReplMessage::interrupt`]
+    Interrupted,
+    Done,
+}
+
+pub struct ReplMessage {
+    pub request:   ReplRequest,
+    /// Cancel to interrupt this evaluation only, even if it is stuck in a loop
+    pub interrupt: CancellationToken,
+    pub reply:     oneshot::Sender<ReplReply>,
+}
+
+/// The result of a REPL evaluation, as `String()` would print it
+struct ReplValue(String);
+
+impl<'js> FromJs<'js> for ReplValue {
+    fn from_js(_: &Ctx<'js>, value: Value<'js>) -> rquickjs::Result<Self> {
+        // Symbols refuse implicit conversion to strings
+        if let Some(symbol) = value.as_symbol() {
+            let description = symbol.description()?;
+            let description = description.as_string().map(|x| x.to_string()).transpose()?;
+            return Ok(Self(format!("Symbol({})", description.unwrap_or_default())));
+        }
+        let Coerced(text) = value.get::<Coerced<String>>()?;
+        Ok(Self(text))
+    }
+}
+
+/// The `typeof` of a REPL evaluation, with the constructor name for
+/// objects, e.g. `object (Map)`
+struct ReplType(String);
+
+impl<'js> FromJs<'js> for ReplType {
+    fn from_js(_: &Ctx<'js>, value: Value<'js>) -> rquickjs::Result<Self> {
+        let type_of = match value.type_of() {
+            Type::Uninitialized | Type::Undefined => "undefined",
+            Type::Bool => "boolean",
+            Type::Int | Type::Float => "number",
+            Type::String => "string",
+            Type::Symbol => "symbol",
+            Type::BigInt => "bigint",
+            Type::Function | Type::Constructor => "function",
+            _ => "object",
+        };
+        let constructor = value
+            .as_object()
+            .filter(|_| type_of == "object")
+            .and_then(|x| x.get::<_, Object>("constructor").ok())
+            .and_then(|x| x.get::<_, String>("name").ok())
+            .filter(|x| !x.is_empty());
+
+        Ok(Self(match constructor {
+            Some(constructor) => format!("{type_of} ({constructor})"),
+            None => type_of.to_string(),
+        }))
+    }
+}
+
+/// A value formatted the way `console.log` would print it
+struct Printed(String);
+
+impl<'js> FromJs<'js> for Printed {
+    fn from_js(_: &Ctx<'js>, value: Value<'js>) -> rquickjs::Result<Self> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "stdlib-console")] {
+                let mut text = String::new();
+                den_stdlib_console::Formatter::builder()
+                    .max_depth(3)
+                    .build()
+                    .format(&mut text, value)?;
+                Ok(Self(text))
+            } else {
+                let Coerced(text) = value.get::<Coerced<String>>()?;
+                Ok(Self(text))
+            }
         }
     }
 }
 
 impl App {
-    pub fn start_repl_session(&mut self) {
-        let (repl_tx, mut repl_rx) = mpsc::unbounded_channel::<String>();
+    /// Spawn a REPL evaluator on the engine and return the channel to feed it
+    pub fn spawn_repl_evaluator(engine: &Engine) -> mpsc::UnboundedSender<ReplMessage> {
+        let (repl_tx, mut repl_rx) = mpsc::unbounded_channel::<ReplMessage>();
 
-        // This task accepts all strings input from a channel, so that it can be from
-        // any data source Right now this is for stdin, but we can obviously
-        // extend this to run from a buffer for automated E2EE test
+        // This task accepts all requests from a channel, so that it can be from
+        // any data source, be it stdin, a transcript script or a remote client
         tokio::spawn({
-            let engine = self.engine.clone();
+            let mut engine = engine.clone();
             let token = engine.stop_token.child_token();
             async move {
                 let subtoken = token.child_token();
@@ -35,50 +151,96 @@ impl App {
                     .run_until_cancelled(async move {
                         // Each received data has to be one complete instance of script for eval,
                         // i.e. full buffer.
-                        // Don't handle things like missing bracket balance here
-                        while let Some(source) = repl_rx.recv().await {
-                            let fut = {
-                                let engine = engine.clone();
-                                let source = source.clone();
-                                let subtoken = subtoken.child_token();
-                                async move {
-                                    match subtoken.run_until_cancelled(engine.eval::<Coerced<String>>(&source)).await {
-                                        Some(Ok(Coerced(res))) => {
-                                            println!("{res}")
-                                        }
-                                        // Handles runtime exception during execution
-                                        Some(Err(EngineError::Rquickjs(_))) => {
-                                            async_with!(engine.context => |ctx| {
-                                                let e = ctx.catch();
-                                                if let Some(e) = e.as_exception() {
-                                                    eprintln!("{e}")
-                                                } else if let Ok(Coerced(e)) = e.get::<Coerced<String>>() {
-                                                    eprintln!("{e}")
-                                                } else {
-                                                    eprintln!("unknown error")
-                                                }
-                                            })
-                                            .await;
-                                        }
-                                        // This can be something else such as SWC error
-                                        #[allow(unreachable_patterns)]
-                                        Some(Err(e)) => {
-                                            eprintln!("{e}")
-                                        },
-                                        None => {}
+                        // Don't handle things like missing bracket balance here.
+                        // Requests are handled one at a time so that `_` and `_error` always
+                        // refer to the previous input
+                        while let Some(ReplMessage {
+                            request,
+                            interrupt,
+                            reply,
+                        }) = repl_rx.recv().await
+                        {
+                            if let ReplRequest::Clear = request {
+                                engine.reset_context().await;
+                                let _ = reply.send(ReplReply::Done);
+                                continue;
+                            }
+
+                            // A script stuck in a loop never yields back to us, so the interrupt
+                            // handler has to observe the token as well
+                            engine.set_interrupt_token(interrupt.clone());
+                            let res =
+                                match request {
+                                    ReplRequest::Eval(source) => {
+                                        subtoken
+                                            .run_until_cancelled(interrupt.run_until_cancelled(
+                                                // The value is remembered as `_` so the next input
+                                                // can refer to it
+                                                engine.eval_with(&source, |ctx, value| {
+                                                    ctx.globals().set("_", value.clone())?;
+                                                    ReplValue::from_js(ctx, value)
+                                                }),
+                                            ))
+                                            .await
+                                            .flatten()
+                                            .map(|res| res.map(|ReplValue(x)| x))
+                                    }
+                                    ReplRequest::TypeOf(source) => {
+                                        subtoken
+                                            .run_until_cancelled(interrupt.run_until_cancelled(
+                                                engine.eval::<ReplType>(&source),
+                                            ))
+                                            .await
+                                            .flatten()
+                                            .map(|res| res.map(|ReplType(x)| x))
                                     }
-                                }
-                            };
-                            tokio::spawn(fut);
-                            yield_now().await;
+                                    ReplRequest::Clear => unreachable!(),
+                                };
+                            engine.set_interrupt_token(CancellationToken::new());
+
+                            if interrupt.is_cancelled() {
+                                // Drop the "interrupted" exception so it does not leak into
+                                // `_error`
+                                async_with!(engine.context => |ctx| { ctx.catch(); }).await;
+                                let _ = reply.send(ReplReply::Interrupted);
+                                continue;
+                            }
+
+                            let _ = reply.send(match res {
+                                Some(Ok(res)) => ReplReply::Value(res),
+                                // Handles runtime exception during execution
+                                Some(Err(EngineError::Rquickjs(_))) => ReplReply::Error(
+                                    async_with!(engine.context => |ctx| {
+                                        let e = ctx.catch();
+                                        let _ = ctx.globals().set("_error", e.clone());
+                                        if let Some(e) = e.as_exception() {
+                                            e.to_string()
+                                        } else if let Ok(Coerced(e)) = e.get::<Coerced<String>>() {
+                                            e
+                                        } else {
+                                            "unknown error".to_string()
+                                        }
+                                    })
+                                    .await,
+                                ),
+                                // This can be something else such as SWC error
+                                #[allow(unreachable_patterns)]
+                                Some(Err(e)) => ReplReply::Error(e.to_string()),
+                                None => ReplReply::Done,
+                            });
                         }
                     })
                     .await;
             }
         });
 
+        repl_tx
+    }
+
+    pub fn start_repl_session(&mut self) {
+        let repl_tx = Self::spawn_repl_evaluator(&self.engine);
+
         // The REPL runs on a different task, and send data to our REPL eval handler
-        // above
         tokio::spawn({
             let stop_token = self.engine.stop_token.clone();
             repl::run_repl(repl_tx).then(move |_| async move { stop_token.cancel() })
@@ -87,6 +249,93 @@ impl App {
         self.wait_for_cancel_signal = true;
     }
 
+    /// Feed every input of a script through a REPL session and record what
+    /// each of them outputs
+    pub async fn run_repl_script(&mut self, script: &str) -> Transcript {
+        let mut session = ReplSession::new(Self::spawn_repl_evaluator(&self.engine), false);
+        let mut transcript = Transcript::default();
+
+        for input in transcript::split_inputs(script) {
+            let mut output = vec![];
+            let proceed = session.handle(&input, &mut output).await;
+            transcript.push(input, output);
+            if !proceed {
+                break;
+            }
+        }
+
+        transcript
+    }
+
+    /// Run a script file as the main module and report any uncaught error,
+    /// returns whether it succeeded
+    pub async fn run_file(&self, path: PathBuf) -> bool {
+        self.run_main(self.engine.run_file::<()>(path)).await
+    }
+
+    /// Run a module from its source, e.g. read from stdin, in the same way as
+    /// [`App::run_file`]
+    pub async fn run_module_source(&self, name: &str, src: &str) -> bool {
+        self.run_main(self.engine.run_module_source(name, src))
+            .await
+    }
+
+    /// Evaluate a piece of code in the same way as [`App::run_file`], and
+    /// optionally print its result
+    pub async fn run_eval(&self, code: &str, print: bool) -> bool {
+        self.run_main(async {
+            if print {
+                let Printed(x) = self.engine.eval::<Printed>(code).await?;
+                println!("{x}");
+            } else {
+                self.engine.eval::<()>(code).await?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Report the uncaught error of a main script if any, returns whether it
+    /// succeeded. Stopping the engine counts as success
+    async fn run_main(&self, main: impl Future<Output = Result<(), EngineError>>) -> bool {
+        match self
+            .engine
+            .stop_token
+            .child_token()
+            .run_until_cancelled(main)
+            .await
+        {
+            // The script was interrupted by the stop, with nothing to report
+            Some(Err(_)) if self.engine.stop_token.is_cancelled() => {
+                async_with!(self.engine.context => |ctx| { ctx.catch(); }).await;
+                true
+            }
+            Some(Err(EngineError::Rquickjs(_))) => {
+                let e = async_with!(self.engine.context => |ctx| {
+                    let e = ctx.catch();
+                    if let Some(e) = e.as_exception() {
+                        e.to_string()
+                    } else if let Ok(Coerced(e)) = e.get::<Coerced<String>>() {
+                        e
+                    } else {
+                        "unknown error".to_string()
+                    }
+                })
+                .await;
+                #[cfg(feature = "transpile")]
+                let e = self.engine.map_error_locations(&e);
+                eprintln!("{e}");
+                false
+            }
+            #[allow(unreachable_patterns)]
+            Some(Err(e)) => {
+                eprintln!("{e}");
+                false
+            }
+            _ => true,
+        }
+    }
+
     pub async fn run_until_end(&mut self) {
         // This part does 3 things
         // 1. Handle if a stop signal has been received (in the form of a cancellation)
@@ -120,3 +369,59 @@ impl App {
         }));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    async fn send(
+        repl: &mpsc::UnboundedSender<ReplMessage>,
+        source: &str,
+        interrupt: CancellationToken,
+    ) -> ReplReply {
+        let (reply, rx) = oneshot::channel();
+        repl.send(ReplMessage {
+            request: ReplRequest::Eval(source.to_string()),
+            interrupt,
+            reply,
+        })
+        .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), rx)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn interrupts_endless_loops() {
+        let engine = Engine::new().await;
+        let repl = App::spawn_repl_evaluator(&engine);
+        send(&repl, "const kept = 1", CancellationToken::new()).await;
+
+        let interrupt = CancellationToken::new();
+        // The loop holds a worker of the runtime, so the interrupt comes from
+        // another thread
+        std::thread::spawn({
+            let interrupt = interrupt.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(100));
+                interrupt.cancel();
+            }
+        });
+        let reply = send(&repl, "while (true) {}", interrupt).await;
+        assert!(matches!(reply, ReplReply::Interrupted), "{reply:?}");
+
+        let reply = send(
+            &repl,
+            "`${kept + 1} ${typeof _error}`",
+            CancellationToken::new(),
+        )
+        .await;
+        assert!(
+            matches!(&reply, ReplReply::Value(x) if x == "2 undefined"),
+            "{reply:?}"
+        );
+    }
+}
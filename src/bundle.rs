@@ -0,0 +1,62 @@
+use std::{fs, path::PathBuf};
+
+use den_core::{
+    bundle::{BundleOptions, MinifyOptions},
+    config::Config,
+};
+
+use crate::{
+    cli::{BundleArgs, MinifyArgs},
+    info::format_size,
+};
+
+impl MinifyArgs {
+    pub fn options(&self) -> Option<MinifyOptions> {
+        self.minify.then_some(MinifyOptions {
+            keep_fn_names:    self.keep_fnames,
+            keep_class_names: self.keep_classnames,
+        })
+    }
+}
+
+/// Write the bundle of `args.entry` to the output, or stdout without one
+pub async fn bundle(config: &Config, args: BundleArgs) -> color_eyre::eyre::Result<()> {
+    let options = BundleOptions {
+        minify:     args.minify.options(),
+        source_map: args.source_map,
+    };
+    let bundle = den_core::bundle::bundle(config, &args.entry, &options).await?;
+    let mut code = bundle.code;
+    if !code.ends_with('\n') {
+        code.push('\n');
+    }
+
+    let Some(output) = args.output else {
+        print!("{code}");
+        return Ok(());
+    };
+    if let Some(dir) = output.parent().filter(|x| !x.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    if let Some(source_map) = bundle.source_map {
+        let mut path = output.clone().into_os_string();
+        path.push(".map");
+        let path = PathBuf::from(path);
+        source_map.to_writer(fs::File::create(&path)?)?;
+        if let Some(name) = path.file_name() {
+            code.push_str(&format!(
+                "//# sourceMappingURL={}\n",
+                name.to_string_lossy()
+            ));
+        }
+    }
+    fs::write(&output, &code)?;
+
+    eprintln!(
+        "Bundled {} modules into {} ({})",
+        bundle.modules,
+        output.display(),
+        format_size(code.len())
+    );
+    Ok(())
+}
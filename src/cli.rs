@@ -0,0 +1,269 @@
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use crate::{inspect::InspectAddr, logging::LogFormat};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global:  GlobalArgs,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Without a subcommand, a file or `--eval` is run as with `den run`,
+    /// otherwise a REPL is started as with `den repl`
+    #[command(flatten)]
+    pub run:     RunArgs,
+    #[command(flatten)]
+    pub repl:    ReplArgs,
+}
+
+/// Flags shared by every subcommand
+#[derive(Args, Debug, Clone)]
+pub struct GlobalArgs {
+    /// Use this config file instead of looking for a den.json in the working
+    /// directory and its ancestors
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "no_config")]
+    pub config:        Option<PathBuf>,
+    /// Do not load any den.json
+    #[arg(long, global = true)]
+    pub no_config:     bool,
+    /// Use this import map instead of the one from the config
+    #[arg(long, global = true, value_name = "FILE")]
+    pub import_map:    Option<PathBuf>,
+    /// Load environment variables from a dotenv file, `.env` by default.
+    /// Variables that are already set are kept
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".env"
+    )]
+    pub env_file:      Vec<PathBuf>,
+    /// How logs and `console` output are printed
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub log_format:    LogFormat,
+    /// Write logs to this file instead of the terminal, `console` output of
+    /// the plain format still goes to stdout and stderr
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log_file:      Option<PathBuf>,
+    /// Rotate the log file once it grows past this many megabytes
+    #[arg(
+        long,
+        global = true,
+        value_name = "MB",
+        default_value_t = 10,
+        requires = "log_file"
+    )]
+    pub log_file_size: u64,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run a script file, or a module from stdin with `-`
+    Run(RunArgs),
+    /// Start an interactive REPL
+    Repl(ReplCommand),
+    /// Evaluate a piece of code, transpiled like a script file
+    Eval(EvalArgs),
+    /// Run test files, each in a fresh runtime
+    Test(TestArgs),
+    /// Check scripts with the built-in lint rules
+    Lint(LintArgs),
+    /// Show information about the runtime and the project, or the modules a
+    /// script imports
+    Info(InfoArgs),
+    /// Bundle a script and the modules it imports into a single ES module
+    Bundle(BundleArgs),
+    /// Compile a script and the modules it imports into a self-contained
+    /// executable
+    Compile(CompileArgs),
+    /// Manage the cache directory
+    Cache(CacheArgs),
+    /// Run a task defined in den.json, or list them without a name
+    Task(TaskArgs),
+    /// Print the TypeScript declarations of the built-in modules
+    Types,
+    /// Run as a Jupyter kernel, or install the kernelspec for it
+    Jupyter(JupyterArgs),
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct RunArgs {
+    /// The script to run, or `-` to read a module from stdin
+    #[arg()]
+    pub file:         Option<PathBuf>,
+    /// Arguments for the script, available as `Den.args`
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, requires = "file")]
+    pub args:         Vec<String>,
+    /// Evaluate a piece of code instead of a file, same as `den eval`
+    #[arg(short, long, value_name = "CODE", conflicts_with = "file")]
+    pub eval:         Option<String>,
+    /// Print the result of `--eval`
+    #[arg(short, long, requires = "eval")]
+    pub print:        bool,
+    /// Restart the file whenever one of the modules it loaded changes
+    #[arg(long, requires = "file")]
+    pub watch:        bool,
+    /// Also restart when a file matching this glob changes
+    #[arg(long, value_name = "GLOB", requires = "watch")]
+    pub watch_path:   Vec<String>,
+    /// Accept remote REPL sessions on `host:port` or a Unix socket path, a
+    /// Unix socket in the temporary directory by default. Clients have to
+    /// send the token from `DEN_INSPECT_TOKEN`, or the one printed at startup,
+    /// as their first line
+    #[arg(
+        long,
+        value_name = "ADDR",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    pub inspect_repl: Option<InspectAddr>,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ReplArgs {
+    /// Feed REPL inputs from a script file, or `-` for stdin, and print the
+    /// transcript instead of starting an interactive session
+    #[arg(long, value_name = "FILE")]
+    pub repl_script: Option<PathBuf>,
+    /// Compare the transcript of `--repl-script` against an expected one and
+    /// fail on any mismatch
+    #[arg(long, value_name = "FILE", requires = "repl_script")]
+    pub repl_expect: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReplCommand {
+    #[command(flatten)]
+    pub args:         ReplArgs,
+    /// Also accept remote REPL sessions, see `den run --inspect-repl`
+    #[arg(
+        long,
+        value_name = "ADDR",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    pub inspect_repl: Option<InspectAddr>,
+}
+
+#[derive(Args, Debug)]
+pub struct EvalArgs {
+    pub code:  String,
+    /// Print the result using the console formatter
+    #[arg(short, long)]
+    pub print: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct TestArgs {
+    /// Test files or directories to look for them in, the files matching
+    /// `test.include` of the config by default
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct LintArgs {
+    /// Files or directories to look for them in, the files matching
+    /// `lint.include` of the config by default
+    pub paths: Vec<PathBuf>,
+    /// Fix the problems that can be fixed safely, in place
+    #[arg(long)]
+    pub fix:   bool,
+    /// Print JSON instead of text
+    #[arg(long)]
+    pub json:  bool,
+}
+
+#[derive(Args, Debug)]
+pub struct InfoArgs {
+    /// Print the module graph of this script instead
+    pub entry: Option<PathBuf>,
+    /// Print JSON instead of text
+    #[arg(long)]
+    pub json:  bool,
+}
+
+#[derive(Args, Debug)]
+pub struct BundleArgs {
+    pub entry:      PathBuf,
+    /// Where to write the bundle instead of stdout
+    #[arg(short, long)]
+    pub output:     Option<PathBuf>,
+    #[command(flatten)]
+    pub minify:     MinifyArgs,
+    /// Write a source map next to the output, as `<output>.map`
+    #[arg(long, requires = "output")]
+    pub source_map: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct MinifyArgs {
+    /// Compress the output, drop dead code and mangle names
+    #[arg(long)]
+    pub minify:          bool,
+    /// Keep the names of functions when minifying
+    #[arg(long, requires = "minify")]
+    pub keep_fnames:     bool,
+    /// Keep the names of classes when minifying
+    #[arg(long, requires = "minify")]
+    pub keep_classnames: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct CompileArgs {
+    pub entry:   PathBuf,
+    /// Where to write the executable, named after the entry by default
+    #[arg(short, long)]
+    pub output:  Option<PathBuf>,
+    /// Also embed this module and the ones it imports, for imports with
+    /// computed specifiers
+    #[arg(long, value_name = "FILE")]
+    pub include: Vec<PathBuf>,
+    #[command(flatten)]
+    pub minify:  MinifyArgs,
+    /// Arguments always given to the program, before the ones it is run with
+    #[arg(last = true)]
+    pub args:    Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct TaskArgs {
+    pub name: Option<String>,
+    /// Appended to the command line of the task
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, requires = "name")]
+    pub args: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub action: CacheAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheAction {
+    /// Print the location of the cache directory
+    Dir,
+    /// Remove everything from the cache directory
+    Clean,
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("mode").required(true).args(["install", "kernel"])))]
+pub struct JupyterArgs {
+    /// Install the kernelspec for the current user
+    #[arg(long)]
+    pub install: bool,
+    /// Start the kernel, this is what Jupyter runs
+    #[arg(long, requires = "conn")]
+    pub kernel:  bool,
+    /// The connection file given by Jupyter
+    #[arg(long, value_name = "FILE")]
+    pub conn:    Option<PathBuf>,
+}
@@ -0,0 +1,61 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use den_core::{config::Config, standalone::Payload};
+
+use crate::{app::App, cli::CompileArgs, info::format_size, logging};
+
+/// Write a copy of this executable that runs `args.entry` and the modules it
+/// imports without needing them on disk
+pub async fn compile(config: &Config, args: CompileArgs) -> color_eyre::eyre::Result<()> {
+    let payload = Payload::compile(
+        config,
+        &args.entry,
+        &args.include,
+        args.minify.options(),
+        args.args,
+    )
+    .await?;
+
+    let output = args.output.unwrap_or_else(|| {
+        let name = args.entry.file_stem().unwrap_or(args.entry.as_os_str());
+        PathBuf::from(name)
+    });
+    if let Some(dir) = output.parent().filter(|x| !x.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    payload.write(&std::env::current_exe()?, &output)?;
+
+    eprintln!(
+        "Compiled {} modules into {} ({})",
+        payload.modules.len(),
+        output.display(),
+        format_size(fs::metadata(&output)?.len() as usize)
+    );
+    Ok(())
+}
+
+/// Run the program of a compiled executable, with the arguments it was
+/// compiled with followed by the ones it was given
+pub async fn run_standalone(payload: Payload) -> color_eyre::eyre::Result<ExitCode> {
+    logging::init(Default::default(), None, 0)?;
+
+    let entry = PathBuf::from(&payload.entry);
+    let args = payload
+        .args
+        .iter()
+        .cloned()
+        .chain(std::env::args().skip(1))
+        .collect();
+
+    let mut app = App::standalone(payload).await?;
+    app.engine.set_args(args).await?;
+    app.hook_ctrlc_handler();
+    let succeeded = app.run_file(entry).await;
+    app.run_until_end().await;
+
+    Ok(if succeeded {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
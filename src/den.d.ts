@@ -0,0 +1,234 @@
+// Type declarations of the den built-in modules and globals, as printed by
+// `den types`
+
+declare module "den:core" {
+  export function atob(value: string): string;
+  export function btoa(value: string): string;
+  export function gc(): void;
+}
+
+declare module "den:crypto" {
+  export function getRandomValues<T extends ArrayBufferView>(array: T): T;
+  export function randomUUID(): string;
+}
+
+declare module "den:fs" {
+  export function canonicalize(path: string): Promise<string | undefined>;
+  export function copy(from: string, to: string): Promise<void>;
+  export function createDir(path: string): Promise<void>;
+  export function createDirAll(path: string): Promise<void>;
+  export function hardLink(src: string, dst: string): Promise<void>;
+  export function read(path: string): Promise<number[]>;
+  export function readToString(path: string): Promise<string>;
+  export function removeDir(path: string): Promise<void>;
+  export function removeDirAll(path: string): Promise<void>;
+  export function removeFile(path: string): Promise<void>;
+  export function rename(from: string, to: string): Promise<void>;
+  export function write(path: string, contents: ArrayLike<number>): Promise<void>;
+}
+
+declare module "den:timer" {
+  export class CancellationToken {
+    cancel(): void;
+  }
+  export function setInterval(func: () => void, delay?: number): CancellationToken;
+  export function clearInterval(token: CancellationToken): void;
+  export function setTimeout(func: () => void, delay?: number): CancellationToken;
+  export function clearTimeout(token: CancellationToken): void;
+}
+
+declare module "den:jsx" {
+  export const Fragment: unique symbol;
+  export type Node =
+    | JSX.Element
+    | string
+    | number
+    | bigint
+    | boolean
+    | null
+    | undefined
+    | Promise<Node>
+    | Iterable<Node>;
+  export type Component<P = {}> = (props: P) => Node;
+  export function jsx(type: unknown, props: object, key?: unknown): JSX.Element;
+  export function jsxs(type: unknown, props: object, key?: unknown): JSX.Element;
+  export function jsxDEV(
+    type: unknown,
+    props: object,
+    key?: unknown,
+    isStatic?: boolean,
+    source?: { fileName: string; lineNumber: number; columnNumber: number },
+  ): JSX.Element;
+  export function createElement(type: unknown, props?: object | null, ...children: Node[]): JSX.Element;
+  export const h: typeof createElement;
+  export function isValidElement(value: unknown): value is JSX.Element;
+  export function renderToString(node: Node): Promise<string>;
+  export function renderToStream(node: Node): AsyncGenerator<string, void>;
+}
+
+declare module "den:jsx/jsx-runtime" {
+  export { Fragment, jsx, jsxs, jsxDEV, renderToStream, renderToString } from "den:jsx";
+}
+
+declare module "den:jsx/jsx-dev-runtime" {
+  export { Fragment, jsx, jsxs, jsxDEV, renderToStream, renderToString } from "den:jsx";
+}
+
+declare namespace JSX {
+  interface Element {
+    type: unknown;
+    props: Record<string, unknown>;
+    key: unknown;
+  }
+  interface IntrinsicElements {
+    [name: string]: Record<string, unknown>;
+  }
+  interface ElementChildrenAttribute {
+    children: {};
+  }
+}
+
+declare module "den:networking" {
+  export class IpAddr {
+    readonly is_unspecified: boolean;
+    readonly is_loopback: boolean;
+    readonly is_multicast: boolean;
+    readonly is_ipv4: boolean;
+    readonly is_ipv6: boolean;
+    toString(): string;
+  }
+  export class SocketAddr {
+    port: number;
+    ip: IpAddr;
+    readonly is_ipv4: boolean;
+    readonly is_ipv6: boolean;
+    toString(): string;
+  }
+  export class TcpStream {
+    static connect(addr: string): Promise<TcpStream>;
+    readonly local_addr: SocketAddr;
+    read_to_string(): Promise<string>;
+    read_to_end(): Promise<number[]>;
+    read(bytes: number): Promise<Uint8Array>;
+    write_all(bytes: Uint8Array): Promise<void>;
+    flush(): Promise<void>;
+    shutdown(): Promise<void>;
+    [Symbol.asyncDispose](): Promise<void>;
+  }
+  export class TcpListener {
+    static listen(addr: string): Promise<TcpListener>;
+    readonly local_addr: SocketAddr;
+    accept(): Promise<[TcpStream, SocketAddr]>;
+    [Symbol.asyncDispose](): Promise<void>;
+  }
+}
+
+declare module "den:sqlite" {
+  type Params = unknown[] | Record<string, unknown>;
+
+  export class Connection {
+    static open_in_memory(): Connection;
+    static open(path: string): Connection;
+    execute(sql: string, params?: Params): number;
+    query_rows(sql: string, params?: Params): Record<string, unknown>[] | undefined;
+    close(): void;
+    [Symbol.dispose](): void;
+  }
+}
+
+declare module "den:text" {
+  export class TextEncoder {
+    readonly encoding: string;
+    encode(input: string): Uint8Array;
+    encodeInto(input: string, dest: Uint8Array): { read: number; written: number };
+  }
+  export class TextDecoder {
+    constructor(label?: string, options?: { fatal?: boolean; ignoreBOM?: boolean });
+    readonly encoding: string;
+    readonly fatal: boolean;
+    readonly ignoreBOM: boolean;
+    decode(input?: ArrayBufferView | ArrayBuffer): string;
+  }
+}
+
+declare module "den:transpiler" {
+  export interface Diagnostic {
+    file: string;
+    start: number;
+    end: number;
+    line: number;
+    column: number;
+    severity: "error" | "warning";
+    message: string;
+    notes: string[];
+  }
+
+  export interface ParseOptions {
+    syntax?: "js" | "jsx" | "ts" | "tsx";
+    module?: boolean;
+    filename?: string;
+  }
+
+  export interface TranspileOptions extends ParseOptions {
+    sourceMap?: boolean;
+    jsx?: "react" | "react-jsx" | "react-jsxdev";
+    jsxFactory?: string;
+    jsxFragmentFactory?: string;
+    jsxImportSource?: string;
+    target?: string;
+    minify?: boolean | { keepFnames?: boolean; keepClassnames?: boolean };
+  }
+
+  export function transpile(
+    source: string,
+    options?: TranspileOptions,
+  ): { code: string | null; map: string | null; diagnostics: Diagnostic[] };
+  export function parse(source: string, options?: ParseOptions): any;
+}
+
+declare module "den:console" {}
+
+declare var console: {
+  debug(...data: unknown[]): void;
+  log(...data: unknown[]): void;
+  warn(...data: unknown[]): void;
+  error(...data: unknown[]): void;
+};
+
+declare function atob(value: string): string;
+declare function btoa(value: string): string;
+declare function gc(): void;
+
+interface SymbolConstructor {
+  readonly dispose: unique symbol;
+  readonly asyncDispose: unique symbol;
+}
+
+declare var TextEncoder: typeof import("den:text").TextEncoder;
+declare var TextDecoder: typeof import("den:text").TextDecoder;
+
+declare var Den: {
+  /** The arguments given to the script */
+  args: string[];
+  /**
+   * Transpile `code` with the compiler options of the project, as a module
+   * written in TSX unless told otherwise. The source map is only made when
+   * asked for
+   */
+  transpile(
+    code: string,
+    options?: {
+      syntax?: "js" | "jsx" | "ts" | "tsx";
+      module?: boolean;
+      /** The name the source map refers to the code by */
+      filename?: string;
+      sourceMap?: boolean;
+      /** Compress, drop dead code and mangle names */
+      minify?: boolean | { keepFnames?: boolean; keepClassnames?: boolean };
+    },
+  ): { code: string; map: string | null };
+  /** Only available when running as a Jupyter kernel */
+  jupyter?: {
+    display(mime: string, data: unknown): void;
+  };
+};
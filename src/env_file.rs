@@ -0,0 +1,252 @@
+use std::{collections::BTreeMap, io, path::PathBuf};
+
+use derive_more::{Display, Error};
+
+#[derive(Display, Error, Debug)]
+pub enum EnvFileError {
+    #[display("cannot read {}: {_1}", _0.display())]
+    Io(PathBuf, io::Error),
+    #[display("{}:{_1}: {_2}", _0.display())]
+    Parse(PathBuf, usize, #[error(not(source))] &'static str),
+}
+
+/// Parse the dotenv syntax, `lookup` resolves `${VAR}` references to
+/// variables that are not defined earlier in the same source
+fn parse(
+    source: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<(String, String)>, (usize, &'static str)> {
+    let mut vars: Vec<(String, String)> = vec![];
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    let resolve = |vars: &[(String, String)], name: &str| {
+        vars.iter()
+            .rev()
+            .find(|(x, _)| x == name)
+            .map(|(_, x)| x.clone())
+            .or_else(|| lookup(name))
+    };
+
+    loop {
+        // Skip blank lines and comments
+        while let Some(&c) = chars.peek() {
+            match c {
+                '\n' => line += 1,
+                ' ' | '\t' | '\r' => {}
+                '#' => {
+                    while chars.next_if(|x| *x != '\n').is_some() {}
+                    continue;
+                }
+                _ => break,
+            }
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|x| x.is_ascii_alphanumeric() || "_.-".contains(*x)) {
+            key.push(c);
+        }
+        while chars.next_if(|x| *x == ' ' || *x == '\t').is_some() {}
+        if key == "export" && chars.peek() != Some(&'=') {
+            key.clear();
+            while let Some(c) = chars.next_if(|x| x.is_ascii_alphanumeric() || "_.-".contains(*x)) {
+                key.push(c);
+            }
+            while chars.next_if(|x| *x == ' ' || *x == '\t').is_some() {}
+        }
+        if key.is_empty() {
+            return Err((line, "expected a variable name"));
+        }
+        if chars.next() != Some('=') {
+            return Err((line, "expected `=` after the variable name"));
+        }
+        while chars.next_if(|x| *x == ' ' || *x == '\t').is_some() {}
+
+        let mut value = String::new();
+        let expand = |chars: &mut std::iter::Peekable<std::str::Chars>, value: &mut String| {
+            // `$` has already been consumed
+            let braced = chars.next_if_eq(&'{').is_some();
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|x| x.is_ascii_alphanumeric() || *x == '_') {
+                name.push(c);
+            }
+            let mut default = None;
+            if braced {
+                if chars.next_if_eq(&':').is_some() && chars.next_if_eq(&'-').is_some() {
+                    let mut text = String::new();
+                    while let Some(c) = chars.next_if(|x| *x != '}' && *x != '\n') {
+                        text.push(c);
+                    }
+                    default = Some(text);
+                }
+                if chars.next_if_eq(&'}').is_none() {
+                    return Err("unterminated `${`");
+                }
+            }
+            if name.is_empty() {
+                value.push('$');
+                if braced {
+                    value.push_str("{}");
+                }
+                return Ok(());
+            }
+            match resolve(&vars, &name).filter(|x| !x.is_empty()) {
+                Some(x) => value.push_str(&x),
+                None => value.push_str(default.as_deref().unwrap_or_default()),
+            }
+            Ok(())
+        };
+
+        match chars.peek() {
+            Some(&quote @ ('\'' | '`')) => {
+                let start = line;
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => return Err((start, "unterminated quote")),
+                    }
+                }
+            }
+            Some('"') => {
+                let start = line;
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            match chars.next() {
+                                Some('n') => value.push('\n'),
+                                Some('r') => value.push('\r'),
+                                Some('t') => value.push('\t'),
+                                Some(c @ ('"' | '\\' | '$')) => value.push(c),
+                                Some(c) => {
+                                    value.push('\\');
+                                    value.push(c);
+                                }
+                                None => return Err((start, "unterminated quote")),
+                            }
+                        }
+                        Some('$') => expand(&mut chars, &mut value).map_err(|e| (line, e))?,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => return Err((start, "unterminated quote")),
+                    }
+                }
+            }
+            _ => {
+                while let Some(c) = chars.next_if(|x| *x != '\n') {
+                    match c {
+                        // A comment needs whitespace before it, so `a#b` is a value
+                        '#' if value.is_empty() || value.ends_with([' ', '\t']) => {
+                            while chars.next_if(|x| *x != '\n').is_some() {}
+                        }
+                        '$' => expand(&mut chars, &mut value).map_err(|e| (line, e))?,
+                        c => value.push(c),
+                    }
+                }
+                value.truncate(value.trim_end().len());
+            }
+        }
+
+        // Only a comment may follow a quoted value
+        while chars
+            .next_if(|x| *x == ' ' || *x == '\t' || *x == '\r')
+            .is_some()
+        {}
+        match chars.peek() {
+            None | Some('\n') => {}
+            Some('#') => while chars.next_if(|x| *x != '\n').is_some() {},
+            Some(_) => return Err((line, "unexpected characters after the value")),
+        }
+
+        vars.push((key, value));
+    }
+
+    Ok(vars)
+}
+
+/// Read dotenv files and set their variables in the process environment.
+/// Variables that are already set take precedence, and later files take
+/// precedence over earlier ones. Has to be called before any other thread is
+/// started
+pub fn load_env_files(paths: &[PathBuf]) -> Result<(), EnvFileError> {
+    let mut vars = BTreeMap::new();
+    for path in paths {
+        let source =
+            std::fs::read_to_string(path).map_err(|e| EnvFileError::Io(path.clone(), e))?;
+        let parsed = parse(&source, |name| {
+            std::env::var(name).ok().or_else(|| vars.get(name).cloned())
+        })
+        .map_err(|(line, e)| EnvFileError::Parse(path.to_path_buf(), line, e))?;
+        vars.extend(parsed);
+    }
+
+    for (name, value) in vars {
+        if std::env::var_os(&name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    fn parse(source: &str) -> Result<Vec<(String, String)>, (usize, &'static str)> {
+        super::parse(source, |_| None)
+    }
+
+    fn pairs(x: &[(&str, &str)]) -> Vec<(String, String)> {
+        x.iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_dotenv() {
+        let source = r#"
+# comment
+A=plain value # trailing comment
+export B = 'single $A'
+C="double\n${A}\"" # comment
+D="multi
+line"
+E=${MISSING:-fallback}-$A
+F=a#b
+G=
+"#;
+        assert_eq!(
+            parse(source).unwrap(),
+            pairs(&[
+                ("A", "plain value"),
+                ("B", "single $A"),
+                ("C", "double\nplain value\""),
+                ("D", "multi\nline"),
+                ("E", "fallback-plain value"),
+                ("F", "a#b"),
+                ("G", ""),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("A='x").unwrap_err(), (1, "unterminated quote"));
+        assert_eq!(parse("\n\nA").unwrap_err().0, 3);
+        assert_eq!(parse("A=\"x\" y").unwrap_err().0, 1);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use den_core::{
+    config::{self, Config},
+    graph::{ModuleGraph, ModuleKind},
+};
+use serde_json::json;
+
+use crate::cli::InfoArgs;
+
+/// Print the runtime and project information, or the module graph of
+/// `args.entry` when given
+pub async fn info(
+    config_path: Option<&Path>,
+    config: &Config,
+    args: InfoArgs,
+) -> color_eyre::eyre::Result<()> {
+    let cache = config::cache_dir();
+    let Some(entry) = args.entry else {
+        let config_path = config_path.map(Path::to_path_buf);
+        let import_map = config.import_map.as_ref().map(|x| config.resolve_path(x));
+        if args.json {
+            let info = json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "config": config_path,
+                "importMap": import_map,
+                "cache": cache,
+            });
+            println!("{}", serde_json::to_string_pretty(&info)?);
+        } else {
+            let or_none =
+                |x: Option<PathBuf>| x.map_or("none".to_string(), |x| x.display().to_string());
+            println!("den {}", env!("CARGO_PKG_VERSION"));
+            println!("config: {}", or_none(config_path));
+            println!("import map: {}", or_none(import_map));
+            println!("cache: {}", cache.display());
+        }
+        return Ok(());
+    };
+
+    let graph = ModuleGraph::build(config, &entry).await?;
+    if args.json {
+        let mut info = serde_json::to_value(&graph)?;
+        info["size"] = graph.size().into();
+        info["cycles"] = serde_json::to_value(graph.cycles())?;
+        info["cache"] = serde_json::to_value(&cache)?;
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
+
+    println!("entry: {}", graph.root);
+    println!("cache: {}", cache.display());
+    println!(
+        "modules: {} ({})",
+        graph.modules.len(),
+        format_size(graph.size())
+    );
+    println!();
+    println!("{}", label(&graph, &graph.root));
+    print_dependencies(
+        &graph,
+        &graph.root,
+        "",
+        &mut vec![graph.root.as_str()],
+        &mut BTreeSet::new(),
+    );
+    Ok(())
+}
+
+/// A module name along with its size, or what keeps it from being part of
+/// the graph
+fn label(graph: &ModuleGraph, name: &str) -> String {
+    match graph.modules.get(name) {
+        Some(module) if module.kind == ModuleKind::Builtin => format!("{name} (den: builtin)"),
+        Some(module) => {
+            match (&module.error, module.size) {
+                (Some(error), _) => format!("{name} (error: {error})"),
+                (None, Some(size)) => format!("{name} ({})", format_size(size)),
+                (None, None) => name.to_string(),
+            }
+        }
+        None => name.to_string(),
+    }
+}
+
+fn print_dependencies<'a>(
+    graph: &'a ModuleGraph,
+    name: &'a str,
+    prefix: &str,
+    stack: &mut Vec<&'a str>,
+    expanded: &mut BTreeSet<&'a str>,
+) {
+    let Some(module) = graph.modules.get(name) else {
+        return;
+    };
+    expanded.insert(name);
+
+    for (i, dependency) in module.dependencies.iter().enumerate() {
+        let last = i + 1 == module.dependencies.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        let dynamic = if dependency.dynamic { " (dynamic)" } else { "" };
+
+        let Some(resolved) = &dependency.resolved else {
+            let error = dependency.error.as_deref().unwrap_or_default();
+            println!(
+                "{prefix}{branch}{}{dynamic} (error: {error})",
+                dependency.specifier
+            );
+            continue;
+        };
+        // Cycles and modules shown before are not expanded again
+        if stack.contains(&resolved.as_str()) {
+            println!("{prefix}{branch}{resolved}{dynamic} (cycle)");
+        } else if expanded.contains(resolved.as_str()) {
+            println!("{prefix}{branch}{resolved}{dynamic} *");
+        } else {
+            println!("{prefix}{branch}{}{dynamic}", label(graph, resolved));
+            stack.push(resolved);
+            print_dependencies(
+                graph,
+                resolved,
+                &format!("{prefix}{indent}"),
+                stack,
+                expanded,
+            );
+            stack.pop();
+        }
+    }
+}
+
+pub fn format_size(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{bytes} B"),
+        1024..1048576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
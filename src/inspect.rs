@@ -0,0 +1,315 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    net::SocketAddr,
+    str::FromStr,
+};
+#[cfg(unix)]
+use std::{
+    fs::{self, DirBuilder, Permissions},
+    os::unix::fs::{DirBuilderExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use den_core::engine::Engine;
+#[cfg(unix)] use tokio::net::UnixListener;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::mpsc,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    app::App,
+    repl::{dot_command, is_complete, ReplOutput, ReplSession},
+};
+
+/// Where the remote REPL server listens for clients
+#[derive(Debug, Clone)]
+pub enum InspectAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Default for InspectAddr {
+    fn default() -> Self {
+        #[cfg(unix)]
+        {
+            let name = format!("den-{}.sock", std::process::id());
+            Self::Unix(std::env::temp_dir().join(name))
+        }
+        #[cfg(not(unix))]
+        {
+            Self::Tcp(SocketAddr::from(([127, 0, 0, 1], 9230)))
+        }
+    }
+}
+
+impl FromStr for InspectAddr {
+    type Err = String;
+
+    /// Accepts `host:port` for TCP, and a path optionally prefixed with `unix:`
+    /// for a Unix socket. An empty string picks the default address
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(Self::Tcp(addr));
+        }
+
+        #[cfg(unix)]
+        {
+            Ok(Self::Unix(s.strip_prefix("unix:").unwrap_or(s).into()))
+        }
+        #[cfg(not(unix))]
+        {
+            Err(format!("{s} is not a valid socket address"))
+        }
+    }
+}
+
+impl Display for InspectAddr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InspectAddr::Tcp(addr) => write!(f, "{addr}"),
+            #[cfg(unix)]
+            InspectAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+pub enum InspectListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl InspectListener {
+    pub async fn bind(addr: &InspectAddr) -> io::Result<Self> {
+        match addr {
+            InspectAddr::Tcp(addr) => Ok(Self::Tcp(TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            InspectAddr::Unix(path) => Ok(Self::Unix(bind_private(path)?, path.clone())),
+        }
+    }
+
+    /// Accept clients until the engine stops, each of them gets its own REPL
+    /// session once authenticated with `token`
+    pub async fn serve(self, engine: Engine, token: String) {
+        let stop_token = engine.stop_token.child_token();
+        stop_token
+            .run_until_cancelled(async {
+                loop {
+                    let res = match &self {
+                        InspectListener::Tcp(listener) => {
+                            listener.accept().await.map(|(stream, _)| {
+                                tokio::spawn(handle_client(stream, engine.clone(), token.clone()));
+                            })
+                        }
+                        #[cfg(unix)]
+                        InspectListener::Unix(listener, _) => {
+                            listener.accept().await.map(|(stream, _)| {
+                                tokio::spawn(handle_client(stream, engine.clone(), token.clone()));
+                            })
+                        }
+                    };
+
+                    if let Err(e) = res {
+                        eprintln!("remote REPL failed to accept a client: {e}");
+                    }
+                }
+            })
+            .await;
+    }
+}
+
+/// Bind a Unix socket that only the current user can connect to. Anyone who
+/// can connect can run arbitrary code, so other users are kept out on top of
+/// the token. The socket is bound in a directory only we can enter, then
+/// linked into place once private, as it is created with the umask
+#[cfg(unix)]
+fn bind_private(path: &Path) -> io::Result<UnixListener> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let dir = path.with_file_name(format!(".{name}.{}", std::process::id()));
+    DirBuilder::new().mode(0o700).create(&dir)?;
+
+    let temp = dir.join("socket");
+    let listener = UnixListener::bind(&temp).and_then(|listener| {
+        fs::set_permissions(&temp, Permissions::from_mode(0o600))?;
+        // Unlike renaming, linking does not replace an existing file
+        fs::hard_link(&temp, path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&temp);
+    let _ = fs::remove_dir(&dir);
+    listener
+}
+
+#[cfg(unix)]
+impl Drop for InspectListener {
+    fn drop(&mut self) {
+        if let InspectListener::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Compare without bailing out on the first different byte, so the token
+/// cannot be guessed by timing the answers
+fn token_matches(input: &str, token: &str) -> bool {
+    input.len() == token.len()
+        && input
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// A line based REPL over the stream. The first line has to be the token,
+/// then inputs are accumulated until complete and evaluated just like in the
+/// terminal REPL. A broken connection interrupts what it left running
+async fn handle_client<S: AsyncRead + AsyncWrite + Send + 'static>(
+    stream: S,
+    engine: Engine,
+    token: String,
+) -> io::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    writer.write_all(b"token: ").await?;
+    match lines.next_line().await? {
+        Some(input) if token_matches(input.trim(), &token) => {}
+        _ => {
+            writer.write_all(b"authentication failed\n").await?;
+            return Ok(());
+        }
+    }
+    writer
+        .write_all(b"Welcome to den, one word less than Deno\n")
+        .await?;
+
+    // Every connection can only interrupt its own evaluations
+    let interrupt = CancellationToken::new();
+    let _interrupt = interrupt.clone().drop_guard();
+    let mut session = ReplSession::new(App::spawn_repl_evaluator(&engine), false)
+        .with_interrupt(interrupt.clone());
+
+    // Lines are read while evaluating, to notice the connection breaking. The
+    // inputs sent before closing it still run
+    let (line_tx, mut line_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            match lines.next_line().await {
+                Ok(Some(line)) => {
+                    if line_tx.send(line).is_err() {
+                        return;
+                    }
+                }
+                Ok(None) => return,
+                Err(_) => return interrupt.cancel(),
+            }
+        }
+    });
+
+    let mut buffer = vec![];
+    loop {
+        let prompt = if buffer.is_empty() { "> " } else { "... " };
+        writer.write_all(prompt.as_bytes()).await?;
+
+        let Some(line) = line_rx.recv().await else {
+            break;
+        };
+        if buffer.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+
+        buffer.push(line);
+        let input = buffer.join("\n");
+        if dot_command(&input).is_none() && !is_complete(&input) {
+            continue;
+        }
+        buffer.clear();
+
+        let mut output = vec![];
+        let proceed = session.handle(&input, &mut output).await;
+        for x in output {
+            let (ReplOutput::Stdout(x) | ReplOutput::Stderr(x)) = x;
+            writer.write_all(x.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+        }
+
+        if !proceed {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use den_core::engine::Engine;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::handle_client;
+
+    /// Talk to a remote REPL session expecting `token`, returns everything it
+    /// answered once it is done with `input`
+    async fn session(token: &str, input: &str) -> String {
+        let engine = Engine::new().await;
+        let (mut client, server) = tokio::io::duplex(4096);
+        let handle = tokio::spawn(handle_client(server, engine, token.to_string()));
+
+        client.write_all(input.as_bytes()).await.unwrap();
+        client.shutdown().await.unwrap();
+        let mut output = String::new();
+        client.read_to_string(&mut output).await.unwrap();
+        handle.await.unwrap().unwrap();
+        output
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn accepts_the_token() {
+        let output = session("secret", "secret\n1 + 1\n").await;
+        assert_eq!(
+            output,
+            "token: Welcome to den, one word less than Deno\n> 2\n> "
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rejects_other_tokens() {
+        for input in ["secreT\n1 + 1\n", "secre\n", "secrets\n", ""] {
+            let output = session("secret", input).await;
+            assert_eq!(output, "token: authentication failed\n", "{input:?}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_sockets_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        use super::{InspectAddr, InspectListener};
+
+        let dir = std::env::temp_dir().join(format!("den-inspect-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("repl.sock");
+        let addr = InspectAddr::Unix(path.clone());
+
+        let listener = InspectListener::bind(&addr).await.unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // Nothing is left behind but the socket, which is not taken over
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(InspectListener::bind(&addr).await.is_err());
+        tokio::net::UnixStream::connect(&path).await.unwrap();
+
+        drop(listener);
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
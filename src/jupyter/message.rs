@@ -0,0 +1,243 @@
+use bytes::Bytes;
+use derive_more::{Display, Error, From};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use zeromq::ZmqMessage;
+
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+const PROTOCOL_VERSION: &str = "5.3";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+    pub msg_id:   String,
+    pub session:  String,
+    pub username: String,
+    pub date:     String,
+    pub msg_type: String,
+    pub version:  String,
+}
+
+impl Header {
+    pub fn new(session: &str, msg_type: &str) -> Self {
+        Self {
+            msg_id:   uuid::Uuid::new_v4().to_string(),
+            session:  session.to_string(),
+            username: "kernel".to_string(),
+            date:     chrono::Utc::now().to_rfc3339(),
+            msg_type: msg_type.to_string(),
+            version:  PROTOCOL_VERSION.to_string(),
+        }
+    }
+}
+
+#[derive(Display, From, Error, Debug)]
+pub enum MessageError {
+    #[display("message has no <IDS|MSG> delimiter")]
+    MissingDelimiter,
+    #[display("message is missing some of its parts")]
+    MissingParts,
+    #[display("message signature does not match")]
+    BadSignature,
+    #[from]
+    Json(serde_json::Error),
+}
+
+/// Signs messages with HMAC-SHA256 as announced in the connection file. An
+/// empty key disables signing altogether
+#[derive(Clone)]
+pub struct Signer(Option<Hmac<Sha256>>);
+
+impl Signer {
+    pub fn new(key: &str) -> Self {
+        Self(
+            (!key.is_empty()).then(|| {
+                Hmac::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any size")
+            }),
+        )
+    }
+
+    fn sign(&self, parts: &[&[u8]]) -> String {
+        let Some(mac) = &self.0 else {
+            return String::new();
+        };
+        let mut mac = mac.clone();
+        for part in parts {
+            mac.update(part);
+        }
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|x| format!("{x:02x}"))
+            .collect()
+    }
+
+    fn verify(&self, signature: &[u8], parts: &[&[u8]]) -> bool {
+        let expected = self.sign(parts);
+        expected.len() == signature.len()
+            && expected
+                .bytes()
+                .zip(signature.iter())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+/// One message of the Jupyter messaging protocol, along with the routing
+/// identities it came with
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub identities:    Vec<Bytes>,
+    pub header:        Header,
+    pub parent_header: Value,
+    pub metadata:      Value,
+    pub content:       Value,
+}
+
+impl Message {
+    pub fn decode(message: ZmqMessage, signer: &Signer) -> Result<Self, MessageError> {
+        let frames = message.into_vec();
+        let delimiter = frames
+            .iter()
+            .position(|x| x.as_ref() == DELIMITER)
+            .ok_or(MessageError::MissingDelimiter)?;
+        let (identities, frames) = frames.split_at(delimiter);
+        let [_, signature, header, parent_header, metadata, content, ..] = frames else {
+            return Err(MessageError::MissingParts);
+        };
+
+        if !signer.verify(signature, &[header, parent_header, metadata, content]) {
+            return Err(MessageError::BadSignature);
+        }
+
+        Ok(Self {
+            identities:    identities.to_vec(),
+            header:        serde_json::from_slice(header)?,
+            parent_header: serde_json::from_slice(parent_header)?,
+            metadata:      serde_json::from_slice(metadata)?,
+            content:       serde_json::from_slice(content)?,
+        })
+    }
+
+    pub fn encode(&self, signer: &Signer) -> ZmqMessage {
+        let header = serde_json::to_vec(&self.header).unwrap();
+        let parent_header = serde_json::to_vec(&self.parent_header).unwrap();
+        let metadata = serde_json::to_vec(&self.metadata).unwrap();
+        let content = serde_json::to_vec(&self.content).unwrap();
+        let signature = signer.sign(&[&header, &parent_header, &metadata, &content]);
+
+        let mut frames = self.identities.clone();
+        frames.extend([
+            Bytes::from_static(DELIMITER),
+            Bytes::from(signature),
+            Bytes::from(header),
+            Bytes::from(parent_header),
+            Bytes::from(metadata),
+            Bytes::from(content),
+        ]);
+        ZmqMessage::try_from(frames).expect("frames are never empty")
+    }
+
+    /// A new message caused by this one, routed back to the same client
+    pub fn reply(&self, msg_type: &str, content: Value) -> Self {
+        Self {
+            identities: self.identities.clone(),
+            ..self.child(msg_type, content)
+        }
+    }
+
+    /// A new message caused by this one, to be broadcast on IOPub under its
+    /// message type as the topic
+    pub fn child(&self, msg_type: &str, content: Value) -> Self {
+        Self {
+            identities: vec![Bytes::from(msg_type.to_string())],
+            header: Header::new(&self.header.session, msg_type),
+            parent_header: serde_json::to_value(&self.header).unwrap_or(json!({})),
+            metadata: json!({}),
+            content,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // HMAC-SHA256 of "The quick brown fox jumps over the lazy dog" with "key"
+    const SIGNATURE: &str = "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8";
+
+    fn message() -> Message {
+        Message {
+            identities:    vec![Bytes::from_static(b"client")],
+            header:        Header::new("session", "execute_request"),
+            parent_header: json!({}),
+            metadata:      json!({}),
+            content:       json!({ "code": "1 + 1" }),
+        }
+    }
+
+    #[test]
+    fn signs_the_parts_as_one() {
+        let signer = Signer::new("key");
+        let parts: &[&[u8]] = &[b"The quick brown fox ", b"jumps over the lazy dog"];
+        assert_eq!(signer.sign(parts), SIGNATURE);
+        assert!(signer.verify(SIGNATURE.as_bytes(), parts));
+        assert!(!signer.verify(SIGNATURE.as_bytes(), &[b"The quick brown fox"]));
+        assert!(!signer.verify(&SIGNATURE.as_bytes()[1..], parts));
+    }
+
+    #[test]
+    fn empty_keys_do_not_sign() {
+        let signer = Signer::new("");
+        assert_eq!(signer.sign(&[b"anything"]), "");
+        assert!(signer.verify(b"", &[b"anything"]));
+        assert!(!signer.verify(SIGNATURE.as_bytes(), &[b"anything"]));
+    }
+
+    #[test]
+    fn frames_identities_then_signed_parts() {
+        let signer = Signer::new("key");
+        let message = message();
+        let frames = message.encode(&signer).into_vec();
+
+        assert_eq!(frames.len(), 7);
+        assert_eq!(frames[0].as_ref(), b"client");
+        assert_eq!(frames[1].as_ref(), DELIMITER);
+        let parts = frames[3..].iter().map(|x| x.as_ref()).collect::<Vec<_>>();
+        assert_eq!(frames[2].as_ref(), signer.sign(&parts).as_bytes());
+        assert_eq!(
+            serde_json::from_slice::<Value>(&frames[6]).unwrap(),
+            json!({ "code": "1 + 1" })
+        );
+
+        let decoded = Message::decode(ZmqMessage::try_from(frames).unwrap(), &signer).unwrap();
+        assert_eq!(decoded.identities, message.identities);
+        assert_eq!(decoded.header.msg_id, message.header.msg_id);
+        assert_eq!(decoded.content, message.content);
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        let signer = Signer::new("key");
+        let frames = message().encode(&signer).into_vec();
+
+        let decoded = Message::decode(message().encode(&Signer::new("other")), &signer);
+        assert!(matches!(decoded, Err(MessageError::BadSignature)));
+
+        let mut tampered = frames.clone();
+        tampered[6] = Bytes::from_static(b"{\"code\":\"2 + 2\"}");
+        let decoded = Message::decode(ZmqMessage::try_from(tampered).unwrap(), &signer);
+        assert!(matches!(decoded, Err(MessageError::BadSignature)));
+
+        let decoded = Message::decode(ZmqMessage::try_from(frames[..6].to_vec()).unwrap(), &signer);
+        assert!(matches!(decoded, Err(MessageError::MissingParts)));
+
+        let without_delimiter = frames.iter().filter(|x| x.as_ref() != DELIMITER).cloned();
+        let decoded = Message::decode(
+            ZmqMessage::try_from(without_delimiter.collect::<Vec<_>>()).unwrap(),
+            &signer,
+        );
+        assert!(matches!(decoded, Err(MessageError::MissingDelimiter)));
+    }
+}
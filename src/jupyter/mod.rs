@@ -0,0 +1,773 @@
+use std::{
+    collections::BTreeSet,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use den_core::{
+    config::Config,
+    engine::{Engine, EngineError},
+};
+use den_utils::serde_json::SerdeJsonValue;
+use derive_more::{Display, Error, From};
+use message::{Message, MessageError, Signer};
+use rquickjs::{async_with, convert::Coerced, Ctx, Function, Object, Value as JsValue};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{filter::LevelFilter, layer::Context, prelude::*, EnvFilter, Layer};
+use zeromq::{prelude::*, PubSocket, RepSocket, RouterSocket, ZmqError};
+
+use crate::{
+    app::{App, ReplMessage, ReplReply, ReplRequest},
+    logging::{event_message, is_console},
+    repl::is_complete,
+};
+
+mod message;
+
+const BANNER: &str = "Welcome to den, one word less than Deno";
+
+/// The connection file Jupyter hands over to the kernel
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnectionInfo {
+    pub transport:    String,
+    pub ip:           String,
+    pub shell_port:   u16,
+    pub iopub_port:   u16,
+    pub stdin_port:   u16,
+    pub control_port: u16,
+    pub hb_port:      u16,
+    pub key:          String,
+}
+
+impl ConnectionInfo {
+    fn endpoint(&self, port: u16) -> String {
+        match self.transport.as_str() {
+            "ipc" => format!("ipc://{}-{port}", self.ip),
+            transport => format!("{transport}://{}:{port}", self.ip),
+        }
+    }
+}
+
+#[derive(Display, From, Error, Debug)]
+pub enum KernelError {
+    #[from]
+    Io(io::Error),
+    #[from]
+    Json(serde_json::Error),
+    #[from]
+    Zmq(ZmqError),
+    #[from]
+    Message(MessageError),
+    #[from]
+    Engine(EngineError),
+}
+
+/// Everything that ends up on the IOPub socket. Outputs are attributed to the
+/// request that was last started, which keeps them ordered before its result
+/// as they all go through the same channel
+pub enum IoPub {
+    /// A request starts being handled, the kernel is busy
+    Begin(Box<Message>),
+    /// The current request is done, the kernel is idle
+    End,
+    /// A message about the current request
+    Publish(&'static str, Value),
+    Stream(&'static str, String),
+    Display(Value),
+}
+
+/// Forwards `console` output to the notebook as stream outputs
+pub struct ConsoleCapture(mpsc::UnboundedSender<IoPub>);
+
+impl<S: Subscriber> Layer<S> for ConsoleCapture {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if !is_console(event.metadata().target()) {
+            return;
+        }
+
+        let name = if *event.metadata().level() <= Level::WARN {
+            "stderr"
+        } else {
+            "stdout"
+        };
+        let _ = self
+            .0
+            .send(IoPub::Stream(name, event_message(event) + "\n"));
+    }
+}
+
+/// Where Jupyter looks for kernelspecs of the current user
+fn jupyter_data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("JUPYTER_DATA_DIR") {
+        return dir.into();
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        dirs::home_dir().unwrap_or_default().join("Library/Jupyter")
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        dirs::data_dir().unwrap_or_default().join("jupyter")
+    }
+}
+
+/// Write the kernelspec that makes Jupyter start this executable as a kernel,
+/// returns the directory it has been written to
+pub fn install() -> Result<PathBuf, KernelError> {
+    let dir = jupyter_data_dir().join("kernels").join("den");
+    std::fs::create_dir_all(&dir)?;
+
+    let exe = std::env::current_exe()?;
+    let spec = json!({
+        "argv": [exe, "jupyter", "--kernel", "--conn", "{connection_file}"],
+        "display_name": "Den",
+        "language": "typescript",
+    });
+    std::fs::write(dir.join("kernel.json"), serde_json::to_vec_pretty(&spec)?)?;
+    Ok(dir)
+}
+
+/// Run as a Jupyter kernel with the sockets described in `connection_file`,
+/// until asked to shut down
+pub async fn run_kernel(connection_file: &Path, config: &Config) -> Result<(), KernelError> {
+    let connection: ConnectionInfo =
+        serde_json::from_slice(&tokio::fs::read(connection_file).await?)?;
+
+    let (iopub_tx, iopub_rx) = mpsc::unbounded_channel();
+    tracing_subscriber::registry()
+        .with(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .with(ConsoleCapture(iopub_tx.clone()))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .with_filter(tracing_subscriber::filter::filter_fn(|x| {
+                    !is_console(x.target())
+                })),
+        )
+        .init();
+
+    let app = App::new(config).await?;
+
+    let signer = Signer::new(&connection.key);
+    let mut shell = RouterSocket::new();
+    shell
+        .bind(&connection.endpoint(connection.shell_port))
+        .await?;
+    let mut control = RouterSocket::new();
+    control
+        .bind(&connection.endpoint(connection.control_port))
+        .await?;
+    let mut iopub = PubSocket::new();
+    iopub
+        .bind(&connection.endpoint(connection.iopub_port))
+        .await?;
+    // Input requests are not supported, but clients still expect to connect
+    let mut stdin = RouterSocket::new();
+    stdin
+        .bind(&connection.endpoint(connection.stdin_port))
+        .await?;
+    let mut heartbeat = RepSocket::new();
+    heartbeat
+        .bind(&connection.endpoint(connection.hb_port))
+        .await?;
+
+    let stop_token = app.engine.stop_token.clone();
+    let mut kernel = Kernel::new(&app.engine, iopub_tx).await;
+    let interrupt = kernel.interrupt.clone();
+
+    spawn_until_stopped(&stop_token, async move {
+        while let Ok(message) = heartbeat.recv().await {
+            if heartbeat.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+    spawn_until_stopped(&stop_token, publish_iopub(iopub, iopub_rx, signer.clone()));
+    spawn_until_stopped(
+        &stop_token,
+        serve_control(
+            control,
+            signer.clone(),
+            stop_token.clone(),
+            interrupt.clone(),
+        ),
+    );
+
+    stop_token
+        .child_token()
+        .run_until_cancelled(async {
+            loop {
+                let message = match shell.recv().await {
+                    Ok(x) => Message::decode(x, &signer),
+                    Err(e) => {
+                        tracing::error!("failed to receive on the shell socket: {e}");
+                        break;
+                    }
+                };
+                let message = match message {
+                    Ok(x) => x,
+                    Err(e) => {
+                        tracing::warn!("dropped a shell message: {e}");
+                        continue;
+                    }
+                };
+
+                let _ = kernel.iopub.send(IoPub::Begin(Box::new(message.clone())));
+                if let Some(reply) = kernel.handle(&message).await {
+                    if let Err(e) = shell.send(reply.encode(&signer)).await {
+                        tracing::error!("failed to reply on the shell socket: {e}");
+                    }
+                }
+                let _ = kernel.iopub.send(IoPub::End);
+            }
+        })
+        .await;
+
+    drop(stdin);
+    stop_token.cancel();
+    Ok(())
+}
+
+fn spawn_until_stopped(
+    stop_token: &CancellationToken,
+    future: impl Future<Output = ()> + Send + 'static,
+) {
+    let token = stop_token.child_token();
+    tokio::spawn(async move { token.run_until_cancelled(future).await });
+}
+
+async fn publish_iopub(
+    mut socket: PubSocket,
+    mut rx: mpsc::UnboundedReceiver<IoPub>,
+    signer: Signer,
+) {
+    let mut parent: Option<Box<Message>> = None;
+    while let Some(event) = rx.recv().await {
+        let message = match event {
+            IoPub::Begin(request) => {
+                let message = request.child("status", json!({ "execution_state": "busy" }));
+                parent = Some(request);
+                message
+            }
+            event => {
+                // Outputs from before the first request have nowhere to go
+                let Some(parent) = &parent else {
+                    continue;
+                };
+                match event {
+                    IoPub::End => parent.child("status", json!({ "execution_state": "idle" })),
+                    IoPub::Publish(msg_type, content) => parent.child(msg_type, content),
+                    IoPub::Stream(name, text) => {
+                        parent.child("stream", json!({ "name": name, "text": text }))
+                    }
+                    IoPub::Display(data) => {
+                        parent.child(
+                            "display_data",
+                            json!({ "data": data, "metadata": {}, "transient": {} }),
+                        )
+                    }
+                    IoPub::Begin(_) => unreachable!(),
+                }
+            }
+        };
+
+        if let Err(e) = socket.send(message.encode(&signer)).await {
+            tracing::error!("failed to publish on the IOPub socket: {e}");
+        }
+    }
+}
+
+async fn serve_control(
+    mut socket: RouterSocket,
+    signer: Signer,
+    stop_token: CancellationToken,
+    interrupt: Arc<Mutex<CancellationToken>>,
+) {
+    while let Ok(message) = socket.recv().await {
+        let message = match Message::decode(message, &signer) {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::warn!("dropped a control message: {e}");
+                continue;
+            }
+        };
+
+        let reply = match message.header.msg_type.as_str() {
+            "shutdown_request" => {
+                let restart = message.content["restart"].as_bool().unwrap_or(false);
+                message.reply(
+                    "shutdown_reply",
+                    json!({ "status": "ok", "restart": restart }),
+                )
+            }
+            "interrupt_request" => {
+                interrupt.lock().unwrap().cancel();
+                message.reply("interrupt_reply", json!({ "status": "ok" }))
+            }
+            "kernel_info_request" => message.reply("kernel_info_reply", kernel_info()),
+            msg_type => {
+                tracing::debug!("unsupported control message {msg_type}");
+                continue;
+            }
+        };
+
+        let _ = socket.send(reply.encode(&signer)).await;
+        if message.header.msg_type == "shutdown_request" {
+            stop_token.cancel();
+            break;
+        }
+    }
+}
+
+/// Expose `Den.jupyter.display(mime, data)` to scripts, which shows `data` as
+/// a rich output of the running cell. Data that is not a string is sent as
+/// JSON
+async fn register_display(engine: &Engine, iopub: mpsc::UnboundedSender<IoPub>) {
+    async_with!(engine.context => |ctx| {
+        let res: rquickjs::Result<()> = (|| {
+            let globals = ctx.globals();
+            let den = match globals.get::<_, Option<Object>>("Den")? {
+                Some(den) => den,
+                None => {
+                    let den = Object::new(ctx.clone())?;
+                    globals.set("Den", den.clone())?;
+                    den
+                }
+            };
+
+            let jupyter = Object::new(ctx.clone())?;
+            let display = Function::new(
+                ctx.clone(),
+                move |mime: String, data: SerdeJsonValue| {
+                    let _ = iopub.send(IoPub::Display(json!({ mime: data.0 })));
+                },
+            )?;
+            jupyter.set("display", display)?;
+            den.set("jupyter", jupyter)?;
+            Ok(())
+        })();
+
+        if let Err(e) = res {
+            tracing::error!("failed to register Den.jupyter: {e}");
+        }
+    })
+    .await;
+}
+
+fn kernel_info() -> Value {
+    json!({
+        "status": "ok",
+        "protocol_version": "5.3",
+        "implementation": "den",
+        "implementation_version": env!("CARGO_PKG_VERSION"),
+        "language_info": {
+            "name": "typescript",
+            "version": "5.0",
+            "mimetype": "text/typescript",
+            "file_extension": ".ts",
+            "pygments_lexer": "typescript",
+            "codemirror_mode": "typescript",
+        },
+        "banner": BANNER,
+        "help_links": [],
+    })
+}
+
+struct Kernel {
+    engine:          Engine,
+    evaluator:       mpsc::UnboundedSender<ReplMessage>,
+    iopub:           mpsc::UnboundedSender<IoPub>,
+    // Cancelled by an interrupt request on the control socket, replaced for
+    // every execution
+    interrupt:       Arc<Mutex<CancellationToken>>,
+    execution_count: u64,
+}
+
+impl Kernel {
+    /// A kernel evaluating cells in `engine`, whose runtime keeps running
+    /// between cells so that timers and pending promises still make progress
+    async fn new(engine: &Engine, iopub: mpsc::UnboundedSender<IoPub>) -> Self {
+        register_display(engine, iopub.clone()).await;
+        tokio::spawn(engine.runtime.drive());
+        Self {
+            engine: engine.clone(),
+            evaluator: App::spawn_repl_evaluator(engine),
+            iopub,
+            interrupt: Arc::default(),
+            execution_count: 0,
+        }
+    }
+
+    /// Handle a shell request, returns the reply to it if it is supported
+    async fn handle(&mut self, message: &Message) -> Option<Message> {
+        let content = &message.content;
+        let (msg_type, reply) = match message.header.msg_type.as_str() {
+            "kernel_info_request" => ("kernel_info_reply", kernel_info()),
+            "execute_request" => {
+                let code = content["code"].as_str().unwrap_or_default();
+                let silent = content["silent"].as_bool().unwrap_or(false);
+                let store_history = content["store_history"].as_bool().unwrap_or(true);
+                (
+                    "execute_reply",
+                    self.execute(code, silent, store_history).await,
+                )
+            }
+            "complete_request" => {
+                let code = content["code"].as_str().unwrap_or_default();
+                let cursor = content["cursor_pos"].as_u64().unwrap_or_default() as usize;
+                ("complete_reply", self.complete(code, cursor).await)
+            }
+            "inspect_request" => {
+                let code = content["code"].as_str().unwrap_or_default();
+                let cursor = content["cursor_pos"].as_u64().unwrap_or_default() as usize;
+                ("inspect_reply", self.inspect(code, cursor).await)
+            }
+            "is_complete_request" => {
+                let code = content["code"].as_str().unwrap_or_default();
+                (
+                    "is_complete_reply",
+                    if is_complete(code) {
+                        json!({ "status": "complete" })
+                    } else {
+                        json!({ "status": "incomplete", "indent": "  " })
+                    },
+                )
+            }
+            "comm_info_request" => ("comm_info_reply", json!({ "status": "ok", "comms": {} })),
+            "history_request" => ("history_reply", json!({ "status": "ok", "history": [] })),
+            msg_type => {
+                tracing::debug!("unsupported shell message {msg_type}");
+                return None;
+            }
+        };
+
+        Some(message.reply(msg_type, reply))
+    }
+
+    async fn execute(&mut self, code: &str, silent: bool, store_history: bool) -> Value {
+        if !silent && store_history {
+            self.execution_count += 1;
+        }
+        let execution_count = self.execution_count;
+        if !silent {
+            let _ = self.iopub.send(IoPub::Publish(
+                "execute_input",
+                json!({ "code": code, "execution_count": execution_count }),
+            ));
+        }
+
+        let interrupt = CancellationToken::new();
+        *self.interrupt.lock().unwrap() = interrupt.clone();
+        let (reply, rx) = oneshot::channel();
+        let _ = self.evaluator.send(ReplMessage {
+            request: ReplRequest::Eval(code.to_string()),
+            interrupt,
+            reply,
+        });
+
+        let error = match rx.await.unwrap_or(ReplReply::Done) {
+            ReplReply::Value(value) => {
+                if !silent && value != "undefined" {
+                    let _ = self.iopub.send(IoPub::Publish(
+                        "execute_result",
+                        json!({
+                            "execution_count": execution_count,
+                            "data": { "text/plain": value },
+                            "metadata": {},
+                        }),
+                    ));
+                }
+                None
+            }
+            ReplReply::Done => None,
+            ReplReply::Interrupted => {
+                Some(json!({
+                    "ename": "Interrupted",
+                    "evalue": "",
+                    "traceback": ["Interrupted"],
+                }))
+            }
+            ReplReply::Error(e) => {
+                let first = e.lines().next().unwrap_or_default();
+                let (ename, evalue) = first.split_once(": ").unwrap_or(("Error", first));
+                Some(json!({
+                    "ename": ename,
+                    "evalue": evalue,
+                    "traceback": e.lines().collect::<Vec<_>>(),
+                }))
+            }
+        };
+
+        match error {
+            Some(mut error) => {
+                if !silent {
+                    let _ = self.iopub.send(IoPub::Publish("error", error.clone()));
+                }
+                error["status"] = json!("error");
+                error["execution_count"] = json!(execution_count);
+                error
+            }
+            None => {
+                json!({
+                    "status": "ok",
+                    "execution_count": execution_count,
+                    "user_expressions": {},
+                    "payload": [],
+                })
+            }
+        }
+    }
+
+    /// Complete the property or global name right before the cursor
+    async fn complete(&self, code: &str, cursor: usize) -> Value {
+        let cursor = byte_offset(code, cursor);
+        let (start, path) = identifier_path(code, cursor, cursor);
+        let (object, prefix) = path.rsplit_once('.').unwrap_or(("globalThis", path));
+        let start = start + path.len() - prefix.len();
+
+        let object = object.to_string();
+        let prefix = prefix.to_string();
+        let mut matches: Vec<String> = if is_identifier_path(&object) {
+            async_with!(self.engine.context => |ctx| {
+                let names = lookup(&ctx, &object)
+                    .and_then(JsValue::into_object)
+                    .map(|x| property_names(&ctx, x))
+                    .unwrap_or_default();
+                ctx.catch();
+                names
+            })
+            .await
+        } else {
+            vec![]
+        };
+        matches.retain(|x| x.starts_with(&prefix));
+        matches.sort();
+
+        json!({
+            "status": "ok",
+            "matches": matches,
+            "cursor_start": code[..start].chars().count(),
+            "cursor_end": code[..cursor].chars().count(),
+            "metadata": {},
+        })
+    }
+
+    /// Show the type and value of the name under the cursor
+    async fn inspect(&self, code: &str, cursor: usize) -> Value {
+        let cursor = byte_offset(code, cursor);
+        let end = cursor
+            + code[cursor..]
+                .find(|c: char| !is_identifier_char(c))
+                .unwrap_or(code.len() - cursor);
+        let (_, path) = identifier_path(code, cursor, end);
+
+        let text = if is_identifier_path(path) {
+            let path = path.to_string();
+            async_with!(self.engine.context => |ctx| {
+                let text = lookup(&ctx, &path).map(|x| describe(&path, x));
+                ctx.catch();
+                text
+            })
+            .await
+        } else {
+            None
+        };
+
+        match text {
+            Some(text) => {
+                json!({
+                    "status": "ok",
+                    "found": true,
+                    "data": { "text/plain": text },
+                    "metadata": {},
+                })
+            }
+            None => json!({ "status": "ok", "found": false, "data": {}, "metadata": {} }),
+        }
+    }
+}
+
+/// The value at a dotted identifier path, read through property descriptors
+/// so that no getter runs. A name the global object lacks can only be a
+/// lexical binding, which reads without side effects
+fn lookup<'js>(ctx: &Ctx<'js>, path: &str) -> Option<JsValue<'js>> {
+    let mut names = path.split('.');
+    let first = names.next()?;
+    let mut value = match data_property(ctx, ctx.globals(), first) {
+        Ok(Some(value)) => value,
+        Ok(None) => ctx.eval(first).ok()?,
+        Err(()) => return None,
+    };
+    for name in names {
+        value = data_property(ctx, value.into_object()?, name).ok()??;
+    }
+    Some(value)
+}
+
+/// The value of the data property `name` of `object` or its prototypes, an
+/// error for accessors
+fn data_property<'js>(
+    ctx: &Ctx<'js>,
+    object: Object<'js>,
+    name: &str,
+) -> Result<Option<JsValue<'js>>, ()> {
+    let describe: Function = object_function(ctx, "getOwnPropertyDescriptor").ok_or(())?;
+    let mut object = Some(object);
+    while let Some(current) = object {
+        let descriptor: Option<Object> = describe.call((current.clone(), name)).map_err(|_| ())?;
+        if let Some(descriptor) = descriptor {
+            if !descriptor.contains_key("value").map_err(|_| ())? {
+                return Err(());
+            }
+            return descriptor.get("value").map(Some).map_err(|_| ());
+        }
+        object = current.get_prototype();
+    }
+    Ok(None)
+}
+
+/// The own property names of `object` and its prototypes
+fn property_names<'js>(ctx: &Ctx<'js>, object: Object<'js>) -> Vec<String> {
+    let Some(own_names) = object_function(ctx, "getOwnPropertyNames") else {
+        return vec![];
+    };
+    let mut names = BTreeSet::new();
+    let mut object = Some(object);
+    while let Some(current) = object {
+        names.extend(
+            own_names
+                .call::<_, Vec<String>>((current.clone(),))
+                .unwrap_or_default(),
+        );
+        object = current.get_prototype();
+    }
+    names.into_iter().collect()
+}
+
+fn object_function<'js>(ctx: &Ctx<'js>, name: &str) -> Option<Function<'js>> {
+    ctx.globals()
+        .get::<_, Object>("Object")
+        .and_then(|x| x.get(name))
+        .ok()
+}
+
+fn describe(path: &str, value: JsValue<'_>) -> String {
+    let type_name = value.type_name();
+    let text = value
+        .get::<Coerced<String>>()
+        .map(|Coerced(x)| x)
+        .unwrap_or_default();
+    format!("{path}: {type_name}\n{text}")
+}
+
+/// Jupyter counts the cursor position in code points
+fn byte_offset(code: &str, cursor: usize) -> usize {
+    code.char_indices()
+        .nth(cursor)
+        .map_or(code.len(), |(i, _)| i)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// The dotted identifier path ending at `end`, e.g. `foo.bar.ba`, with the
+/// byte offset it starts at. `cursor` has to be within the path
+fn identifier_path(code: &str, cursor: usize, end: usize) -> (usize, &str) {
+    let start = code[..cursor]
+        .rfind(|c: char| !is_identifier_char(c) && c != '.')
+        .map_or(0, |i| {
+            i + code[i..].chars().next().map_or(0, char::len_utf8)
+        });
+    (start, &code[start..end])
+}
+
+/// Only plain property accesses get looked up for completion and inspection,
+/// anything else might have side effects
+fn is_identifier_path(path: &str) -> bool {
+    path.split('.')
+        .all(|x| x.starts_with(|c: char| !c.is_ascii_digit()) && x.chars().all(is_identifier_char))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    async fn kernel() -> (Kernel, mpsc::UnboundedReceiver<IoPub>) {
+        let (iopub, rx) = mpsc::unbounded_channel();
+        (Kernel::new(&Engine::new().await, iopub).await, rx)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn looks_up_without_running_getters() {
+        let (kernel, _) = kernel().await;
+        kernel
+            .engine
+            .eval::<()>(concat!(
+                "globalThis.reads = 0;\n",
+                "globalThis.obj = { get lazy() { reads++; return {} }, value: { inner: \"one\" } \
+                 };\n",
+                "const local = { field: 2 };\n",
+            ))
+            .await
+            .unwrap();
+
+        let reply = kernel.complete("obj.", 4).await;
+        let matches = reply["matches"].as_array().unwrap();
+        assert!(matches.contains(&json!("lazy")) && matches.contains(&json!("value")));
+        assert_eq!(reply["cursor_start"], 4);
+        assert_eq!(kernel.complete("obj.lazy.", 9).await["matches"], json!([]));
+        assert_eq!(
+            kernel.complete("local.fi", 8).await["matches"],
+            json!(["field"])
+        );
+
+        assert_eq!(kernel.inspect("obj.lazy", 5).await["found"], false);
+        assert_eq!(
+            kernel.inspect("obj.value.inner", 11).await["data"]["text/plain"],
+            "obj.value.inner: string\none"
+        );
+
+        assert_eq!(kernel.engine.eval::<u32>("reads").await.unwrap(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn runs_timers_between_cells() {
+        let (mut kernel, mut iopub) = kernel().await;
+        let reply = kernel
+            .execute(
+                "setTimeout(() => Den.jupyter.display('text/plain', 'later'), 10)",
+                false,
+                true,
+            )
+            .await;
+        assert_eq!(reply["status"], "ok");
+
+        let display = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match iopub.recv().await {
+                    Some(IoPub::Display(data)) => return data,
+                    Some(_) => continue,
+                    None => panic!("the kernel dropped IOPub"),
+                }
+            }
+        });
+        assert_eq!(
+            display.await.expect("the timer never ran"),
+            json!({ "text/plain": "later" })
+        );
+    }
+}
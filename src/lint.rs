@@ -0,0 +1,108 @@
+use std::process::ExitCode;
+
+use den_core::{
+    config::Config,
+    lint::{LintError, Linter},
+};
+use serde_json::json;
+
+use crate::{cli::LintArgs, test_runner::display};
+
+/// Lint the files of `args`, or those of the config, and print what was
+/// found. Fails when anything was, or when a file cannot be linted
+pub fn lint(config: &Config, args: LintArgs) -> color_eyre::eyre::Result<ExitCode> {
+    let linter = Linter::new(config)?;
+    let files = Linter::files(config, &args.paths)?;
+
+    let mut problems = vec![];
+    let mut errors = vec![];
+    let mut fixed = 0;
+    for file in &files {
+        match linter.lint(file, args.fix) {
+            Ok(linted) => {
+                fixed += linted.fixed;
+                problems.extend(linted.problems.into_iter().map(|x| (display(file), x)));
+            }
+            Err(e) => errors.push((display(file), e)),
+        }
+    }
+
+    if args.json {
+        let problems: Vec<_> = problems
+            .iter()
+            .map(|(file, x)| {
+                json!({
+                    "file": file,
+                    "line": x.line,
+                    "column": x.column,
+                    "start": x.span.start,
+                    "end": x.span.end,
+                    "rule": x.rule,
+                    "message": x.message,
+                    "fixable": x.fix.is_some(),
+                })
+            })
+            .collect();
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|(file, e)| json!({ "file": file, "message": e.to_string() }))
+            .collect();
+        let report = json!({
+            "files": files.len(),
+            "fixed": fixed,
+            "problems": problems,
+            "errors": errors,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for (file, problem) in &problems {
+            let fixable = if problem.fix.is_some() {
+                " (fixable)"
+            } else {
+                ""
+            };
+            println!(
+                "{file}:{}:{}  {}  {}{fixable}",
+                problem.line, problem.column, problem.rule, problem.message
+            );
+        }
+        for (_, e) in &errors {
+            match e {
+                // Diagnostics say what they are already
+                LintError::Transpiler(e) => eprintln!("{e}"),
+                e => eprintln!("error: {e}"),
+            }
+        }
+
+        if !problems.is_empty() || !errors.is_empty() {
+            println!();
+        }
+        let fixable = problems.iter().filter(|(_, x)| x.fix.is_some()).count();
+        print!(
+            "Checked {}, found {}",
+            count(files.len(), "file"),
+            count(problems.len(), "problem")
+        );
+        if fixable > 0 {
+            print!(", {fixable} fixable with --fix");
+        }
+        if fixed > 0 {
+            print!(", fixed {fixed}");
+        }
+        println!();
+    }
+
+    Ok(if problems.is_empty() && errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// `count` of `noun`, in the plural unless there is one
+fn count(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {noun}"),
+        _ => format!("{count} {noun}s"),
+    }
+}
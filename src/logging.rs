@@ -0,0 +1,323 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use clap::ValueEnum;
+use tracing::{field::Field, Event, Level, Subscriber};
+use tracing_subscriber::{
+    field::Visit,
+    filter::{filter_fn, LevelFilter},
+    fmt::{writer::BoxMakeWriter, MakeWriter},
+    layer::Context,
+    prelude::*,
+    registry::LookupSpan,
+    EnvFilter, Layer,
+};
+
+// How many rotated log files are kept next to the current one
+const ROTATED_LOG_FILES: usize = 5;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum LogFormat {
+    /// `console` output as is on stdout and stderr, other logs on stderr
+    #[default]
+    Plain,
+    /// Multi-line human readable logs
+    Pretty,
+    /// Single-line human readable logs
+    Compact,
+    /// One JSON object per line
+    Json,
+}
+
+/// Whether an event comes from the `console` of a script
+pub fn is_console(target: &str) -> bool {
+    target.starts_with("den_stdlib_console")
+}
+
+/// The formatted message of an event
+pub fn event_message(event: &Event<'_>) -> String {
+    struct MessageVisitor(String);
+    impl Visit for MessageVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{value:?}");
+            }
+        }
+    }
+
+    let mut visitor = MessageVisitor(String::new());
+    event.record(&mut visitor);
+    visitor.0
+}
+
+/// Prints `console` messages without any decoration, like a plain program
+/// would, warnings and errors go to stderr
+struct PlainConsole<O, E> {
+    stdout: O,
+    stderr: E,
+}
+
+impl<S, O, E> Layer<S> for PlainConsole<O, E>
+where
+    S: Subscriber,
+    O: for<'a> MakeWriter<'a> + 'static,
+    E: for<'a> MakeWriter<'a> + 'static,
+{
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let message = event_message(event);
+        let _ = if matches!(*event.metadata().level(), Level::WARN | Level::ERROR) {
+            writeln!(self.stderr.make_writer(), "{message}")
+        } else {
+            writeln!(self.stdout.make_writer(), "{message}")
+        };
+    }
+}
+
+/// The [`PlainConsole`] layer, which only sees `console` events
+fn plain_console<S, O, E>(stdout: O, stderr: E) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    O: for<'a> MakeWriter<'a> + 'static,
+    E: for<'a> MakeWriter<'a> + 'static,
+{
+    PlainConsole { stdout, stderr }.with_filter(filter_fn(|x| is_console(x.target())))
+}
+
+/// A log file that is moved aside to `<path>.1` once it grows past a size,
+/// pushing older ones to `<path>.2` and so on
+pub struct RotatingFile {
+    path:     PathBuf,
+    max_size: u64,
+    file:     Mutex<(File, u64)>,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_size: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            file: Mutex::new((file, size)),
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&self) -> io::Result<File> {
+        for index in (1..ROTATED_LOG_FILES).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut guard = self.file.lock().unwrap();
+        let (file, size) = &mut *guard;
+        // Rotate between writes so that a single event never gets split
+        if *size > 0 && *size + buf.len() as u64 > self.max_size {
+            *file = self.rotate()?;
+            *size = 0;
+        }
+        file.write_all(buf)?;
+        *size += buf.len() as u64;
+        Ok(buf.len())
+    }
+}
+
+pub struct RotatingFileWriter<'a>(&'a RotatingFile);
+
+impl Write for RotatingFileWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.file.lock().unwrap().0.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingFile {
+    type Writer = RotatingFileWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RotatingFileWriter(self)
+    }
+}
+
+/// Install the global tracing subscriber. Logs go to `log_file` instead of
+/// the terminal when given, except for `console` output in the plain format
+pub fn init(format: LogFormat, log_file: Option<&Path>, max_size: u64) -> io::Result<()> {
+    let (writer, ansi) = match log_file {
+        Some(path) => {
+            (
+                BoxMakeWriter::new(RotatingFile::open(path, max_size)?),
+                false,
+            )
+        }
+        None if format == LogFormat::Plain => (BoxMakeWriter::new(io::stderr), true),
+        None => (BoxMakeWriter::new(io::stdout), true),
+    };
+
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    let layers = match format {
+        // `console` output is what the script prints, so RUST_LOG does not
+        // hide it
+        LogFormat::Plain => {
+            vec![
+                plain_console(io::stdout, io::stderr).boxed(),
+                layer
+                    .compact()
+                    .with_filter(env_filter())
+                    .with_filter(filter_fn(|x| !is_console(x.target())))
+                    .boxed(),
+            ]
+        }
+        LogFormat::Pretty => vec![layer.pretty().with_filter(env_filter()).boxed()],
+        LogFormat::Compact => vec![layer.compact().with_filter(env_filter()).boxed()],
+        LogFormat::Json => vec![layer.json().with_filter(env_filter()).boxed()],
+    };
+
+    tracing_subscriber::registry().with(layers).init();
+    Ok(())
+}
+
+/// `RUST_LOG`, or info and above when it is not set
+fn env_filter() -> EnvFilter {
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy()
+        // swc reports every pass of the minifier and how long it took as info
+        .add_directive("swc_timer=warn".parse().unwrap())
+        .add_directive("swc_ecma_minifier=warn".parse().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tracing::{error, info, warn};
+
+    use super::*;
+
+    #[test]
+    fn parses_log_formats() {
+        for (name, format) in [
+            ("plain", LogFormat::Plain),
+            ("pretty", LogFormat::Pretty),
+            ("compact", LogFormat::Compact),
+            ("json", LogFormat::Json),
+        ] {
+            assert_eq!(LogFormat::from_str(name, false), Ok(format));
+        }
+        assert_eq!(LogFormat::from_str("JSON", true), Ok(LogFormat::Json));
+        assert!(LogFormat::from_str("JSON", false).is_err());
+        assert!(LogFormat::from_str("xml", true).is_err());
+        assert_eq!(LogFormat::default(), LogFormat::Plain);
+    }
+
+    #[test]
+    fn keeps_the_newest_rotated_files() {
+        let dir = std::env::temp_dir().join(format!("den-logging-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("den.log");
+        let file = RotatingFile::open(&path, 10).unwrap();
+        // Every write but the first one overflows the file
+        for index in 1..=8 {
+            file.make_writer()
+                .write_all(format!("event {index}\n").as_bytes())
+                .unwrap();
+        }
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+        let files = [
+            read("den.log"),
+            read("den.log.1"),
+            read("den.log.2"),
+            read("den.log.3"),
+            read("den.log.4"),
+            read("den.log.5"),
+            read("den.log.6"),
+        ];
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(
+            files,
+            [
+                Some("event 8\n".to_string()),
+                Some("event 7\n".to_string()),
+                Some("event 6\n".to_string()),
+                Some("event 5\n".to_string()),
+                Some("event 4\n".to_string()),
+                Some("event 3\n".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl MakeWriter<'_> for Buffer {
+        type Writer = Self;
+
+        fn make_writer(&self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn splits_plain_console_output() {
+        let (stdout, stderr, logs) = (Buffer::default(), Buffer::default(), Buffer::default());
+        let subscriber = tracing_subscriber::registry()
+            .with(plain_console(stdout.clone(), stderr.clone()))
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(logs.clone())
+                    .with_filter(EnvFilter::new("off"))
+                    .with_filter(filter_fn(|x| !is_console(x.target()))),
+            );
+        tracing::subscriber::with_default(subscriber, || {
+            info!(target: "den_stdlib_console", "log");
+            warn!(target: "den_stdlib_console", "warn");
+            error!(target: "den_stdlib_console", "error");
+            error!(target: "den", "internal");
+        });
+
+        assert_eq!(stdout.text(), "log\n");
+        assert_eq!(stderr.text(), "warn\nerror\n");
+        assert_eq!(logs.text(), "");
+    }
+}
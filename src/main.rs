@@ -1,83 +1,263 @@
-use std::path::PathBuf;
+use std::{io::Read, process::ExitCode};
 
 use app::App;
 use clap::Parser;
-use den_core::engine::EngineError;
-use rquickjs::{async_with, Coerced};
-use tracing_subscriber::{filter::LevelFilter, EnvFilter};
+use cli::{CacheAction, Cli, Command, GlobalArgs, JupyterArgs, ReplArgs, RunArgs};
+use den_core::{
+    config::{self, Config},
+    standalone::Payload,
+};
+use inspect::InspectListener;
 
 #[cfg(feature = "mimalloc")]
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Cli {
-    #[arg()]
-    file:       Option<PathBuf>,
-    #[arg(long, default_value_t = false)]
-    repl:       bool,
-    #[arg(long, default_value_t = true)]
-    typescript: bool,
+fn main() -> color_eyre::eyre::Result<ExitCode> {
+    color_eyre::install()?;
+
+    // A compiled program takes its whole command line as arguments for the
+    // script
+    if let Some(payload) = Payload::current()? {
+        return runtime()?.block_on(compile::run_standalone(payload));
+    }
+
+    let cli = Cli::parse();
+    // Setting variables is only sound while no other thread may read them
+    env_file::load_env_files(&cli.global.env_file)?;
+
+    runtime()?.block_on(run(cli))
 }
 
-#[tokio::main]
-async fn main() -> color_eyre::eyre::Result<()> {
+fn runtime() -> std::io::Result<tokio::runtime::Runtime> {
+    // A script stuck in a loop occupies a worker until the interrupt handler
+    // kicks in, so keep at least one more around to observe Ctrl-C in the REPL
+    let worker_threads = std::thread::available_parallelism().map_or(2, |x| x.get().max(2));
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads)
+        .enable_all()
+        .build()
+}
+
+/// The config from `--config`, or the `den.json` that applies to the working
+/// directory unless `--no-config`, with the overrides of the global flags
+fn load_config(
+    args: &GlobalArgs,
+) -> color_eyre::eyre::Result<(Option<std::path::PathBuf>, Config)> {
+    let cwd = std::env::current_dir()?;
+    let (path, mut config) = match &args.config {
+        Some(path) => (Some(path.clone()), Config::load(path)?),
+        None if args.no_config => (None, Config::default()),
+        None => {
+            match Config::discover(&cwd)? {
+                Some((path, config)) => (Some(path), config),
+                None => {
+                    let mut config = Config::default();
+                    config.merge_tsconfig(&cwd)?;
+                    (None, config)
+                }
+            }
+        }
+    };
+    if let Some(import_map) = &args.import_map {
+        config.import_map = Some(cwd.join(import_map));
+    }
+    Ok((path, config))
+}
+
+async fn run(cli: Cli) -> color_eyre::eyre::Result<ExitCode> {
     #[cfg(all(feature = "tokio-console", tokio_unstable))]
     {
         console_subscriber::init();
     }
-    color_eyre::install()?;
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::INFO.into())
-                .from_env_lossy(),
-        )
-        .pretty()
-        .init();
 
-    let cli = Cli::parse();
-    let mut app = App::new().await;
+    let (config_path, config) = load_config(&cli.global)?;
 
-    if let Some(x) = cli.file.clone() {
-        app.hook_ctrlc_handler();
-        match app
-            .engine
-            .stop_token
-            .child_token()
-            .run_until_cancelled(app.engine.run_file::<()>(x))
-            .await
-        {
-            Some(Err(EngineError::Rquickjs(_))) => {
-                async_with!(app.engine.context => |ctx| {
-                    let e = ctx.catch();
-                    if let Some(e) = e.as_exception() {
-                        eprintln!("{e}")
-                    } else if let Ok(Coerced(e)) = e.get::<Coerced<String>>() {
-                        eprintln!("{e}")
-                    } else {
-                        eprintln!("unknown error")
+    if let Some(Command::Jupyter(JupyterArgs { install, conn, .. })) = &cli.command {
+        if *install {
+            let dir = jupyter::install()?;
+            println!("Installed the den kernelspec to {}", dir.display());
+        } else if let Some(conn) = conn {
+            jupyter::run_kernel(conn, &config).await?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    logging::init(
+        cli.global.log_format,
+        cli.global.log_file.as_deref(),
+        cli.global.log_file_size * 1024 * 1024,
+    )?;
+
+    match cli.command {
+        Some(Command::Run(args)) => {
+            if args.file.is_none() && args.eval.is_none() {
+                eprintln!("Nothing to run, give a file or `--eval`");
+                return Ok(ExitCode::FAILURE);
+            }
+            run_main(&config, args, ReplArgs::default(), false).await
+        }
+        Some(Command::Repl(args)) => {
+            let run = RunArgs {
+                inspect_repl: args.inspect_repl,
+                ..Default::default()
+            };
+            run_main(&config, run, args.args, true).await
+        }
+        Some(Command::Eval(args)) => {
+            let run = RunArgs {
+                eval: Some(args.code),
+                print: args.print,
+                ..Default::default()
+            };
+            run_main(&config, run, ReplArgs::default(), false).await
+        }
+        Some(Command::Test(args)) => test_runner::run_tests(&config, &args.paths).await,
+        Some(Command::Lint(args)) => lint::lint(&config, args),
+        Some(Command::Info(args)) => {
+            info::info(config_path.as_deref(), &config, args).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::Bundle(args)) => {
+            bundle::bundle(&config, args).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::Compile(args)) => {
+            compile::compile(&config, args).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::Cache(args)) => {
+            let dir = config::cache_dir();
+            match args.action {
+                CacheAction::Dir => println!("{}", dir.display()),
+                CacheAction::Clean => {
+                    if dir.exists() {
+                        std::fs::remove_dir_all(&dir)?;
                     }
-                })
-                .await;
+                    println!("Removed {}", dir.display());
+                }
             }
-            #[allow(unreachable_patterns)]
-            Some(Err(e)) => {
-                eprintln!("{e}")
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::Task(args)) => {
+            match args.name {
+                Some(name) => {
+                    tokio::task::block_in_place(|| task::run_task(&config, &name, &args.args))
+                }
+                None => {
+                    task::list_tasks(config_path.as_deref(), &config);
+                    Ok(ExitCode::SUCCESS)
+                }
             }
-            _ => {}
         }
+        Some(Command::Types) => {
+            print!("{}", include_str!("den.d.ts"));
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::Jupyter(_)) => unreachable!(),
+        None => {
+            let repl = cli.run.file.is_none() && cli.run.eval.is_none();
+            run_main(&config, cli.run, cli.repl, repl).await
+        }
+    }
+}
+
+/// Run a file or a piece of code, then a REPL transcript or session if asked
+async fn run_main(
+    config: &Config,
+    run: RunArgs,
+    repl: ReplArgs,
+    start_repl: bool,
+) -> color_eyre::eyre::Result<ExitCode> {
+    if let (true, Some(file)) = (run.watch, &run.file) {
+        return watch::run_watch(file.clone(), run.args, run.watch_path, config).await;
     }
 
-    if cli.repl || cli.file.is_none() {
+    let mut app = App::new(config).await?;
+    app.engine.set_args(run.args).await?;
+
+    if let Some(addr) = run.inspect_repl {
+        let token = std::env::var("DEN_INSPECT_TOKEN").unwrap_or_else(|_| {
+            rand::random::<[u8; 16]>()
+                .iter()
+                .map(|x| format!("{x:02x}"))
+                .collect()
+        });
+        let listener = InspectListener::bind(&addr).await?;
+        eprintln!("Remote REPL listening on {addr} with token {token}");
+        tokio::spawn(listener.serve(app.engine.clone(), token));
+        // Waiting for the runtime to go idle would hold its lock and starve the
+        // remote sessions, so stay around until explicitly stopped instead
+        app.set_wait_for_cancel_signal(true);
+    }
+
+    let mut exit_code = ExitCode::SUCCESS;
+
+    let succeeded = if let Some(code) = &run.eval {
+        app.hook_ctrlc_handler();
+        app.run_eval(code, run.print).await
+    } else if let Some(x) = run.file {
+        app.hook_ctrlc_handler();
+        if x.as_os_str() == "-" {
+            let mut src = String::new();
+            std::io::stdin().read_to_string(&mut src)?;
+            app.run_module_source("$stdin", &src).await
+        } else {
+            app.run_file(x).await
+        }
+    } else {
+        true
+    };
+    if !succeeded {
+        exit_code = ExitCode::FAILURE;
+    }
+
+    if let Some(script) = repl.repl_script {
+        let script = if script.as_os_str() == "-" {
+            let mut script = String::new();
+            std::io::stdin().read_to_string(&mut script)?;
+            script
+        } else {
+            std::fs::read_to_string(script)?
+        };
+
+        let transcript = app.run_repl_script(&script).await;
+        match repl.repl_expect {
+            Some(expected) => {
+                let mismatches = transcript.diff(&std::fs::read_to_string(&expected)?);
+                if !mismatches.is_empty() {
+                    eprintln!("transcript does not match {}:", expected.display());
+                    for (line, expected, actual) in mismatches {
+                        eprintln!("line {line}:");
+                        eprintln!("  - {}", expected.as_deref().unwrap_or("<none>"));
+                        eprintln!("  + {}", actual.as_deref().unwrap_or("<none>"));
+                    }
+                    exit_code = ExitCode::FAILURE;
+                }
+            }
+            None => print!("{transcript}"),
+        }
+    } else if start_repl {
         println!("Welcome to den, one word less than Deno");
         app.start_repl_session();
     }
 
     app.run_until_end().await;
-    Ok(())
+    Ok(exit_code)
 }
 
 mod app;
+mod bundle;
+mod cli;
+mod compile;
+mod env_file;
+mod info;
+mod inspect;
+mod jupyter;
+mod lint;
+mod logging;
 mod repl;
+mod task;
+mod test_runner;
+mod transcript;
+mod watch;
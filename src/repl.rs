@@ -1,9 +1,18 @@
+use std::path::Path;
+
 use rustyline::{
-    config::Configurer, error::ReadlineError, sqlite_history::SQLiteHistory,
+    config::Configurer, error::ReadlineError, history::History, sqlite_history::SQLiteHistory,
     validate::MatchingBracketValidator, Behavior, Completer, Config, Editor, Helper, Highlighter,
     Hinter, Validator,
 };
-use tokio::{sync::mpsc, task::yield_now};
+use tokio::{
+    signal,
+    sync::{mpsc, oneshot},
+    task::yield_now,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::app::{ReplMessage, ReplReply, ReplRequest};
 
 #[derive(Completer, Helper, Highlighter, Hinter, Validator)]
 struct InputValidator {
@@ -11,22 +20,285 @@ struct InputValidator {
     brackets: MatchingBracketValidator,
 }
 
-pub async fn run_repl(output_sink: mpsc::UnboundedSender<String>) {
+const HELP: &str = r#".clear    Reset the evaluation context
+.editor   Enter editor mode, finish with Ctrl+D or cancel with Ctrl+C
+.exit     Exit the REPL
+.help     Print this help message
+.load     Load a script file into the REPL session
+.save     Save all evaluated inputs of this REPL session to a file
+.type     Show the typeof of an expression, with the constructor of objects
+
+Press Ctrl+C to abort current expression, Ctrl+D to exit the REPL"#;
+
+/// A piece of output produced while handling one REPL input, in the order it
+/// was produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplOutput {
+    Stdout(String),
+    Stderr(String),
+}
+
+impl ReplOutput {
+    pub fn print(&self) {
+        match self {
+            ReplOutput::Stdout(x) => println!("{x}"),
+            ReplOutput::Stderr(x) => eprintln!("{x}"),
+        }
+    }
+}
+
+/// The front end state of one REPL session, independent of where the inputs
+/// come from
+pub struct ReplSession {
+    output_sink: mpsc::UnboundedSender<ReplMessage>,
+    // Every input that has been sent for evaluation since the last `.clear`, for
+    // `.save`
+    inputs:      Vec<String>,
+    // Whether Ctrl+C interrupts a running evaluation, only makes sense when
+    // attached to a terminal
+    ctrl_c:      bool,
+    // Interrupts every evaluation of the session when cancelled
+    interrupt:   CancellationToken,
+}
+
+impl ReplSession {
+    pub fn new(output_sink: mpsc::UnboundedSender<ReplMessage>, ctrl_c: bool) -> Self {
+        Self {
+            output_sink,
+            inputs: vec![],
+            ctrl_c,
+            interrupt: CancellationToken::new(),
+        }
+    }
+
+    /// Interrupt the evaluations of the session once `token` is cancelled,
+    /// including any to come
+    #[must_use]
+    pub fn with_interrupt(mut self, token: CancellationToken) -> Self {
+        self.interrupt = token;
+        self
+    }
+
+    /// Send a request to the evaluator and wait for its answer. If enabled,
+    /// pressing Ctrl+C while waiting interrupts the evaluation instead of
+    /// exiting
+    async fn send_request(&self, request: ReplRequest) -> Option<ReplReply> {
+        let (reply, mut rx) = oneshot::channel();
+        let interrupt = self.interrupt.child_token();
+        let message = ReplMessage {
+            request,
+            interrupt: interrupt.clone(),
+            reply,
+        };
+        self.output_sink.send(message).ok()?;
+
+        if !self.ctrl_c {
+            return rx.await.ok();
+        }
+
+        // The line editor is not in raw mode while we wait, so Ctrl+C arrives as a
+        // signal rather than as an input
+        tokio::select! {
+            res = &mut rx => res,
+            _ = signal::ctrl_c() => {
+                interrupt.cancel();
+                rx.await
+            }
+        }
+        .ok()
+    }
+
+    async fn eval(&mut self, source: String, output: &mut Vec<ReplOutput>) {
+        let reply = self.send_request(ReplRequest::Eval(source.clone())).await;
+        self.inputs.push(source);
+        output.extend(reply.and_then(ReplReply::into_output));
+    }
+
+    /// Handle one complete input, either a script or a dot-command, and
+    /// collect what it outputs. Returns `false` if the session should end
+    pub async fn handle(&mut self, text: &str, output: &mut Vec<ReplOutput>) -> bool {
+        let Some(command) = dot_command(text) else {
+            if !text.trim().is_empty() {
+                self.eval(text.to_string(), output).await;
+            }
+            return true;
+        };
+
+        let (keyword, arg) = command
+            .split_once(char::is_whitespace)
+            .map(|(keyword, arg)| (keyword, arg.trim()))
+            .unwrap_or((command, ""));
+
+        match keyword {
+            "help" => output.push(ReplOutput::Stdout(HELP.to_string())),
+            "exit" => return false,
+            "clear" => {
+                self.inputs.clear();
+                self.send_request(ReplRequest::Clear).await;
+                output.push(ReplOutput::Stdout("Clearing context...".to_string()));
+            }
+            "load" if !arg.is_empty() => {
+                match tokio::fs::read_to_string(Path::new(arg)).await {
+                    Ok(source) => self.eval(source, output).await,
+                    Err(e) => output.push(ReplOutput::Stderr(format!("Failed to load {arg}: {e}"))),
+                }
+            }
+            "save" if !arg.is_empty() => {
+                output.push(
+                    match tokio::fs::write(Path::new(arg), self.inputs.join("\n")).await {
+                        Ok(_) => ReplOutput::Stdout(format!("Session saved to: {arg}")),
+                        Err(e) => ReplOutput::Stderr(format!("Failed to save {arg}: {e}")),
+                    },
+                );
+            }
+            "type" if !arg.is_empty() => {
+                let reply = self
+                    .send_request(ReplRequest::TypeOf(arg.to_string()))
+                    .await;
+                output.extend(reply.and_then(ReplReply::into_output));
+            }
+            "load" | "save" | "type" => {
+                output.push(ReplOutput::Stderr(format!(
+                    ".{keyword} requires an argument"
+                )))
+            }
+            "editor" => {
+                output.push(ReplOutput::Stderr(
+                    ".editor is only available in an interactive session".to_string(),
+                ))
+            }
+            _ => {
+                output.push(ReplOutput::Stderr(
+                    "Invalid REPL keyword, type .help for the list".to_string(),
+                ))
+            }
+        }
+        true
+    }
+}
+
+impl ReplReply {
+    fn into_output(self) -> Option<ReplOutput> {
+        match self {
+            ReplReply::Value(x) => Some(ReplOutput::Stdout(x)),
+            ReplReply::Error(e) => Some(ReplOutput::Stderr(e)),
+            ReplReply::Interrupted => Some(ReplOutput::Stderr("Interrupted".to_string())),
+            ReplReply::Done => None,
+        }
+    }
+}
+
+/// The dot-command of an input without its leading dot, if it is one.
+/// Dot-commands are only recognized when directly followed by a letter, so
+/// that inputs like `.5 + 1` are still evaluated
+pub fn dot_command(text: &str) -> Option<&str> {
+    text.trim()
+        .strip_prefix('.')
+        .filter(|x| x.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+/// Check whether a piece of script has all its brackets, strings and comments
+/// closed, i.e. whether it makes sense to send it for evaluation yet
+pub fn is_complete(source: &str) -> bool {
+    // Each entry is the closing character we are waiting for. Template literals
+    // push '`' and their `${` substitutions push '}'
+    let mut stack = vec![];
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (stack.last().copied(), c) {
+            (Some('`'), '\\') => {
+                chars.next();
+            }
+            (Some('`'), '`') => {
+                stack.pop();
+            }
+            (Some('`'), '$') if chars.peek() == Some(&'{') => {
+                chars.next();
+                stack.push('}');
+            }
+            (Some('`'), _) => {}
+            (_, '"' | '\'') => {
+                // Strings cannot span multiple lines without an escape, an unterminated one is
+                // a syntax error rather than an incomplete input
+                while let Some(x) = chars.next() {
+                    match x {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '\n' => break,
+                        x if x == c => break,
+                        _ => {}
+                    }
+                }
+            }
+            (_, '`') => stack.push('`'),
+            (_, '/') if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&x| x != '\n').is_some() {}
+            }
+            (_, '/') if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut closed = false;
+                while let Some(x) = chars.next() {
+                    if x == '*' && chars.next_if_eq(&'/').is_some() {
+                        closed = true;
+                        break;
+                    }
+                }
+                if !closed {
+                    return false;
+                }
+            }
+            (_, '(') => stack.push(')'),
+            (_, '[') => stack.push(']'),
+            (_, '{') => stack.push('}'),
+            // Closing brackets pop their opening one, an unbalanced one is a syntax error so
+            // let the evaluator report it
+            (_, ')' | ']' | '}') if stack.pop() != Some(c) => return true,
+            _ => {}
+        }
+    }
+
+    stack.is_empty()
+}
+
+/// Read lines until Ctrl+D, returns `None` if cancelled with Ctrl+C
+fn read_editor_buffer<H: Helper, I: History>(rl: &mut Editor<H, I>) -> Option<String> {
+    println!("// Entering editor mode (Ctrl+D to finish, Ctrl+C to cancel)");
+    let mut buffer = vec![];
+    loop {
+        match rl.readline("") {
+            Ok(line) => buffer.push(line),
+            Err(ReadlineError::Eof) => break Some(buffer.join("\n")),
+            Err(_) => break None,
+        }
+    }
+}
+
+pub async fn run_repl(output_sink: mpsc::UnboundedSender<ReplMessage>) {
     let h = InputValidator {
         brackets: MatchingBracketValidator::new(),
     };
     let mut interrupted = false;
     let config = Config::default();
+    // Without a place to keep it, the history only lasts for the session
+    let dir = den_core::config::data_dir();
+    let history = std::fs::create_dir_all(&dir)
+        .ok()
+        .and_then(|_| SQLiteHistory::open(config, &dir.join("history.db")).ok());
     let mut rl = Editor::with_history(
         config,
-        SQLiteHistory::open(config, "history.db")
-            .or(SQLiteHistory::with_config(config))
+        history
+            .map(Ok)
+            .unwrap_or_else(|| SQLiteHistory::with_config(config))
             .unwrap(),
     )
     .unwrap();
     rl.set_behavior(Behavior::PreferTerm);
     rl.set_helper(Some(h));
 
+    let mut session = ReplSession::new(output_sink, true);
+
     'repl: loop {
         match rl.readline("> ") {
             Err(ReadlineError::Eof) => break 'repl,
@@ -41,10 +313,27 @@ pub async fn run_repl(output_sink: mpsc::UnboundedSender<String>) {
                 interrupted = false;
 
                 if !text.is_empty() {
-                    let _ = output_sink.send(text.clone());
                     let _ = rl.add_history_entry(&text).unwrap();
                 }
 
+                let mut output = vec![];
+                let proceed = if text.trim() == ".editor" {
+                    if let Some(source) = read_editor_buffer(&mut rl) {
+                        session.eval(source, &mut output).await;
+                    }
+                    true
+                } else {
+                    session.handle(&text, &mut output).await
+                };
+
+                for x in output {
+                    x.print();
+                }
+
+                if !proceed {
+                    break 'repl;
+                }
+
                 yield_now().await;
             }
         }
@@ -0,0 +1,303 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::OnceLock,
+    thread,
+};
+
+use den_core::config::{Config, TaskDefinition};
+use derive_more::{Display, Error};
+use shell::{CommandLine, Shell, ShellError};
+
+mod shell;
+
+#[derive(Display, Error, Debug)]
+pub enum TaskError {
+    #[display("task {_0:?} is not defined")]
+    NotFound(#[error(not(source))] String),
+    #[display("task {_0:?} depends on itself through {_1}")]
+    Cycle(String, #[error(not(source))] String),
+    #[display("task {_0:?} needs either a command or a script")]
+    Empty(#[error(not(source))] String),
+    #[display("invalid command of task {_0:?}: {_1}")]
+    Shell(String, ShellError),
+}
+
+/// Print the tasks of the config along with their descriptions
+pub fn list_tasks(config_path: Option<&Path>, config: &Config) {
+    let Some(config_path) = config_path else {
+        eprintln!("No config file found, tasks are defined in den.json");
+        return;
+    };
+    if config.tasks.is_empty() {
+        eprintln!("No tasks defined in {}", config_path.display());
+        return;
+    }
+
+    println!("Available tasks:");
+    for (name, task) in &config.tasks {
+        let definition = task.definition();
+        println!("- {name}");
+        if let Some(description) = &definition.description {
+            println!("    {description}");
+        }
+        if let Some(line) = command_line(&definition) {
+            println!("    {line}");
+        }
+    }
+}
+
+/// What a task runs, a den script is run through the `den` builtin
+fn command_line(definition: &TaskDefinition) -> Option<String> {
+    definition.command.clone().or_else(|| {
+        definition
+            .script
+            .as_ref()
+            .map(|x| format!("den run {}", shell::quote(&x.to_string_lossy())))
+    })
+}
+
+struct TaskRunner<'a> {
+    config: &'a Config,
+    // Each task runs at most once, even when several others depend on it
+    status: BTreeMap<&'a str, OnceLock<bool>>,
+    lines:  BTreeMap<&'a str, (String, CommandLine)>,
+    exe:    PathBuf,
+}
+
+impl<'a> TaskRunner<'a> {
+    /// Check the tasks reachable from `name` before running anything, so a
+    /// typo does not show up halfway through
+    fn prepare(
+        &mut self,
+        name: &str,
+        stack: &mut Vec<&'a str>,
+        args: &[String],
+    ) -> Result<(), TaskError> {
+        if stack.contains(&name) {
+            let cycle = [stack.as_slice(), &[name]].concat().join(" -> ");
+            return Err(TaskError::Cycle(name.to_string(), cycle));
+        }
+        if self.status.contains_key(name) {
+            return Ok(());
+        }
+
+        let (name, task) = self
+            .config
+            .tasks
+            .get_key_value(name)
+            .ok_or_else(|| TaskError::NotFound(name.to_string()))?;
+        let definition = task.definition();
+        let mut line = command_line(&definition).ok_or_else(|| TaskError::Empty(name.clone()))?;
+        // Extra arguments only go to the task that was asked for
+        if stack.is_empty() {
+            for arg in args {
+                line.push(' ');
+                line.push_str(&shell::quote(arg));
+            }
+        }
+        let parsed = CommandLine::parse(&line).map_err(|e| TaskError::Shell(name.clone(), e))?;
+        self.status.insert(name, OnceLock::new());
+        self.lines.insert(name, (line, parsed));
+
+        stack.push(name);
+        for dependency in &definition.dependencies {
+            for name in dependency.names() {
+                self.prepare(name, stack, args)?;
+            }
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    fn run(&self, name: &str) -> bool {
+        *self.status[name].get_or_init(|| self.run_once(name))
+    }
+
+    fn run_once(&self, name: &str) -> bool {
+        let definition = self.config.tasks[name].definition();
+
+        for dependency in &definition.dependencies {
+            let succeeded = match dependency.names() {
+                [name] => self.run(name),
+                names => {
+                    thread::scope(|s| {
+                        let handles: Vec<_> = names
+                            .iter()
+                            .map(|name| s.spawn(|| self.run(name)))
+                            .collect();
+                        // Let every task of the group finish before giving up
+                        let succeeded: Vec<bool> = handles
+                            .into_iter()
+                            .map(|x| x.join().unwrap_or(false))
+                            .collect();
+                        succeeded.into_iter().all(|x| x)
+                    })
+                }
+            };
+            if !succeeded {
+                return false;
+            }
+        }
+
+        let cwd = match &definition.cwd {
+            Some(cwd) => self.config.resolve_path(cwd),
+            None => self.config.dir.clone(),
+        };
+        let mut env: BTreeMap<String, String> = std::env::vars_os()
+            .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+            .collect();
+        env.extend(definition.env);
+
+        let (line, parsed) = &self.lines[name];
+        eprintln!("Task {name} {line}");
+        let status = Shell::new(cwd, env, self.exe.clone()).run(parsed);
+        if status != 0 {
+            eprintln!("Task {name} failed with exit code {status}");
+        }
+        status == 0
+    }
+}
+
+/// Run a task of the config after the tasks it depends on, with `args`
+/// appended to its command line
+pub fn run_task(
+    config: &Config,
+    name: &str,
+    args: &[String],
+) -> color_eyre::eyre::Result<ExitCode> {
+    let mut runner = TaskRunner {
+        config,
+        status: BTreeMap::new(),
+        lines: BTreeMap::new(),
+        exe: std::env::current_exe()?,
+    };
+    runner.prepare(name, &mut vec![], args)?;
+
+    Ok(if runner.run(name) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// A config of `tasks` in a fresh directory, which tasks run in
+    fn config(name: &str, tasks: serde_json::Value) -> Config {
+        let dir = std::env::temp_dir().join(format!("den-task-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Config {
+            dir,
+            ..serde_json::from_value(json!({ "tasks": tasks })).unwrap()
+        }
+    }
+
+    fn runner(config: &Config) -> TaskRunner<'_> {
+        TaskRunner {
+            config,
+            status: BTreeMap::new(),
+            lines: BTreeMap::new(),
+            exe: PathBuf::new(),
+        }
+    }
+
+    /// Run `name` and return what the tasks wrote to `log`
+    fn run(config: &Config, name: &str) -> (bool, String) {
+        let mut runner = runner(config);
+        runner.prepare(name, &mut vec![], &[]).unwrap();
+        let succeeded = runner.run(name);
+        let log = fs::read_to_string(config.dir.join("log")).unwrap_or_default();
+        let _ = fs::remove_dir_all(&config.dir);
+        (succeeded, log)
+    }
+
+    #[test]
+    fn runs_dependencies_first() {
+        let config = config(
+            "order",
+            json!({
+                "a": { "command": "echo a >> log", "dependencies": ["b", "c"] },
+                "b": { "command": "echo b >> log", "dependencies": ["c"] },
+                "c": "echo c >> log",
+            }),
+        );
+        assert_eq!(run(&config, "a"), (true, "c\nb\na\n".to_string()));
+    }
+
+    #[test]
+    fn stops_at_a_failed_dependency() {
+        let config = config(
+            "failure",
+            json!({
+                "a": { "command": "echo a >> log", "dependencies": ["fail", "b"] },
+                "b": "echo b >> log",
+                "fail": "false",
+            }),
+        );
+        assert_eq!(run(&config, "a"), (false, String::new()));
+    }
+
+    #[test]
+    fn prepare_rejects_cycles_and_unknown_tasks() {
+        let config = config(
+            "prepare",
+            json!({
+                "a": { "command": "true", "dependencies": ["b"] },
+                "b": { "command": "true", "dependencies": [["c", "a"]] },
+                "c": "true",
+                "d": { "command": "true", "dependencies": ["missing"] },
+            }),
+        );
+        let prepare = |name| runner(&config).prepare(name, &mut vec![], &[]);
+        assert!(matches!(
+            prepare("a"),
+            Err(TaskError::Cycle(name, cycle)) if name == "a" && cycle == "a -> b -> a"
+        ));
+        assert!(matches!(
+            prepare("d"),
+            Err(TaskError::NotFound(name)) if name == "missing"
+        ));
+        assert!(matches!(prepare("e"), Err(TaskError::NotFound(name)) if name == "e"));
+        assert!(prepare("c").is_ok());
+        let _ = fs::remove_dir_all(&config.dir);
+    }
+
+    #[test]
+    fn runs_shared_dependencies_once() {
+        let config = config(
+            "shared",
+            json!({
+                "all": { "command": "true", "dependencies": [["a", "b", "c"]] },
+                "a": { "command": "true", "dependencies": ["shared"] },
+                "b": { "command": "true", "dependencies": ["shared"] },
+                "c": { "command": "true", "dependencies": ["shared"] },
+                "shared": "echo shared >> log",
+            }),
+        );
+        assert_eq!(run(&config, "all"), (true, "shared\n".to_string()));
+    }
+
+    #[test]
+    fn merges_the_task_env_into_the_inherited_one() {
+        let path = std::env::var("PATH").unwrap_or_default();
+        let config = config(
+            "env",
+            json!({
+                "greet": {
+                    "command": "echo $GREETING \"$PATH\" > log",
+                    "env": { "GREETING": "hi" },
+                },
+            }),
+        );
+        assert_eq!(run(&config, "greet"), (true, format!("hi {path}\n")));
+    }
+}
@@ -0,0 +1,849 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{self, PipeReader, PipeWriter, Write},
+    mem,
+    path::PathBuf,
+    process::{self, Stdio},
+};
+
+use derive_more::{Display, Error};
+
+// A small shell for task commands that behaves the same on every platform. It
+// knows `;`, `&&`, `||`, pipes, redirections, quoting, `$VAR` expansion and
+// `NAME=value` assignments, along with a handful of builtins. Expanded
+// variables are never split into several words
+
+#[derive(Display, Error, Debug, Clone, PartialEq)]
+pub enum ShellError {
+    #[display("unterminated quote")]
+    UnterminatedQuote,
+    #[display("unexpected `{_0}`")]
+    UnexpectedToken(#[error(not(source))] &'static str),
+    #[display("missing file name after `{_0}`")]
+    MissingRedirectTarget(#[error(not(source))] &'static str),
+    #[display("running commands in the background is not supported")]
+    Background,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum WordPart {
+    Literal(String),
+    Variable(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Word {
+    parts:      Vec<WordPart>,
+    // Whether any part of the word was quoted or escaped, so that e.g. `"2">`
+    // is not a redirection of stderr
+    quoted:     bool,
+    // The variable name of a `NAME=value` word
+    assignment: Option<String>,
+}
+
+impl Word {
+    fn push(&mut self, c: char) {
+        match self.parts.last_mut() {
+            Some(WordPart::Literal(x)) => x.push(c),
+            _ => self.parts.push(WordPart::Literal(c.to_string())),
+        }
+    }
+
+    fn is_plain(&self, text: &str) -> bool {
+        !self.quoted && self.parts == [WordPart::Literal(text.to_string())]
+    }
+
+    fn expand(&self, env: &BTreeMap<String, String>) -> String {
+        self.parts
+            .iter()
+            .map(|x| {
+                match x {
+                    WordPart::Literal(x) => x.as_str(),
+                    WordPart::Variable(x) => env.get(x).map_or("", String::as_str),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    And,
+    Or,
+    Semi,
+    Pipe,
+    In,
+    Out,
+    Append,
+    Err,
+    AppendErr,
+    ErrToOut,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::And => "&&",
+            Op::Or => "||",
+            Op::Semi => ";",
+            Op::Pipe => "|",
+            Op::In => "<",
+            Op::Out => ">",
+            Op::Append => ">>",
+            Op::Err => "2>",
+            Op::AppendErr => "2>>",
+            Op::ErrToOut => "2>&1",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(Word),
+    Op(Op),
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|x| x.is_ascii_alphabetic() || x == '_')
+        && chars.all(|x| x.is_ascii_alphanumeric() || x == '_')
+}
+
+fn read_variable(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    word: &mut Word,
+) -> Result<(), ShellError> {
+    if chars.peek() == Some(&'{') {
+        chars.next();
+        let mut name = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) => name.push(c),
+                None => return Err(ShellError::UnterminatedQuote),
+            }
+        }
+        word.parts.push(WordPart::Variable(name));
+        return Ok(());
+    }
+
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if !(c.is_ascii_alphanumeric() || c == '_') {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    if name.is_empty() {
+        word.push('$');
+    } else {
+        word.parts.push(WordPart::Variable(name));
+    }
+    Ok(())
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ShellError> {
+    let mut tokens = vec![];
+    let mut word: Option<Word> = None;
+    let mut chars = input.chars().peekable();
+
+    fn finish(tokens: &mut Vec<Token>, word: &mut Option<Word>) {
+        if let Some(word) = word.take() {
+            tokens.push(Token::Word(word));
+        }
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\r' | '\n' => finish(&mut tokens, &mut word),
+            '\'' => {
+                let word = word.get_or_insert_with(Word::default);
+                word.quoted = true;
+                word.parts.push(WordPart::Literal(String::new()));
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(ShellError::UnterminatedQuote),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(Word::default);
+                word.quoted = true;
+                word.parts.push(WordPart::Literal(String::new()));
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            match chars.next() {
+                                Some(c @ ('"' | '\\' | '$')) => word.push(c),
+                                Some(c) => {
+                                    word.push('\\');
+                                    word.push(c);
+                                }
+                                None => return Err(ShellError::UnterminatedQuote),
+                            }
+                        }
+                        Some('$') => read_variable(&mut chars, word)?,
+                        Some(c) => word.push(c),
+                        None => return Err(ShellError::UnterminatedQuote),
+                    }
+                }
+            }
+            '\\' => {
+                let word = word.get_or_insert_with(Word::default);
+                word.quoted = true;
+                word.push(chars.next().unwrap_or('\\'));
+            }
+            '$' => read_variable(&mut chars, word.get_or_insert_with(Word::default))?,
+            ';' => {
+                finish(&mut tokens, &mut word);
+                tokens.push(Token::Op(Op::Semi));
+            }
+            '&' => {
+                finish(&mut tokens, &mut word);
+                if chars.next_if_eq(&'&').is_none() {
+                    return Err(ShellError::Background);
+                }
+                tokens.push(Token::Op(Op::And));
+            }
+            '|' => {
+                finish(&mut tokens, &mut word);
+                tokens.push(Token::Op(if chars.next_if_eq(&'|').is_some() {
+                    Op::Or
+                } else {
+                    Op::Pipe
+                }));
+            }
+            '<' => {
+                finish(&mut tokens, &mut word);
+                tokens.push(Token::Op(Op::In));
+            }
+            '>' => {
+                let stderr = word.as_ref().is_some_and(|x| x.is_plain("2"));
+                if stderr {
+                    word = None;
+                } else {
+                    finish(&mut tokens, &mut word);
+                }
+                let append = chars.next_if_eq(&'>').is_some();
+                let op = match (stderr, append) {
+                    (true, false) if chars.peek() == Some(&'&') => {
+                        chars.next();
+                        if chars.next_if_eq(&'1').is_none() {
+                            return Err(ShellError::UnexpectedToken("2>&"));
+                        }
+                        Op::ErrToOut
+                    }
+                    (true, false) => Op::Err,
+                    (true, true) => Op::AppendErr,
+                    (false, false) => Op::Out,
+                    (false, true) => Op::Append,
+                };
+                tokens.push(Token::Op(op));
+            }
+            '=' => {
+                let word = word.get_or_insert_with(Word::default);
+                if word.assignment.is_none() && !word.quoted {
+                    if let [WordPart::Literal(name)] = word.parts.as_slice() {
+                        if is_name(name) {
+                            word.assignment = Some(name.clone());
+                        }
+                    }
+                }
+                word.push('=');
+            }
+            c => word.get_or_insert_with(Word::default).push(c),
+        }
+    }
+    finish(&mut tokens, &mut word);
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Redirect {
+    In(Word),
+    Out(Word, bool),
+    Err(Word, bool),
+    ErrToOut,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct SimpleCommand {
+    assignments: Vec<(String, Word)>,
+    args:        Vec<Word>,
+    redirects:   Vec<Redirect>,
+}
+
+impl SimpleCommand {
+    fn is_empty(&self) -> bool {
+        self.assignments.is_empty() && self.args.is_empty() && self.redirects.is_empty()
+    }
+}
+
+type Pipeline = Vec<SimpleCommand>;
+
+/// A parsed command line, each pipeline runs depending on the status of the
+/// previous one
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandLine(Vec<(Op, Pipeline)>);
+
+impl CommandLine {
+    pub fn parse(input: &str) -> Result<Self, ShellError> {
+        let mut list = vec![];
+        let mut connector = Op::Semi;
+        let mut pipeline: Pipeline = vec![];
+        let mut command = SimpleCommand::default();
+
+        let mut tokens = tokenize(input)?.into_iter();
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::Word(word) => {
+                    match &word.assignment {
+                        Some(name) if command.args.is_empty() => {
+                            command.assignments.push((name.clone(), word.clone()))
+                        }
+                        _ => command.args.push(word),
+                    }
+                }
+                Token::Op(op @ (Op::In | Op::Out | Op::Append | Op::Err | Op::AppendErr)) => {
+                    let Some(Token::Word(target)) = tokens.next() else {
+                        return Err(ShellError::MissingRedirectTarget(op.as_str()));
+                    };
+                    command.redirects.push(match op {
+                        Op::In => Redirect::In(target),
+                        Op::Out => Redirect::Out(target, false),
+                        Op::Append => Redirect::Out(target, true),
+                        Op::Err => Redirect::Err(target, false),
+                        _ => Redirect::Err(target, true),
+                    });
+                }
+                Token::Op(Op::ErrToOut) => command.redirects.push(Redirect::ErrToOut),
+                Token::Op(op) => {
+                    if command.is_empty() {
+                        return Err(ShellError::UnexpectedToken(op.as_str()));
+                    }
+                    pipeline.push(mem::take(&mut command));
+                    if op != Op::Pipe {
+                        list.push((connector, mem::take(&mut pipeline)));
+                        connector = op;
+                    }
+                }
+            }
+        }
+
+        if command.is_empty() {
+            // A trailing `;` is fine, anything else needs a command after it
+            if !pipeline.is_empty() {
+                return Err(ShellError::UnexpectedToken(Op::Pipe.as_str()));
+            }
+            if connector != Op::Semi {
+                return Err(ShellError::UnexpectedToken(connector.as_str()));
+            }
+        } else {
+            pipeline.push(command);
+            list.push((connector, pipeline));
+        }
+
+        Ok(Self(list))
+    }
+}
+
+/// Quote an argument so that the shell reads it back as a single word
+pub fn quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || "-_./:=@+,%".contains(x))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Where the output of a command goes
+enum Target {
+    Inherit,
+    // Our stdout, for `2>&1` while stdout is inherited
+    Stdout,
+    File(File),
+    Pipe(PipeWriter),
+}
+
+impl Target {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Target::Inherit | Target::Stdout => Target::Stdout,
+            Target::File(x) => Target::File(x.try_clone()?),
+            Target::Pipe(x) => Target::Pipe(x.try_clone()?),
+        })
+    }
+
+    fn into_stdio(self) -> Stdio {
+        match self {
+            Target::Inherit => Stdio::inherit(),
+            Target::Stdout => io::stdout().into(),
+            Target::File(x) => x.into(),
+            Target::Pipe(x) => x.into(),
+        }
+    }
+
+    fn into_writer(self, inherited: fn() -> Box<dyn Write>) -> Box<dyn Write> {
+        match self {
+            Target::Inherit => inherited(),
+            Target::Stdout => Box::new(io::stdout()),
+            Target::File(x) => Box::new(x),
+            Target::Pipe(x) => Box::new(x),
+        }
+    }
+}
+
+/// Where the input of a command comes from
+enum Source {
+    Inherit,
+    File(File),
+    Pipe(PipeReader),
+}
+
+impl Source {
+    fn into_stdio(self) -> Stdio {
+        match self {
+            Source::Inherit => Stdio::inherit(),
+            Source::File(x) => x.into(),
+            Source::Pipe(x) => x.into(),
+        }
+    }
+}
+
+// Commands that work the same everywhere, without relying on the system
+const BUILTINS: &[&str] = &["cd", "echo", "exit", "export", "false", "true"];
+
+enum Stage {
+    Done(i32),
+    Running(String, process::Child),
+    // Runs once the stages after it are spawned
+    Builtin(String, Vec<String>, Target, Target),
+}
+
+/// The state a command line runs in, which builtins such as `cd` change
+pub struct Shell {
+    cwd:    PathBuf,
+    env:    BTreeMap<String, String>,
+    // What `den` refers to, so tasks run with the same executable
+    exe:    PathBuf,
+    exited: Option<i32>,
+}
+
+impl Shell {
+    pub fn new(cwd: PathBuf, env: BTreeMap<String, String>, exe: PathBuf) -> Self {
+        Self {
+            cwd,
+            env,
+            exe,
+            exited: None,
+        }
+    }
+
+    /// Run a whole command line, returns the status of the last command
+    pub fn run(&mut self, line: &CommandLine) -> i32 {
+        let mut status = 0;
+        for (connector, pipeline) in &line.0 {
+            let proceed = match connector {
+                Op::And => status == 0,
+                Op::Or => status != 0,
+                _ => true,
+            };
+            if !proceed {
+                continue;
+            }
+
+            status = self.run_pipeline(pipeline).unwrap_or_else(|e| {
+                eprintln!("den: {e}");
+                1
+            });
+            if let Some(status) = self.exited {
+                return status;
+            }
+        }
+        status
+    }
+
+    fn open(&self, word: &Word, append: bool) -> io::Result<File> {
+        let path = self.cwd.join(word.expand(&self.env));
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+    }
+
+    fn run_pipeline(&mut self, pipeline: &[SimpleCommand]) -> io::Result<i32> {
+        let mut stages = vec![];
+        let mut next_stdin = Source::Inherit;
+
+        for (i, command) in pipeline.iter().enumerate() {
+            let mut stdin = mem::replace(&mut next_stdin, Source::Inherit);
+            let mut stdout = if i + 1 < pipeline.len() {
+                let (reader, writer) = io::pipe()?;
+                next_stdin = Source::Pipe(reader);
+                Target::Pipe(writer)
+            } else {
+                Target::Inherit
+            };
+            let mut stderr = Target::Inherit;
+            for redirect in &command.redirects {
+                match redirect {
+                    Redirect::In(path) => {
+                        stdin = Source::File(File::open(self.cwd.join(path.expand(&self.env)))?)
+                    }
+                    Redirect::Out(path, append) => stdout = Target::File(self.open(path, *append)?),
+                    Redirect::Err(path, append) => stderr = Target::File(self.open(path, *append)?),
+                    Redirect::ErrToOut => stderr = stdout.try_clone()?,
+                }
+            }
+
+            let assignments: Vec<(String, String)> = command
+                .assignments
+                .iter()
+                .map(|(name, value)| {
+                    let value = value.expand(&self.env);
+                    (name.clone(), value[name.len() + 1..].to_string())
+                })
+                .collect();
+            let args: Vec<String> = command.args.iter().map(|x| x.expand(&self.env)).collect();
+
+            let Some((program, args)) = args.split_first() else {
+                // Assignments alone set shell variables
+                if pipeline.len() == 1 {
+                    self.env.extend(assignments);
+                }
+                stages.push(Stage::Done(0));
+                continue;
+            };
+
+            if BUILTINS.contains(&program.as_str()) {
+                stages.push(Stage::Builtin(
+                    program.clone(),
+                    args.to_vec(),
+                    stdout,
+                    stderr,
+                ));
+                continue;
+            }
+
+            let program_path = if program == "den" {
+                self.exe.clone()
+            } else if program.contains(['/', '\\']) {
+                self.cwd.join(program)
+            } else {
+                PathBuf::from(program)
+            };
+            let child = process::Command::new(program_path)
+                .args(args)
+                .current_dir(&self.cwd)
+                .envs(&self.env)
+                .envs(assignments)
+                .stdin(stdin.into_stdio())
+                .stdout(stdout.into_stdio())
+                .stderr(stderr.into_stdio())
+                .spawn();
+            match child {
+                Ok(child) => stages.push(Stage::Running(program.clone(), child)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    eprintln!("den: {program}: command not found");
+                    stages.push(Stage::Done(127));
+                }
+                Err(e) => {
+                    eprintln!("den: {program}: {e}");
+                    stages.push(Stage::Done(126));
+                }
+            }
+        }
+
+        // Everything after the builtins is spawned by now, so what they write
+        // into a pipe is read even when it does not fit in it
+        let stages: Vec<_> = stages
+            .into_iter()
+            .map(|stage| {
+                match stage {
+                    Stage::Builtin(program, args, stdout, stderr) => {
+                        let in_pipeline = pipeline.len() > 1;
+                        Stage::Done(self.run_builtin(&program, &args, in_pipeline, stdout, stderr))
+                    }
+                    stage => stage,
+                }
+            })
+            .collect();
+
+        let mut status = 0;
+        for stage in stages {
+            status = match stage {
+                Stage::Done(status) => status,
+                Stage::Builtin(..) => unreachable!("builtins ran already"),
+                Stage::Running(program, mut child) => {
+                    match child.wait() {
+                        Ok(x) => x.code().unwrap_or(1),
+                        Err(e) => {
+                            eprintln!("den: {program}: {e}");
+                            1
+                        }
+                    }
+                }
+            };
+        }
+        Ok(status)
+    }
+
+    /// Run one of [`BUILTINS`], returns its status. Builtins in a pipeline do
+    /// not change the shell, as if they ran in a subshell
+    fn run_builtin(
+        &mut self,
+        program: &str,
+        args: &[String],
+        in_pipeline: bool,
+        stdout: Target,
+        stderr: Target,
+    ) -> i32 {
+        let mut stdout = stdout.into_writer(|| Box::new(io::stdout()));
+        let mut stderr = stderr.into_writer(|| Box::new(io::stderr()));
+
+        let status = match program {
+            "true" => 0,
+            "false" => 1,
+            "echo" => {
+                let (newline, args) = match args.split_first() {
+                    Some((first, rest)) if first == "-n" => (false, rest),
+                    _ => (true, args),
+                };
+                let mut line = args.join(" ");
+                if newline {
+                    line.push('\n');
+                }
+                match stdout.write_all(line.as_bytes()) {
+                    Ok(()) => 0,
+                    // The reader of a pipe going away is not an error
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+                    Err(e) => {
+                        let _ = writeln!(stderr, "echo: {e}");
+                        1
+                    }
+                }
+            }
+            "cd" => {
+                let [dir] = args else {
+                    let _ = writeln!(stderr, "cd: expected a single directory");
+                    return 1;
+                };
+                let dir = self.cwd.join(dir);
+                if !dir.is_dir() {
+                    let _ = writeln!(stderr, "cd: {}: no such directory", dir.display());
+                    return 1;
+                }
+                if !in_pipeline {
+                    self.cwd = dir;
+                }
+                0
+            }
+            "export" => {
+                for arg in args {
+                    match arg.split_once('=') {
+                        Some((name, value)) if is_name(name) => {
+                            if !in_pipeline {
+                                self.env.insert(name.to_string(), value.to_string());
+                            }
+                        }
+                        _ => {
+                            let _ = writeln!(stderr, "export: {arg}: not a valid assignment");
+                            return 1;
+                        }
+                    }
+                }
+                0
+            }
+            "exit" => {
+                let status = match args {
+                    [] => 0,
+                    [status] => {
+                        match status.parse() {
+                            Ok(status) => status,
+                            Err(_) => {
+                                let _ =
+                                    writeln!(stderr, "exit: {status}: numeric argument required");
+                                2
+                            }
+                        }
+                    }
+                    _ => {
+                        let _ = writeln!(stderr, "exit: too many arguments");
+                        return 1;
+                    }
+                };
+                if !in_pipeline {
+                    self.exited = Some(status);
+                }
+                status
+            }
+            _ => unreachable!("{program} is not a builtin"),
+        };
+        let _ = stdout.flush();
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<Vec<String>> {
+        let env = BTreeMap::from([("NAME".to_string(), "a b".to_string())]);
+        CommandLine::parse(line)
+            .unwrap()
+            .0
+            .iter()
+            .flat_map(|(_, x)| x)
+            .map(|x| x.args.iter().map(|x| x.expand(&env)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn parse_quotes_and_variables() {
+        assert_eq!(
+            words(r#"echo 'a  b' "$NAME!" ${NAME}c \$NAME"#),
+            [["echo", "a  b", "a b!", "a bc", "$NAME"]]
+        );
+        assert_eq!(words(r#"echo "&&" x=1"#), [["echo", "&&", "x=1"]]);
+    }
+
+    #[test]
+    fn parse_lists_and_redirects() {
+        let line = CommandLine::parse("A=1 a 2>&1 | b > out && c || d; e;").unwrap();
+        let connectors: Vec<_> = line.0.iter().map(|(x, _)| *x).collect();
+        assert_eq!(connectors, [Op::Semi, Op::And, Op::Or, Op::Semi]);
+        assert_eq!(line.0[0].1.len(), 2);
+        assert_eq!(line.0[0].1[0].assignments.len(), 1);
+        assert_eq!(line.0[0].1[0].redirects, [Redirect::ErrToOut]);
+
+        assert_eq!(
+            CommandLine::parse("a &&"),
+            Err(ShellError::UnexpectedToken("&&"))
+        );
+        assert_eq!(
+            CommandLine::parse("| a"),
+            Err(ShellError::UnexpectedToken("|"))
+        );
+        assert_eq!(
+            CommandLine::parse("a >"),
+            Err(ShellError::MissingRedirectTarget(">"))
+        );
+        assert_eq!(
+            CommandLine::parse("echo 'a"),
+            Err(ShellError::UnterminatedQuote)
+        );
+    }
+
+    #[test]
+    fn parse_stderr_redirects() {
+        let redirects = |line: &str| -> Vec<String> {
+            let env = BTreeMap::new();
+            let line = CommandLine::parse(line).unwrap();
+            line.0[0].1[0]
+                .redirects
+                .iter()
+                .map(|x| {
+                    match x {
+                        Redirect::In(x) => format!("< {}", x.expand(&env)),
+                        Redirect::Out(x, append) => {
+                            format!("{} {}", if *append { ">>" } else { ">" }, x.expand(&env))
+                        }
+                        Redirect::Err(x, append) => {
+                            format!("{} {}", if *append { "2>>" } else { "2>" }, x.expand(&env))
+                        }
+                        Redirect::ErrToOut => "2>&1".to_string(),
+                    }
+                })
+                .collect()
+        };
+        assert_eq!(redirects("a 2>&1 > out"), ["2>&1", "> out"]);
+        assert_eq!(redirects("a 2> err 2>> log"), ["2> err", "2>> log"]);
+        // Only a bare `2` names stderr, a quoted one is an argument
+        assert_eq!(redirects(r#"echo "2"> out"#), ["> out"]);
+        assert_eq!(words(r#"echo "2"> out"#), [["echo", "2"]]);
+        assert_eq!(redirects(r#"echo \2> out"#), ["> out"]);
+        assert_eq!(words("echo x2> out"), [["echo", "x2"]]);
+        assert_eq!(
+            CommandLine::parse("a 2>&2"),
+            Err(ShellError::UnexpectedToken("2>&"))
+        );
+    }
+
+    #[test]
+    fn parse_assignments() {
+        let env = BTreeMap::new();
+        let line = CommandLine::parse(r#"A=1 B="x y" a C=2 "D"=3"#).unwrap();
+        let command = &line.0[0].1[0];
+        let assignments: Vec<_> = command
+            .assignments
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.expand(&env)))
+            .collect();
+        // The value keeps the `NAME=` it was written with
+        assert_eq!(
+            assignments,
+            [("A", "A=1".to_string()), ("B", "B=x y".to_string())]
+        );
+        // Past the program name they are plain arguments
+        assert_eq!(words(r#"A=1 B="x y" a C=2 "D"=3"#), [["a", "C=2", "D=3"]]);
+        assert_eq!(words("A=1"), [[] as [&str; 0]]);
+    }
+
+    #[test]
+    fn parse_trailing_operators() {
+        for (line, op) in [
+            ("a &&", "&&"),
+            ("a ||", "||"),
+            ("a |", "|"),
+            ("a | b |", "|"),
+        ] {
+            assert_eq!(
+                CommandLine::parse(line),
+                Err(ShellError::UnexpectedToken(op)),
+                "{line}"
+            );
+        }
+        assert!(CommandLine::parse("a;").is_ok());
+        assert_eq!(CommandLine::parse("a &"), Err(ShellError::Background));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn builtins_write_more_than_a_pipe_holds() {
+        use std::{fs, sync::mpsc, thread, time::Duration};
+
+        let dir = std::env::temp_dir().join(format!("den-shell-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let line = CommandLine::parse(&format!("echo {} | wc -c > out", "x".repeat(100_000)));
+        let mut shell = Shell::new(dir.clone(), BTreeMap::new(), PathBuf::new());
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || tx.send(shell.run(&line.unwrap())));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)), Ok(0));
+        let out = fs::read_to_string(dir.join("out")).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(out.trim(), "100001");
+    }
+
+    #[test]
+    fn quote_round_trips() {
+        for arg in ["plain", "", "a b", "it's", "$HOME"] {
+            assert_eq!(words(&format!("x {}", quote(arg))), [["x", arg]]);
+        }
+    }
+}
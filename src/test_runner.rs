@@ -0,0 +1,84 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+use den_core::config::Config;
+
+use crate::app::App;
+
+/// What `den test` looks for when the config does not say otherwise
+const DEFAULT_TEST_INCLUDE: &[&str] = &[
+    "**/*.test.{js,mjs,jsx,ts,mts,tsx}",
+    "**/*_test.{js,mjs,jsx,ts,mts,tsx}",
+];
+
+/// The test files to run, directories are searched like the project root
+fn collect_test_files(
+    config: &Config,
+    paths: &[PathBuf],
+) -> color_eyre::eyre::Result<Vec<PathBuf>> {
+    if paths.is_empty() {
+        return Ok(config.test.files(&config.dir, DEFAULT_TEST_INCLUDE)?);
+    }
+
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            files.extend(config.test.files(path, DEFAULT_TEST_INCLUDE)?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+pub(crate) fn display(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Run every test file on a fresh engine, a file fails when it throws or
+/// rejects
+pub async fn run_tests(config: &Config, paths: &[PathBuf]) -> color_eyre::eyre::Result<ExitCode> {
+    let files = collect_test_files(config, paths)?;
+    if files.is_empty() {
+        eprintln!("No test files found");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let started = Instant::now();
+    let mut failed = vec![];
+    for file in &files {
+        let mut app = App::new(config).await?;
+        let file_started = Instant::now();
+        let succeeded = app.run_file(file.clone()).await;
+        app.run_until_end().await;
+
+        let elapsed = file_started.elapsed();
+        if succeeded {
+            println!("ok      {} ({elapsed:.2?})", display(file));
+        } else {
+            println!("FAILED  {} ({elapsed:.2?})", display(file));
+            failed.push(file);
+        }
+    }
+
+    println!();
+    println!(
+        "{} passed, {} failed ({:.2?})",
+        files.len() - failed.len(),
+        failed.len(),
+        started.elapsed()
+    );
+    Ok(if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,100 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::repl::{dot_command, is_complete, ReplOutput};
+
+/// Split a script into REPL inputs. A dot-command always takes a single line,
+/// any other input accumulates lines until its brackets, strings and comments
+/// are closed
+pub fn split_inputs(script: &str) -> Vec<String> {
+    let mut inputs = vec![];
+    let mut buffer: Vec<&str> = vec![];
+
+    for line in script.lines() {
+        if buffer.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if dot_command(line).is_some() {
+                inputs.push(line.trim().to_string());
+                continue;
+            }
+        }
+
+        buffer.push(line);
+        let input = buffer.join("\n");
+        if is_complete(&input) {
+            inputs.push(input);
+            buffer.clear();
+        }
+    }
+
+    // Leave it to the evaluator to complain about an unfinished last input
+    if !buffer.is_empty() {
+        inputs.push(buffer.join("\n"));
+    }
+
+    inputs
+}
+
+/// A record of REPL inputs along with the outputs they produced, in order.
+///
+/// It is rendered with every input echoed after a `> ` prompt (`... ` for
+/// continuation lines), followed by its output lines as is and its error
+/// lines prefixed with `! `
+#[derive(Default, Debug)]
+pub struct Transcript {
+    entries: Vec<(String, Vec<ReplOutput>)>,
+}
+
+impl Transcript {
+    pub fn push(&mut self, input: String, output: Vec<ReplOutput>) {
+        self.entries.push((input, output));
+    }
+
+    /// Compare against an expected transcript, ignoring trailing whitespace
+    /// and line ending differences. Returns the mismatching lines as
+    /// `(line number, expected, actual)`
+    pub fn diff(&self, expected: &str) -> Vec<(usize, Option<String>, Option<String>)> {
+        let actual = self.to_string();
+        let normalize = |x: &str| {
+            let mut lines: Vec<String> = x.lines().map(|x| x.trim_end().to_string()).collect();
+            while lines.last().is_some_and(|x| x.is_empty()) {
+                lines.pop();
+            }
+            lines
+        };
+        let (expected, actual) = (normalize(expected), normalize(&actual));
+
+        (0..expected.len().max(actual.len()))
+            .filter_map(|i| {
+                let (expected, actual) = (expected.get(i), actual.get(i));
+                (expected != actual).then(|| (i + 1, expected.cloned(), actual.cloned()))
+            })
+            .collect()
+    }
+}
+
+impl Display for Transcript {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (input, output) in &self.entries {
+            for (i, line) in input.lines().enumerate() {
+                writeln!(f, "{}{line}", if i == 0 { "> " } else { "... " })?;
+            }
+            for x in output {
+                match x {
+                    ReplOutput::Stdout(x) => {
+                        for line in x.lines() {
+                            writeln!(f, "{line}")?;
+                        }
+                    }
+                    ReplOutput::Stderr(x) => {
+                        for line in x.lines() {
+                            writeln!(f, "! {line}")?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
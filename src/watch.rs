@@ -0,0 +1,183 @@
+use std::{
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use den_core::{config::Config, engine::Engine};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::{signal, sync::mpsc, time};
+
+use crate::app::App;
+
+// Editors tend to write a file in several steps, wait for them to settle
+const DEBOUNCE: Duration = Duration::from_millis(100);
+// How often newly imported modules are picked up while the script runs
+const SYNC_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches the modules a script loaded, along with any extra globs
+struct ModuleWatcher {
+    watcher:      RecommendedWatcher,
+    events:       mpsc::UnboundedReceiver<notify::Result<Event>>,
+    // Modules are watched through their parent directory so that editors that
+    // replace files on save do not drop the watch
+    watched_dirs: HashSet<PathBuf>,
+    modules:      HashSet<PathBuf>,
+    globs:        GlobSet,
+    cwd:          PathBuf,
+}
+
+impl ModuleWatcher {
+    fn new(globs: &[String]) -> color_eyre::eyre::Result<Self> {
+        let (tx, events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        })?;
+
+        let cwd = std::env::current_dir()?;
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs {
+            builder.add(Glob::new(glob)?);
+            let base = glob_base(glob);
+            let base = cwd.join(base);
+            if base.exists() {
+                watcher.watch(&base, RecursiveMode::Recursive)?;
+            }
+        }
+
+        Ok(Self {
+            watcher,
+            events,
+            watched_dirs: HashSet::new(),
+            modules: HashSet::new(),
+            globs: builder.build()?,
+            cwd,
+        })
+    }
+
+    /// Start watching modules the engine loaded since the last call
+    fn sync(&mut self, engine: &Engine) {
+        for module in engine.loaded_modules() {
+            if let Some(dir) = module.parent() {
+                if !self.watched_dirs.contains(dir)
+                    && self.watcher.watch(dir, RecursiveMode::NonRecursive).is_ok()
+                {
+                    self.watched_dirs.insert(dir.to_path_buf());
+                }
+            }
+            self.modules.insert(module);
+        }
+    }
+
+    fn is_relevant(&self, path: &Path) -> bool {
+        self.modules.contains(path)
+            || self.globs.is_match(path)
+            || path
+                .strip_prefix(&self.cwd)
+                .is_ok_and(|x| self.globs.is_match(x))
+    }
+
+    /// Wait until a watched file changes, returns its path
+    async fn changed(&mut self, engine: &Engine) -> PathBuf {
+        let mut interval = time::interval(SYNC_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => self.sync(engine),
+                Some(event) = self.events.recv() => {
+                    let Ok(event) = event else {
+                        continue;
+                    };
+                    if !matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) {
+                        continue;
+                    }
+                    let path = event.paths.into_iter().find(|x| self.is_relevant(x));
+                    let Some(path) = path else {
+                        continue;
+                    };
+
+                    time::sleep(DEBOUNCE).await;
+                    while self.events.try_recv().is_ok() {}
+                    return path;
+                }
+            }
+        }
+    }
+}
+
+/// The leading directories of a glob that contain no pattern, e.g. `src` for
+/// `src/**/*.ts`
+fn glob_base(glob: &str) -> PathBuf {
+    Path::new(glob)
+        .components()
+        .take_while(|x| {
+            match x {
+                Component::Normal(x) => !x.to_string_lossy().contains(['*', '?', '[', '{']),
+                _ => true,
+            }
+        })
+        .collect()
+}
+
+fn print_banner(message: &str) {
+    // Clear the screen and move the cursor back to the top left
+    print!("\x1B[2J\x1B[1;1H");
+    println!("Watcher: {message}");
+}
+
+/// Run `file`, and restart it on a fresh engine every time one of the modules
+/// it loaded, or a file matching `globs`, changes. Runs until Ctrl-C
+pub async fn run_watch(
+    file: PathBuf,
+    args: Vec<String>,
+    globs: Vec<String>,
+    config: &Config,
+) -> color_eyre::eyre::Result<ExitCode> {
+    print_banner(&format!("running {}", file.display()));
+
+    loop {
+        let mut app = App::new(config).await?;
+        app.engine.set_args(args.clone()).await?;
+        let mut watcher = ModuleWatcher::new(&globs)?;
+
+        let engine = app.engine.clone();
+
+        // A script that never yields, e.g. one looping forever, holds the
+        // task it runs on, so the engine is stopped from another one
+        let stopper = tokio::spawn({
+            let engine = engine.clone();
+            async move {
+                let changed = tokio::select! {
+                    x = watcher.changed(&engine) => Some(x),
+                    _ = signal::ctrl_c() => None,
+                };
+                engine.stop();
+                (watcher, changed)
+            }
+        });
+
+        app.run_file(file.clone()).await;
+        app.run_until_end().await;
+        if !engine.stop_token.is_cancelled() {
+            println!("Watcher: process finished, restarting on changes");
+        }
+
+        let (watcher, changed) = stopper.await?;
+        let Some(changed) = changed else {
+            return Ok(ExitCode::SUCCESS);
+        };
+
+        print_banner(&format!(
+            "{} changed, restarting {}",
+            changed
+                .strip_prefix(&watcher.cwd)
+                .unwrap_or(&changed)
+                .display(),
+            file.display()
+        ));
+    }
+}
@@ -0,0 +1,44 @@
+use std::{fs, path::Path, process::Command};
+
+// `den bundle` of `tests/bundle/main.ts` has to run like the modules it was
+// made of, with the module imported both statically and dynamically shared
+#[test]
+fn bundle_runs() {
+    let dir = std::env::temp_dir().join(format!("den-bundle-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let bundle = dir.join("bundle.js");
+    let den = |args: &[&Path]| {
+        Command::new(env!("CARGO_BIN_EXE_den"))
+            .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/bundle"))
+            .env("DEN_DIR", dir.join("den"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let bundled = den(&[
+        "bundle".as_ref(),
+        "main.ts".as_ref(),
+        "-o".as_ref(),
+        &bundle,
+    ]);
+    let output = den(&["run".as_ref(), &bundle]);
+    let source = fs::read_to_string(&bundle).unwrap_or_default();
+    let _ = fs::remove_dir_all(&dir);
+
+    assert!(
+        bundled.status.success(),
+        "{}",
+        String::from_utf8_lossy(&bundled.stderr)
+    );
+    assert!(!source.contains("./greet.ts"), "{source}");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "hello den (1), function\nhello later (2)\n"
+    );
+}
@@ -0,0 +1,6 @@
+let greeted: number = 0;
+
+export function greet(name: string): string {
+  greeted += 1;
+  return `hello ${name} (${greeted})`;
+}
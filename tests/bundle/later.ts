@@ -0,0 +1,3 @@
+import { greet } from "./greet.ts";
+
+export const later = () => greet("later");
@@ -0,0 +1,6 @@
+import { greet } from "./greet.ts";
+import { readToString } from "den:fs";
+
+console.log(greet("den"), typeof readToString);
+const { later } = await import("./later.ts");
+console.log(later());
@@ -0,0 +1,32 @@
+use std::{fs, process::Command};
+
+// Decorators inline helpers at the top of the module, the lines of an error
+// still have to be those of the source
+#[test]
+fn decorated_module_error_line() {
+    let dir = std::env::temp_dir().join(format!("den-error-locations-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("main.ts"),
+        concat!(
+            "function dec(t: any) { return t }\n",
+            "@dec class A {}\n",
+            "throw new Error(\"boom \" + A.name);\n",
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_den"))
+        .current_dir(&dir)
+        .env("DEN_DIR", dir.join("den"))
+        .arg("run")
+        .arg("main.ts")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let _ = fs::remove_dir_all(&dir);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("Error: boom A"), "{stderr}");
+    assert!(stderr.contains("(main.ts:3:7)"), "{stderr}");
+}
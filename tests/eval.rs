@@ -0,0 +1,76 @@
+use std::{
+    fs,
+    io::Write,
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// `den eval`, `den -e`, `--print` and `den -`, which runs a module read from
+// stdin
+fn den(args: &[&str], stdin: &str) -> Output {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("den-eval-{}-{run}", std::process::id()));
+    let mut child = Command::new(env!("CARGO_BIN_EXE_den"))
+        .env("DEN_DIR", &dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let _ = fs::remove_dir_all(&dir);
+    output
+}
+
+fn stdout(args: &[&str], stdin: &str) -> String {
+    let output = den(args, stdin);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn eval_subcommand() {
+    assert_eq!(
+        stdout(&["eval", "const a: number = 1; console.log(a + 1)"], ""),
+        "2\n"
+    );
+    assert_eq!(stdout(&["eval", "-p", "'x'.repeat(3)"], ""), "xxx\n");
+}
+
+#[test]
+fn eval_flag() {
+    assert_eq!(
+        stdout(&["-e", "console.log(typeof Den.args)"], ""),
+        "object\n"
+    );
+    assert_eq!(stdout(&["-e", "1 + 1"], ""), "");
+    assert_eq!(stdout(&["-e", "1 + 1", "--print"], ""), "2\n");
+    assert_eq!(stdout(&["-p", "-e", "await Promise.resolve(3)"], ""), "3\n");
+
+    let output = den(&["-e", "throw new Error('boom')"], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("boom"));
+}
+
+#[test]
+fn module_from_stdin() {
+    let module = concat!(
+        "import { readToString } from 'den:fs';\n",
+        "const n: number = await Promise.resolve(typeof readToString);\n",
+        "console.log(`${n} ${Den.args.join()}`);\n",
+    );
+    assert_eq!(stdout(&["-", "a", "b"], module), "function a,b\n");
+    assert_eq!(stdout(&["run", "-"], module), "function \n");
+}
@@ -0,0 +1,55 @@
+use std::{fs, path::Path, process::Command};
+
+// `den info` of `tests/info/<fixture>/main.ts` has to print
+// `tests/info/<fixture>.out`, or `tests/info/<fixture>.json` with `--json`
+fn info(fixture: &str, args: &[&str]) -> String {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/info");
+    let cache = std::env::temp_dir().join(format!("den-info-{}", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_den"))
+        .current_dir(dir.join(fixture))
+        .env("DEN_DIR", &cache)
+        .arg("info")
+        .args(args)
+        .arg("main.ts")
+        .output()
+        .unwrap();
+    let _ = fs::remove_dir_all(&cache);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .replace(&cache.display().to_string(), "<cache>")
+}
+
+fn expected(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/info")
+        .join(name);
+    fs::read_to_string(path).unwrap()
+}
+
+// A graph with a cycle, a module imported twice, a missing module and one
+// that does not parse
+#[test]
+fn text() {
+    assert_eq!(info("graph", &[]), expected("graph.out"));
+}
+
+#[test]
+fn json() {
+    assert_eq!(info("graph", &["--json"]), expected("graph.json"));
+}
+
+#[test]
+fn unresolved_imports() {
+    assert_eq!(info("missing", &[]), expected("missing.out"));
+}
+
+#[test]
+fn cycle() {
+    assert_eq!(info("cycle", &[]), expected("cycle.out"));
+}
@@ -0,0 +1 @@
+import "./main.ts"; export const b = 1; export const later = () => import("./a.ts");
@@ -0,0 +1,6 @@
+import { a } from "./a.ts";
+import { b } from "./b.ts";
+import "./missing.ts";
+import "./broken.ts";
+import * as fs from "den:fs";
+console.log(a, b, fs);
@@ -0,0 +1,3 @@
+import { x } from "./nowhere.ts";
+import { y } from "nowhere";
+console.log(x, y);
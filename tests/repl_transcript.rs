@@ -0,0 +1,32 @@
+use std::{fs, path::Path, process::Command};
+
+// Every `tests/repl/<name>.txt` script is fed through `den --repl-script` and
+// has to reproduce the transcript recorded in `tests/repl/<name>.out`
+#[test]
+fn repl_transcripts() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/repl");
+    let mut failed = vec![];
+
+    for entry in fs::read_dir(&dir).unwrap() {
+        let script = entry.unwrap().path();
+        if script.extension().is_none_or(|x| x != "txt") {
+            continue;
+        }
+
+        let output = Command::new(env!("CARGO_BIN_EXE_den"))
+            .current_dir(&dir)
+            .arg("--repl-script")
+            .arg(&script)
+            .arg("--repl-expect")
+            .arg(script.with_extension("out"))
+            .output()
+            .unwrap();
+
+        if !output.status.success() {
+            eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+            failed.push(script);
+        }
+    }
+
+    assert!(failed.is_empty(), "transcripts mismatched: {failed:?}");
+}
@@ -0,0 +1,105 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::mpsc,
+    time::Duration,
+};
+
+/// A `den run --watch` of `main.ts` among `files` in a directory of its own,
+/// with its output lines sent through a channel
+struct Watch {
+    dir:   PathBuf,
+    child: Child,
+    lines: mpsc::Receiver<String>,
+}
+
+impl Watch {
+    fn start(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("den-watch-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_den"))
+            .current_dir(&dir)
+            .env("DEN_DIR", dir.join("den"))
+            .args(["run", "--watch", "main.ts"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let _ = tx.send(line);
+            }
+        });
+
+        Self { dir, child, lines }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Wait for a line containing `text`, failing after a while
+    fn expect(&self, text: &str) {
+        loop {
+            match self.lines.recv_timeout(Duration::from_secs(10)) {
+                Ok(line) if line.contains(text) => return,
+                Ok(_) => continue,
+                Err(_) => panic!("den never printed {text:?}"),
+            }
+        }
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn write(path: &Path, contents: &str) {
+    // Leave the watcher time to pick the modules up
+    std::thread::sleep(Duration::from_millis(1000));
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn restarts_a_script_that_never_yields() {
+    let watch = Watch::start(
+        "busy",
+        &[("main.ts", "console.log('first'); while (true) {}")],
+    );
+    watch.expect("first");
+
+    write(
+        &watch.path("main.ts"),
+        "console.log('second'); while (true) {}",
+    );
+    watch.expect("main.ts changed, restarting main.ts");
+    watch.expect("second");
+}
+
+#[test]
+fn restarts_a_finished_script_on_imported_module_changes() {
+    let watch = Watch::start(
+        "finished",
+        &[
+            ("main.ts", "import { x } from './dep.ts'; console.log(x)"),
+            ("dep.ts", "export const x = 'first'"),
+        ],
+    );
+    watch.expect("first");
+    watch.expect("process finished");
+
+    write(&watch.path("dep.ts"), "export const x = 'second'");
+    watch.expect("dep.ts changed, restarting main.ts");
+    watch.expect("second");
+}